@@ -0,0 +1,280 @@
+//! Headless command line mode for scripting PAM configuration.
+//!
+//! When any of the flags below except `--user` are present the GTK
+//! application is not started. Enrollment itself still requires interactive
+//! finger scans and is only available from the GUI; `--user` on its own opens
+//! the GUI for another account.
+
+use crate::config;
+use crate::core::util::Finger;
+use crate::pam::helper::{PamHelper, PamOpError};
+use crate::pam::switch::{services, PamService};
+use clap::{ArgAction, Parser};
+use log::{warn, LevelFilter};
+use std::path::PathBuf;
+
+/// Command line interface definition.
+#[derive(Debug, Parser)]
+#[command(
+    name = "xfprintd-gui",
+    disable_version_flag = true,
+    about = "GTK4 GUI to manage fingerprint enrollment and PAM integration",
+    after_help = "Without any options the graphical interface is started. \
+                  Fingerprint enrollment requires interactive scanning and is only available in the GUI."
+)]
+pub struct Cli {
+    /// Print version and build information
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// Enable fingerprint authentication for a PAM service without starting the GUI;
+    /// the services are listed by `xfprintd-gui-helper services`
+    #[arg(
+        long,
+        value_name = "SERVICE",
+        value_parser = parse_service,
+        conflicts_with_all = ["disable", "status"]
+    )]
+    pub enable: Option<PamService>,
+
+    /// Disable fingerprint authentication for a PAM service without starting the GUI
+    #[arg(
+        long,
+        value_name = "SERVICE",
+        value_parser = parse_service,
+        conflicts_with = "status"
+    )]
+    pub disable: Option<PamService>,
+
+    /// Print PAM configuration and enrollment status without starting the GUI
+    #[arg(long)]
+    pub status: bool,
+
+    /// Check fprintd, devices, helper, pkexec and PAM files and report problems
+    #[arg(long, conflicts_with_all = ["enable", "disable", "status", "version"])]
+    pub doctor: bool,
+
+    /// Manage the fingerprints of another user (requires admin authorization)
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["enable", "disable", "version"])]
+    pub user: Option<String>,
+
+    /// Start the GUI without warning about unsupported distributions
+    #[arg(long)]
+    pub force: bool,
+
+    /// Run on unsupported distributions with a dismissible banner instead of the warning dialog,
+    /// even when the configuration refuses to run
+    #[arg(
+        long,
+        env = "XFPRINTD_GUI_ALLOW_UNSUPPORTED",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub allow_unsupported: bool,
+
+    /// Log more details (-v for debug, -vv for trace); overrides RUST_LOG
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only log errors; overrides RUST_LOG
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Also append log output to this file
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+/// Resolve a `--enable`/`--disable` value against the services the helper has patches for.
+fn parse_service(name: &str) -> Result<PamService, String> {
+    let listed = services::list().map_err(|e| format!("could not list the PAM services: {}", e))?;
+
+    services::find(&listed, name).cloned().ok_or_else(|| {
+        let names: Vec<&str> = listed.iter().map(|service| service.name.as_str()).collect();
+        if names.is_empty() {
+            "the helper has no PAM patches installed".to_string()
+        } else {
+            format!("possible values: {}", names.join(", "))
+        }
+    })
+}
+
+impl Cli {
+    /// Whether a headless action was requested.
+    pub fn is_headless(&self) -> bool {
+        self.version
+            || self.doctor
+            || self.enable.is_some()
+            || self.disable.is_some()
+            || self.status
+    }
+
+    /// Log level requested with `-v`/`-q`, if any.
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(LevelFilter::Error),
+            (false, 0) => None,
+            (false, 1) => Some(LevelFilter::Debug),
+            (false, _) => Some(LevelFilter::Trace),
+        }
+    }
+}
+
+/// Run the requested headless action and return the process exit code.
+pub fn run(cli: &Cli) -> i32 {
+    if cli.version {
+        print_version();
+        return 0;
+    }
+
+    if cli.doctor {
+        return crate::doctor::run();
+    }
+
+    if let Some(service) = &cli.enable {
+        return toggle(service, true);
+    }
+
+    if let Some(service) = &cli.disable {
+        return toggle(service, false);
+    }
+
+    print_status(cli.user.as_deref().unwrap_or_default())
+}
+
+/// Print application name, version, ID and the linked GTK version.
+fn print_version() {
+    println!("{} {}", config::app_info::NAME, config::app_info::VERSION);
+    println!("Application ID: {}", config::app_info::ID);
+    println!(
+        "GTK: {}.{}.{}",
+        gtk4::major_version(),
+        gtk4::minor_version(),
+        gtk4::micro_version()
+    );
+    println!("Helper: {}", config::helper::BINARY_PATH);
+}
+
+/// Apply or remove the PAM configuration for a service.
+fn toggle(service: &PamService, enable: bool) -> i32 {
+    let result = if enable {
+        PamHelper::apply_configuration(&service.path)
+    } else {
+        PamHelper::remove_configuration(&service.path)
+    };
+
+    match result {
+        Ok(()) => {
+            println!(
+                "{} fingerprint authentication for {} ({})",
+                if enable { "Enabled" } else { "Disabled" },
+                service.name,
+                service.path
+            );
+            0
+        }
+        Err(PamOpError::UserCancelled) => {
+            eprintln!("Authentication cancelled, no changes were made");
+            1
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to {} fingerprint authentication for {}: {}",
+                if enable { "enable" } else { "disable" },
+                service.name,
+                e
+            );
+            1
+        }
+    }
+}
+
+/// Print PAM configuration status and the enrolled fingerprints of `username`.
+fn print_status(username: &str) -> i32 {
+    let listed = match services::list() {
+        Ok(listed) => listed,
+        Err(e) => {
+            eprintln!("Failed to list the PAM services: {}", e);
+            return 1;
+        }
+    };
+    let paths: Vec<&str> = listed.iter().map(|service| service.path.as_str()).collect();
+    let configured = PamHelper::check_configurations(&paths);
+
+    let width = listed
+        .iter()
+        .map(|service| service.name.len() + 2)
+        .max()
+        .unwrap_or_default()
+        .max(8);
+    let state = |enabled: bool| if enabled { "enabled" } else { "disabled" };
+    for (service, enabled) in listed.iter().zip(configured) {
+        println!(
+            "{:<width$}{:<10}{}",
+            service.name,
+            state(enabled),
+            service.path,
+            width = width
+        );
+    }
+
+    let rt = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("Failed to build Tokio runtime: {}", e);
+            return 1;
+        }
+    };
+
+    // Listing claims nothing, so an interrupted scan only needs to stop waiting
+    let scanned = rt.block_on(async {
+        tokio::select! {
+            enrolled = crate::fingerprints::scan_enrolled_fingerprints(username) => Some(enrolled),
+            _ = shutdown_signal() => None,
+        }
+    });
+    let Some(scanned) = scanned else {
+        eprintln!("Interrupted");
+        return 130;
+    };
+    let scanned = match scanned {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            eprintln!("Failed to list the enrolled fingerprints: {}", e);
+            return 1;
+        }
+    };
+
+    let mut enrolled: Vec<Finger> = scanned.into_iter().collect();
+    enrolled.sort();
+
+    if enrolled.is_empty() {
+        println!("enrolled: none");
+    } else {
+        let names: Vec<&str> = enrolled.iter().map(|f| f.to_dbus_name()).collect();
+        println!("enrolled: {}", names.join(", "));
+    }
+
+    0
+}
+
+/// Resolve once SIGINT or SIGTERM is received.
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
@@ -1,24 +1,74 @@
 //! Centralized configuration and constants for the application.
+//!
+//! Feedback colors come from the `@define-color feedback_*` entries in the
+//! bundled `style-dark.css` or `style-light.css`, following the desktop color
+//! scheme, and can be overridden for both from
+//! `$XDG_CONFIG_HOME/xfprintd-gui/config.toml`:
+//!
+//! ```toml
+//! [colors]
+//! progress = "#a277ff"
+//! error = "#ff4d6d"
+//!
+//! [general]
+//! distribution_check = "warn" # or "off" / "strict"
+//! login_notice_shown = false   # set once the login fallback notice was shown
+//! left_handed = false          # list the left hand first, mirrored
+//!
+//! [enrollment]
+//! timeout = 0                  # seconds without a scan before enrollment stops, 0 waits
+//! retry_advice_after = 5       # rejected scans in a row before advice is shown
+//! ```
+//!
+//! `left_handed` and the `[enrollment]` values are also editable in the Preferences window.
+
+use gtk4::gio;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 /// Color scheme for UI feedback messages.
+#[derive(Debug, Clone)]
 pub struct ColorScheme {
-    pub progress: &'static str,
-    pub warning: &'static str,
-    pub error: &'static str,
-    pub success: &'static str,
-    pub neutral: &'static str,
-    pub process: &'static str,
-}
-
-/// Default color scheme for enrollment feedback.
-pub const COLORS: ColorScheme = ColorScheme {
-    progress: "#a277ff", // Purple - successful scan/progress
-    warning: "#ff6ac1",  // Pink - retry/adjustment needed
-    error: "#ff4d6d",    // Red - failure/error
-    success: "#a277ff",  // Purple - completion
-    neutral: "#8a8f98",  // Gray - neutral/fallback
-    process: "#5ea2ff",  // Blue - processing/neutral status
-};
+    pub progress: String,
+    pub warning: String,
+    pub error: String,
+    pub success: String,
+    pub neutral: String,
+    pub process: String,
+}
+
+impl Default for ColorScheme {
+    /// Default color scheme for enrollment feedback on dark themes.
+    fn default() -> Self {
+        Self {
+            progress: "#a277ff".to_string(), // Purple - successful scan/progress
+            warning: "#ff6ac1".to_string(),  // Pink - retry/adjustment needed
+            error: "#ff4d6d".to_string(),    // Red - failure/error
+            success: "#a277ff".to_string(),  // Purple - completion
+            neutral: "#8a8f98".to_string(),  // Gray - neutral/fallback
+            process: "#5ea2ff".to_string(),  // Blue - processing/neutral status
+        }
+    }
+}
+
+impl ColorScheme {
+    /// Default color scheme for enrollment feedback on light themes, darker for contrast.
+    fn light() -> Self {
+        Self {
+            progress: "#6a3fd1".to_string(),
+            warning: "#b3246e".to_string(),
+            error: "#c8102e".to_string(),
+            success: "#6a3fd1".to_string(),
+            neutral: "#5c6169".to_string(),
+            process: "#1f5fbf".to_string(),
+        }
+    }
+}
 
 /// Application information constants.
 pub mod app_info {
@@ -32,7 +82,410 @@ pub mod helper {
     pub const BINARY_PATH: &str = "/opt/xfprintd-gui/xfprintd-gui-helper";
 }
 
-/// Get color scheme for UI feedback.
+/// Enrollment behavior.
+pub mod enrollment {
+    /// Rejected scans in a row before advice on fixing the problem is shown, unless configured.
+    pub const RETRY_ADVICE_AFTER: usize = 5;
+
+    /// Longest enrollment timeout offered in the preferences, in seconds.
+    pub const MAX_TIMEOUT_SECS: u64 = 600;
+
+    /// Rejected scans in a row (retry, not centered, too short, too fast) before enrollment gives up.
+    pub const MAX_CONSECUTIVE_RETRIES: usize = 8;
+
+    /// Rejected scans per captured scan from which re-enrolling is recommended afterwards.
+    pub const REENROLL_RETRY_RATIO: usize = 1;
+}
+
+/// User configuration file contents.
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    colors: ColorOverrides,
+    #[serde(default)]
+    general: GeneralConfig,
+    #[serde(default)]
+    enrollment: EnrollmentConfig,
+}
+
+/// General application preferences.
+#[derive(Debug, Default, Deserialize)]
+struct GeneralConfig {
+    #[serde(default)]
+    distribution_check: DistributionCheck,
+    #[serde(default)]
+    login_notice_shown: bool,
+    #[serde(default)]
+    left_handed: bool,
+}
+
+/// Enrollment preferences.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct EnrollmentConfig {
+    /// Seconds without a status update before enrollment stops; 0 waits indefinitely.
+    timeout: u64,
+    retry_advice_after: usize,
+}
+
+impl Default for EnrollmentConfig {
+    fn default() -> Self {
+        Self {
+            timeout: 0,
+            retry_advice_after: enrollment::RETRY_ADVICE_AFTER,
+        }
+    }
+}
+
+/// User-tunable behavior, loaded at startup and changed from the Preferences window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// Seconds without a status update before enrollment stops; 0 waits indefinitely.
+    pub enroll_timeout_secs: u64,
+    /// Rejected scans in a row before advice on fixing the problem is shown.
+    pub retry_advice_after: usize,
+    /// List the left hand first and mirror the finger order.
+    pub left_handed: bool,
+}
+
+impl Settings {
+    /// Settings from the user configuration file, with out-of-range values clamped.
+    pub fn load() -> Self {
+        let loaded = &USER_CONFIG.enrollment;
+        Self {
+            enroll_timeout_secs: loaded.timeout.min(enrollment::MAX_TIMEOUT_SECS),
+            retry_advice_after: loaded
+                .retry_advice_after
+                .clamp(1, enrollment::MAX_CONSECUTIVE_RETRIES - 1),
+            left_handed: USER_CONFIG.general.left_handed,
+        }
+    }
+
+    /// Enrollment timeout, `None` to wait indefinitely.
+    pub fn enroll_timeout(&self) -> Option<Duration> {
+        (self.enroll_timeout_secs > 0).then_some(Duration::from_secs(self.enroll_timeout_secs))
+    }
+
+    /// Persist the settings in the user configuration file, keeping every other setting.
+    pub fn save(&self) -> io::Result<()> {
+        set_options(
+            "general",
+            &[("left_handed", toml::Value::Boolean(self.left_handed))],
+        )?;
+        set_options(
+            "enrollment",
+            &[
+                (
+                    "timeout",
+                    toml::Value::Integer(self.enroll_timeout_secs as i64),
+                ),
+                (
+                    "retry_advice_after",
+                    toml::Value::Integer(self.retry_advice_after as i64),
+                ),
+            ],
+        )
+    }
+}
+
+/// How to react when running on a distribution other than XeroLinux.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistributionCheck {
+    /// Warn once per launch and let the user continue.
+    #[default]
+    Warn,
+    /// Skip the check entirely.
+    Off,
+    /// Refuse to run.
+    Strict,
+}
+
+/// Optional color overrides from the bundled stylesheet or the user configuration file.
+#[derive(Debug, Default, Clone, Deserialize)]
+struct ColorOverrides {
+    progress: Option<String>,
+    warning: Option<String>,
+    error: Option<String>,
+    success: Option<String>,
+    neutral: Option<String>,
+    process: Option<String>,
+}
+
+/// Resource path of the stylesheet with the feedback palette for dark themes.
+pub const DARK_STYLE_RESOURCE: &str = "/xyz/xerolinux/xfprintd_gui/css/style-dark.css";
+
+/// Resource path of the stylesheet with the feedback palette for light themes.
+pub const LIGHT_STYLE_RESOURCE: &str = "/xyz/xerolinux/xfprintd_gui/css/style-light.css";
+
+/// Prefix of the `@define-color` names making up the feedback palette.
+const CSS_COLOR_PREFIX: &str = "feedback_";
+
+/// User configuration, loaded once on first use.
+static USER_CONFIG: Lazy<UserConfig> = Lazy::new(load_user_config);
+
+/// Whether a dark theme is in use; set by the theme code when the desktop scheme is known.
+static DARK_THEME: AtomicBool = AtomicBool::new(true);
+
+/// Runtime color schemes, loaded once from the stylesheets and user configuration.
+static DARK_COLORS: Lazy<ColorScheme> =
+    Lazy::new(|| load_color_scheme(ColorScheme::default(), DARK_STYLE_RESOURCE));
+static LIGHT_COLORS: Lazy<ColorScheme> =
+    Lazy::new(|| load_color_scheme(ColorScheme::light(), LIGHT_STYLE_RESOURCE));
+
+/// Get color scheme for UI feedback, matching the current theme.
 pub fn colors() -> &'static ColorScheme {
-    &COLORS
+    if DARK_THEME.load(Ordering::Relaxed) {
+        &DARK_COLORS
+    } else {
+        &LIGHT_COLORS
+    }
+}
+
+/// Record whether a dark theme is in use, selecting the matching feedback colors.
+pub fn set_dark_theme(dark: bool) {
+    DARK_THEME.store(dark, Ordering::Relaxed);
+}
+
+/// Load both color schemes so configuration problems are reported at startup.
+pub fn load_colors() {
+    Lazy::force(&DARK_COLORS);
+    Lazy::force(&LIGHT_COLORS);
+}
+
+/// Get the configured distribution check mode.
+pub fn distribution_check() -> DistributionCheck {
+    USER_CONFIG.general.distribution_check
+}
+
+/// Whether the login fallback notice was shown in an earlier session.
+pub fn login_notice_shown() -> bool {
+    USER_CONFIG.general.login_notice_shown
+}
+
+/// Persist `distribution_check = "off"` in the user configuration file,
+/// keeping every other setting.
+pub fn disable_distribution_check() -> io::Result<()> {
+    set_general_option("distribution_check", toml::Value::String("off".to_string()))
+}
+
+/// Persist `login_notice_shown = true` so the login fallback notice is not shown again.
+pub fn mark_login_notice_shown() -> io::Result<()> {
+    set_general_option("login_notice_shown", toml::Value::Boolean(true))
+}
+
+/// Set a `[general]` option in the user configuration file, keeping every other setting.
+fn set_general_option(key: &str, value: toml::Value) -> io::Result<()> {
+    set_options("general", &[(key, value)])
+}
+
+/// Set options of the `section` table in the user configuration file, keeping every
+/// other setting.
+fn set_options(section: &str, options: &[(&str, toml::Value)]) -> io::Result<()> {
+    let path = user_config_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No configuration directory"))?;
+
+    let mut table = match std::fs::read_to_string(&path) {
+        Ok(content) => content
+            .parse::<toml::Table>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e),
+    };
+
+    let entry = table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let toml::Value::Table(entry) = entry else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("'{}' is not a table", section),
+        ));
+    };
+    for (key, value) in options {
+        entry.insert(key.to_string(), value.clone());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content = toml::to_string(&table).map_err(io::Error::other)?;
+    std::fs::write(&path, content)?;
+    for (key, value) in options {
+        info!("Set {}.{} = {} in {}", section, key, value, path.display());
+    }
+    Ok(())
+}
+
+/// Path of the user configuration file.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("xfprintd-gui").join("config.toml"))
+}
+
+/// Main window size remembered between launches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Path of the file remembering the main window size.
+fn window_geometry_path() -> Option<PathBuf> {
+    user_config_path().map(|path| path.with_file_name("window.toml"))
+}
+
+/// Load the window size saved by the previous session, if any.
+pub fn load_window_geometry() -> Option<WindowGeometry> {
+    let path = window_geometry_path()?;
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    match toml::from_str(&content) {
+        Ok(geometry) => Some(geometry),
+        Err(e) => {
+            warn!("Ignoring invalid window geometry {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Save the window size for the next launch.
+pub fn save_window_geometry(geometry: WindowGeometry) -> io::Result<()> {
+    let path = window_geometry_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No configuration directory"))?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content = toml::to_string(&geometry).map_err(io::Error::other)?;
+    std::fs::write(&path, content)?;
+    info!(
+        "Saved window size {}x{} to {}",
+        geometry.width,
+        geometry.height,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Load user configuration, falling back to defaults when missing or invalid.
+fn load_user_config() -> UserConfig {
+    let Some(path) = user_config_path() else {
+        return UserConfig::default();
+    };
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return UserConfig::default(),
+    };
+
+    match toml::from_str(&content) {
+        Ok(config) => {
+            info!("Loaded user configuration from {}", path.display());
+            config
+        }
+        Err(e) => {
+            warn!("Ignoring invalid configuration {}: {}", path.display(), e);
+            UserConfig::default()
+        }
+    }
+}
+
+/// Build color scheme from `defaults`, the palette of the stylesheet at `resource` and
+/// user overrides.
+/// Requires the gresources to be registered for the stylesheet to be found.
+fn load_color_scheme(defaults: ColorScheme, resource: &str) -> ColorScheme {
+    let mut scheme = defaults;
+
+    if let Some(palette) = load_css_palette(resource) {
+        let name = resource.rsplit('/').next().unwrap_or(resource);
+        apply_overrides(&mut scheme, palette, name);
+    }
+    apply_overrides(&mut scheme, USER_CONFIG.colors.clone(), "configuration");
+
+    scheme
+}
+
+/// Read the feedback palette from the bundled stylesheet at `resource`.
+fn load_css_palette(resource: &str) -> Option<ColorOverrides> {
+    let data = match gio::resources_lookup_data(resource, gio::ResourceLookupFlags::NONE) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Stylesheet unavailable, using default colors: {}", e);
+            return None;
+        }
+    };
+
+    match std::str::from_utf8(&data) {
+        Ok(css) => Some(parse_css_palette(css)),
+        Err(e) => {
+            warn!("Stylesheet is not valid UTF-8, using default colors: {}", e);
+            None
+        }
+    }
+}
+
+/// Collect `@define-color feedback_<name> <value>;` entries from CSS.
+fn parse_css_palette(css: &str) -> ColorOverrides {
+    let mut palette = ColorOverrides::default();
+
+    for line in css.lines() {
+        let Some(rest) = line.trim().strip_prefix("@define-color") else {
+            continue;
+        };
+        let mut parts = rest.trim().trim_end_matches(';').split_whitespace();
+        let (Some(name), Some(value)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+
+        let slot = match name.strip_prefix(CSS_COLOR_PREFIX) {
+            Some("progress") => &mut palette.progress,
+            Some("warning") => &mut palette.warning,
+            Some("error") => &mut palette.error,
+            Some("success") => &mut palette.success,
+            Some("neutral") => &mut palette.neutral,
+            Some("process") => &mut palette.process,
+            _ => continue,
+        };
+        *slot = Some(value.to_string());
+    }
+
+    palette
+}
+
+/// Apply every valid override to the color scheme.
+fn apply_overrides(scheme: &mut ColorScheme, overrides: ColorOverrides, source: &str) {
+    apply_color(&mut scheme.progress, overrides.progress, "progress", source);
+    apply_color(&mut scheme.warning, overrides.warning, "warning", source);
+    apply_color(&mut scheme.error, overrides.error, "error", source);
+    apply_color(&mut scheme.success, overrides.success, "success", source);
+    apply_color(&mut scheme.neutral, overrides.neutral, "neutral", source);
+    apply_color(&mut scheme.process, overrides.process, "process", source);
+}
+
+/// Replace a color with its override if it is a valid hex color.
+fn apply_color(target: &mut String, value: Option<String>, name: &str, source: &str) {
+    let Some(value) = value else {
+        return;
+    };
+
+    if is_hex_color(&value) {
+        *target = value;
+    } else {
+        warn!(
+            "Invalid {} color '{}' in {}, keeping {}",
+            name, value, source, target
+        );
+    }
+}
+
+/// Check for `#rgb`, `#rrggbb` or `#rrggbbaa` hex colors.
+fn is_hex_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .map(|hex| matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .unwrap_or(false)
 }
@@ -1,95 +1,220 @@
 //! Shared context structures for fingerprint operations.
 
+use crate::config::Settings;
+use crate::core::util::Finger;
+use crate::fingerprints::{self, DeviceEnrollment};
+use crate::ui::toast::Toast;
 use gtk4::prelude::*;
-use gtk4::{Button, FlowBox, Label, Stack, Switch};
-use std::cell::RefCell;
-use std::collections::HashSet;
+use gtk4::{Box as GtkBox, Button, DropDown, FlowBox, Image, Label, Spinner, Stack, Switch};
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use zbus::zvariant::OwnedObjectPath;
 
 /// Main context for fingerprint operations, unifying enrollment and removal contexts.
 #[derive(Clone)]
 pub struct FingerprintContext {
     pub rt: Arc<Runtime>,
     pub ui: UiComponents,
-    pub selected_finger: Rc<RefCell<Option<String>>>,
-    pub enrolled: Rc<RefCell<HashSet<String>>>,
+    pub selected_finger: Rc<RefCell<Option<Finger>>>,
+    pub selected_device: Rc<RefCell<Option<OwnedObjectPath>>>,
+    pub devices: Rc<RefCell<Vec<(OwnedObjectPath, String)>>>,
+    pub enrolled: Rc<RefCell<HashMap<OwnedObjectPath, HashSet<Finger>>>>,
+    /// User whose fingerprints are managed; empty for the current user.
+    username: Rc<RefCell<String>>,
+    /// Set until the initial PAM check and enrollment scan complete; PAM switches ignore
+    /// toggles meanwhile.
+    pub initializing: Rc<Cell<bool>>,
+    /// Whether pam_fprintd.so is installed; without it only enabled switches can be toggled,
+    /// so a configuration referencing the missing module can still be removed.
+    pub pam_module_installed: Rc<Cell<bool>>,
+    /// Results of the startup checks that finished while the other is still running.
+    startup: Rc<RefCell<StartupState>>,
+    pub toast: Toast,
+    /// Held for the whole of an enroll, delete or scan so only one claims the device at a time.
+    pub operation_lock: Arc<Mutex<()>>,
+    /// User-tunable behavior, changed from the Preferences window.
+    settings: Rc<Cell<Settings>>,
+    /// Set while an enrollment refresh runs and for a short while after it.
+    pub refresh_busy: Rc<Cell<bool>>,
+    /// Set when a refresh was requested while busy; it runs once the current one is done.
+    pub refresh_pending: Rc<Cell<bool>>,
+    /// Set while an enrollment or deletion runs; navigation and finger actions are disabled.
+    busy: Rc<Cell<bool>>,
+}
+
+/// Startup check results collected until both are known.
+#[derive(Debug, Default)]
+struct StartupState {
+    /// Set once the PAM switches show the configuration found at startup.
+    pam_checked: bool,
+    has_enrolled: Option<bool>,
 }
 
 /// UI components grouped by functionality.
 #[derive(Clone)]
 pub struct UiComponents {
     pub flow: FlowBox,
+    /// Shown over the finger grid until the initial scan finds a reader.
+    pub scan_status: ScanStatus,
+    pub device_dropdown: DropDown,
     pub stack: Stack,
     pub switches: PamSwitches,
     pub labels: FingerprintLabels,
     pub buttons: FingerprintButtons,
+    pub navigation: NavigationButtons,
+}
+
+/// Reader detection state shown over the finger grid.
+#[derive(Clone)]
+pub struct ScanStatus {
+    pub container: GtkBox,
+    pub spinner: Spinner,
+    pub label: Label,
+    /// Only shown once detection failed.
+    pub retry: Button,
 }
 
 /// PAM authentication switches.
 #[derive(Clone)]
 pub struct PamSwitches {
     pub login: Switch,
-    pub term: Switch,
-    pub prompt: Switch,
+    /// Busy indicator shown next to the login switch while pkexec runs.
+    pub login_spinner: Spinner,
+    /// Switches added at startup for the other services the helper has patches for.
+    pub services: Rc<RefCell<Vec<Switch>>>,
 }
 
-/// Fingerprint-related labels.
+/// Fingerprint-related labels and the finger icon shown next to them.
 #[derive(Clone)]
 pub struct FingerprintLabels {
     pub finger: Label,
+    /// When the selected finger was enrolled; hidden for unenrolled fingers.
+    pub enrolled: Label,
     pub action: Label,
+    pub icon: Image,
+    /// One dot per scan below the action label, for press sensors while enrolling.
+    pub stage_dots: GtkBox,
 }
 
 /// Fingerprint operation buttons.
 #[derive(Clone)]
 pub struct FingerprintButtons {
     pub add: Button,
+    pub reenroll: Button,
     pub delete: Button,
 }
 
+/// Buttons leading away from the current page.
+#[derive(Clone)]
+pub struct NavigationButtons {
+    pub manage: Button,
+    pub guided_setup: Button,
+    /// Back from the manage page to the main page.
+    pub back: Button,
+    /// Back from the finger page to the manage page.
+    pub finger_back: Button,
+}
+
 impl UiComponents {
     /// Create UI components from individual widgets.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         flow: FlowBox,
+        scan_status: ScanStatus,
+        device_dropdown: DropDown,
         stack: Stack,
         switches: PamSwitches,
         labels: FingerprintLabels,
         buttons: FingerprintButtons,
+        navigation: NavigationButtons,
     ) -> Self {
         Self {
             flow,
+            scan_status,
+            device_dropdown,
             stack,
             switches,
             labels,
             buttons,
+            navigation,
+        }
+    }
+}
+
+impl ScanStatus {
+    /// Create the detection state from its widgets.
+    pub fn new(container: GtkBox, spinner: Spinner, label: Label, retry: Button) -> Self {
+        Self {
+            container,
+            spinner,
+            label,
+            retry,
         }
     }
 }
 
 impl PamSwitches {
-    /// Create PAM switches from individual switch widgets.
-    pub fn new(login: Switch, term: Switch, prompt: Switch) -> Self {
+    /// Create PAM switches from the login switch widgets; the others are added at startup.
+    pub fn new(login: Switch, login_spinner: Spinner) -> Self {
         Self {
             login,
-            term,
-            prompt,
+            login_spinner,
+            services: Rc::new(RefCell::new(Vec::new())),
         }
     }
+
+    /// Every switch, login first.
+    pub fn all(&self) -> Vec<Switch> {
+        let mut switches = vec![self.login.clone()];
+        switches.extend(self.services.borrow().iter().cloned());
+        switches
+    }
 }
 
 impl FingerprintLabels {
-    /// Create fingerprint labels from individual label widgets.
-    pub fn new(finger: Label, action: Label) -> Self {
-        Self { finger, action }
+    /// Create fingerprint labels from individual label widgets, the finger icon and stage dots.
+    pub fn new(
+        finger: Label,
+        enrolled: Label,
+        action: Label,
+        icon: Image,
+        stage_dots: GtkBox,
+    ) -> Self {
+        Self {
+            finger,
+            enrolled,
+            action,
+            icon,
+            stage_dots,
+        }
     }
 }
 
 impl FingerprintButtons {
     /// Create fingerprint buttons from individual button widgets.
-    pub fn new(add: Button, delete: Button) -> Self {
-        Self { add, delete }
+    pub fn new(add: Button, reenroll: Button, delete: Button) -> Self {
+        Self {
+            add,
+            reenroll,
+            delete,
+        }
+    }
+}
+
+impl NavigationButtons {
+    /// Create navigation buttons from individual button widgets.
+    pub fn new(manage: Button, guided_setup: Button, back: Button, finger_back: Button) -> Self {
+        Self {
+            manage,
+            guided_setup,
+            back,
+            finger_back,
+        }
     }
 }
 
@@ -98,58 +223,227 @@ impl FingerprintContext {
     pub fn new(
         rt: Arc<Runtime>,
         ui: UiComponents,
-        selected_finger: Rc<RefCell<Option<String>>>,
+        selected_finger: Rc<RefCell<Option<Finger>>>,
+        username: &str,
+        toast: Toast,
+        settings: Settings,
     ) -> Self {
         Self {
             rt,
             ui,
             selected_finger,
-            enrolled: Rc::new(RefCell::new(HashSet::new())),
+            username: Rc::new(RefCell::new(username.to_string())),
+            selected_device: Rc::new(RefCell::new(None)),
+            devices: Rc::new(RefCell::new(Vec::new())),
+            enrolled: Rc::new(RefCell::new(HashMap::new())),
+            initializing: Rc::new(Cell::new(true)),
+            pam_module_installed: Rc::new(Cell::new(true)),
+            startup: Rc::new(RefCell::new(StartupState::default())),
+            toast,
+            operation_lock: Arc::new(Mutex::new(())),
+            settings: Rc::new(Cell::new(settings)),
+            refresh_busy: Rc::new(Cell::new(false)),
+            refresh_pending: Rc::new(Cell::new(false)),
+            busy: Rc::new(Cell::new(false)),
         }
     }
 
+    /// Current user-tunable behavior.
+    pub fn settings(&self) -> Settings {
+        self.settings.get()
+    }
+
+    /// Apply changed settings for the rest of the session.
+    pub fn set_settings(&self, settings: Settings) {
+        self.settings.set(settings);
+    }
+
+    /// User whose fingerprints are managed; empty for the current user.
+    pub fn username(&self) -> String {
+        self.username.borrow().clone()
+    }
+
+    /// Manage the fingerprints of `username` from now on; empty for the current user.
+    pub fn set_username(&self, username: &str) {
+        *self.username.borrow_mut() = username.to_string();
+    }
+
     /// Check if any PAM switches are active.
     pub fn has_active_pam_switches(&self) -> bool {
-        self.ui.switches.login.is_active()
-            || self.ui.switches.term.is_active()
-            || self.ui.switches.prompt.is_active()
+        self.ui
+            .switches
+            .all()
+            .iter()
+            .any(|switch| switch.is_active())
     }
 
     /// Enable or disable all PAM switches based on fingerprint availability.
+    /// Switches stay disabled until initialization has finished.
     pub fn set_pam_switches_sensitive(&self, sensitive: bool) {
-        self.ui.switches.login.set_sensitive(sensitive);
-        self.ui.switches.term.set_sensitive(sensitive);
-        self.ui.switches.prompt.set_sensitive(sensitive);
+        let sensitive = sensitive && !self.initializing.get();
+        let module_installed = self.pam_module_installed.get();
+        for switch in self.ui.switches.all() {
+            switch.set_sensitive(sensitive && (module_installed || switch.is_active()));
+        }
+    }
+
+    /// Record the result of an enrollment scan. The first one completes initialization
+    /// together with the PAM check; later ones only update switch sensitivity.
+    pub fn finish_initialization(&self, has_enrolled: bool) {
+        if !self.initializing.get() {
+            self.set_pam_switches_sensitive(has_enrolled);
+            return;
+        }
+        self.startup.borrow_mut().has_enrolled = Some(has_enrolled);
+        self.try_finish_startup();
+    }
+
+    /// Record that the PAM switches show the configuration found at startup.
+    pub fn finish_pam_check(&self) {
+        self.startup.borrow_mut().pam_checked = true;
+        self.try_finish_startup();
+    }
+
+    /// Once both startup checks are known, allow toggles from now on.
+    fn try_finish_startup(&self) {
+        let startup = self.startup.borrow();
+        let (true, Some(has_enrolled)) = (startup.pam_checked, startup.has_enrolled) else {
+            return;
+        };
+        drop(startup);
+
+        self.initializing.set(false);
+        self.set_pam_switches_sensitive(has_enrolled);
+    }
+
+    /// Take the operation lock for a user-started device operation.
+    /// Returns `None` and tells the user when another operation is still running.
+    pub fn try_begin_operation(&self) -> Option<OwnedMutexGuard<()>> {
+        match self.operation_lock.clone().try_lock_owned() {
+            Ok(guard) => Some(guard),
+            Err(_) => {
+                warn!("Refusing to start a device operation while another is running");
+                self.ui.labels.action.set_use_markup(true);
+                self.ui
+                    .labels
+                    .action
+                    .set_markup(&fingerprints::operation_in_progress_markup());
+                None
+            }
+        }
     }
 
     /// Update button states based on selected finger and enrollment status.
+    /// All of them stay disabled while busy.
     pub fn update_button_states(&self, is_enrolled: bool) {
-        self.ui.buttons.add.set_sensitive(!is_enrolled);
-        self.ui.buttons.delete.set_sensitive(is_enrolled);
+        let idle = !self.busy.get();
+        self.ui.buttons.add.set_sensitive(idle && !is_enrolled);
+        self.ui.buttons.reenroll.set_sensitive(idle && is_enrolled);
+        self.ui.buttons.delete.set_sensitive(idle && is_enrolled);
+    }
+
+    /// Whether an enrollment or deletion is running.
+    pub fn is_busy(&self) -> bool {
+        self.busy.get()
+    }
+
+    /// Disable navigation, the finger grid, the device selector and the finger actions while
+    /// an enrollment or deletion runs, so the user can't leave the page mid-operation.
+    /// Clearing it restores the actions for the selected finger.
+    pub fn set_busy(&self, busy: bool) {
+        self.busy.set(busy);
+
+        let navigation = &self.ui.navigation;
+        for button in [
+            &navigation.manage,
+            &navigation.guided_setup,
+            &navigation.back,
+            &navigation.finger_back,
+        ] {
+            button.set_sensitive(!busy);
+        }
+        self.ui.flow.set_sensitive(!busy);
+        self.ui.device_dropdown.set_sensitive(!busy);
+
+        match self.get_selected_finger() {
+            Some(finger) => self.update_button_states(self.is_finger_enrolled(finger)),
+            None => {
+                self.ui.buttons.add.set_sensitive(false);
+                self.ui.buttons.reenroll.set_sensitive(false);
+                self.ui.buttons.delete.set_sensitive(false);
+            }
+        }
     }
 
     /// Get the currently selected finger.
-    pub fn get_selected_finger(&self) -> Option<String> {
-        self.selected_finger.borrow().clone()
+    pub fn get_selected_finger(&self) -> Option<Finger> {
+        *self.selected_finger.borrow()
     }
 
     /// Set the currently selected finger.
-    pub fn set_selected_finger(&self, finger: Option<String>) {
+    pub fn set_selected_finger(&self, finger: Option<Finger>) {
         *self.selected_finger.borrow_mut() = finger;
     }
 
-    /// Get the enrolled fingerprints.
-    pub fn get_enrolled(&self) -> HashSet<String> {
-        self.enrolled.borrow().clone()
+    /// Get the currently selected device.
+    pub fn get_selected_device(&self) -> Option<OwnedObjectPath> {
+        self.selected_device.borrow().clone()
+    }
+
+    /// Set the currently selected device.
+    pub fn set_selected_device(&self, device: Option<OwnedObjectPath>) {
+        *self.selected_device.borrow_mut() = device;
+    }
+
+    /// Get the known devices as (object path, display name) pairs.
+    pub fn get_devices(&self) -> Vec<(OwnedObjectPath, String)> {
+        self.devices.borrow().clone()
+    }
+
+    /// Get the enrolled fingerprints of the selected device.
+    pub fn get_enrolled(&self) -> HashSet<Finger> {
+        self.selected_device
+            .borrow()
+            .as_ref()
+            .and_then(|device| self.enrolled.borrow().get(device).cloned())
+            .unwrap_or_default()
     }
 
-    /// Set the enrolled fingerprints.
-    pub fn set_enrolled(&self, enrolled: HashSet<String>) {
+    /// Store per-device scan results, keeping the selected device if it still exists.
+    pub fn set_device_enrollments(&self, scanned: Vec<DeviceEnrollment>) {
+        let mut devices = Vec::with_capacity(scanned.len());
+        let mut enrolled = HashMap::with_capacity(scanned.len());
+        for device in scanned {
+            devices.push((device.path.clone(), device.name));
+            enrolled.insert(device.path, device.enrolled);
+        }
+
+        let selected = self
+            .get_selected_device()
+            .filter(|path| enrolled.contains_key(path))
+            .or_else(|| devices.first().map(|(path, _)| path.clone()));
+
+        *self.devices.borrow_mut() = devices;
         *self.enrolled.borrow_mut() = enrolled;
+        self.set_selected_device(selected);
+    }
+
+    /// Hide a finger from the selected device's enrollment state without rescanning.
+    pub fn forget_enrolled_finger(&self, finger: Finger) {
+        if let Some(device) = self.selected_device.borrow().as_ref() {
+            if let Some(fingers) = self.enrolled.borrow_mut().get_mut(device) {
+                fingers.remove(&finger);
+            }
+        }
+    }
+
+    /// Check if any device has enrolled fingerprints.
+    pub fn has_any_enrolled(&self) -> bool {
+        self.enrolled.borrow().values().any(|set| !set.is_empty())
     }
 
-    /// Check if a finger is enrolled.
-    pub fn is_finger_enrolled(&self, finger: &str) -> bool {
-        self.enrolled.borrow().contains(finger)
+    /// Check if a finger is enrolled on the selected device.
+    pub fn is_finger_enrolled(&self, finger: Finger) -> bool {
+        self.get_enrolled().contains(&finger)
     }
 }
@@ -1,29 +1,177 @@
 //! Device management abstraction for fingerprint operations.
 
+use crate::config;
 use crate::core::fprintd;
+use crate::core::util::{self, Finger};
+use crate::fingerprints;
 use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zbus::zvariant::OwnedObjectPath;
+
+/// Device currently claimed by this process, so it can be released on shutdown.
+static ACTIVE_DEVICE: Lazy<Mutex<Option<fprintd::Device>>> = Lazy::new(|| Mutex::new(None));
+
+/// Remember `device` as claimed by this process.
+pub fn set_active_device(device: &fprintd::Device) {
+    if let Ok(mut active) = ACTIVE_DEVICE.lock() {
+        *active = Some(device.clone());
+    }
+}
+
+/// Forget `device` as claimed, unless another device was claimed since.
+pub fn clear_active_device(device: &fprintd::Device) {
+    if let Ok(mut active) = ACTIVE_DEVICE.lock() {
+        if active
+            .as_ref()
+            .is_some_and(|active| active.object_path() == device.object_path())
+        {
+            *active = None;
+        }
+    }
+}
+
+/// Stop any enrollment or verification on the claimed device and release it.
+/// Used on shutdown so an interrupted operation doesn't leave the reader claimed.
+pub async fn release_active_device() {
+    let Some(device) = ACTIVE_DEVICE
+        .lock()
+        .ok()
+        .and_then(|mut active| active.take())
+    else {
+        return;
+    };
+
+    info!("Releasing device {} before exit", device.object_path());
+    // At most one of these is running; stopping the other fails harmlessly
+    let _ = device.enroll_stop().await;
+    let _ = device.verify_stop().await;
+    if let Err(e) = device.release().await {
+        warn!("Failed to release device before exit: {}", e);
+    } else {
+        info!("Successfully released device before exit");
+    }
+}
 
 /// Error types for device management operations.
 #[derive(Debug)]
 pub enum DeviceError {
-    ConnectionFailed(String),
+    ConnectionFailed(zbus::Error),
     NoDeviceAvailable,
-    ClaimFailed(String),
+    ClaimFailed(zbus::Error),
+    /// Another client holds the device; carries the application name when it could be found.
+    DeviceBusy(Option<String>),
+    /// The device went away, e.g. the reader was unplugged.
+    NoSuchDevice,
+    EnrollFailed(String),
+    CaptureIncomplete,
+    TooManyRetries,
+    /// No scan arrived within the configured enrollment timeout.
+    EnrollTimedOut(Duration),
+    DeleteUnsupported,
+    /// Polkit denied the operation for this user; empty for the current user.
+    PermissionDenied(String),
+    /// fprintd restarted or the bus connection dropped mid-operation, taking the claim
+    /// with it; the operation has to be started again.
+    ServiceLost(zbus::Error),
+    /// A D-Bus call failed for a reason without a variant of its own.
+    Dbus(zbus::Error),
     OperationFailed(String),
 }
 
 impl std::fmt::Display for DeviceError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DeviceError::ConnectionFailed(msg) => write!(f, "Connection failed: {}", msg),
+            DeviceError::ConnectionFailed(e) => write!(f, "Connection failed: {}", e),
             DeviceError::NoDeviceAvailable => write!(f, "No fingerprint devices available"),
-            DeviceError::ClaimFailed(msg) => write!(f, "Failed to claim device: {}", msg),
+            DeviceError::ClaimFailed(e) => write!(f, "Failed to claim device: {}", e),
+            DeviceError::DeviceBusy(Some(holder)) => {
+                write!(f, "The fingerprint reader is in use by {}", holder)
+            }
+            DeviceError::DeviceBusy(None) => {
+                write!(f, "The fingerprint reader is in use by another application")
+            }
+            DeviceError::NoSuchDevice => write!(f, "The fingerprint reader is no longer available"),
+            DeviceError::EnrollFailed(status) => write!(f, "Enrollment failed: {}", status),
+            DeviceError::CaptureIncomplete => write!(f, "Capture incomplete"),
+            DeviceError::TooManyRetries => write!(f, "Too many rejected scans in a row"),
+            DeviceError::EnrollTimedOut(timeout) => {
+                write!(f, "No scan within {} seconds", timeout.as_secs())
+            }
+            DeviceError::DeleteUnsupported => {
+                write!(
+                    f,
+                    "Deleting a single fingerprint is not supported by this device"
+                )
+            }
+            DeviceError::PermissionDenied(user) if user.is_empty() => {
+                write!(f, "Not authorized to manage your fingerprints")
+            }
+            DeviceError::PermissionDenied(user) => write!(
+                f,
+                "Not authorized to manage fingerprints of user '{}'",
+                user
+            ),
+            DeviceError::ServiceLost(e) => write!(
+                f,
+                "Lost contact with the fingerprint service, which may have restarted; please try again ({})",
+                e
+            ),
+            DeviceError::Dbus(e) => write!(f, "D-Bus call failed: {}", e),
             DeviceError::OperationFailed(msg) => write!(f, "Operation failed: {}", msg),
         }
     }
 }
 
-impl std::error::Error for DeviceError {}
+impl std::error::Error for DeviceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeviceError::ConnectionFailed(e)
+            | DeviceError::ClaimFailed(e)
+            | DeviceError::ServiceLost(e)
+            | DeviceError::Dbus(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<zbus::Error> for DeviceError {
+    /// Sort out the fprintd errors the UI explains on their own, keeping any other error
+    /// with its D-Bus name. Polkit denials name no user until [`DeviceError::for_user`].
+    fn from(e: zbus::Error) -> Self {
+        if fprintd::is_already_in_use(&e) {
+            DeviceError::DeviceBusy(util::find_running_fprintd_client().map(str::to_string))
+        } else if fprintd::is_permission_denied(&e) {
+            DeviceError::PermissionDenied(String::new())
+        } else if fprintd::is_no_such_device(&e) {
+            DeviceError::NoSuchDevice
+        } else if fprintd::is_service_lost(&e) || fprintd::is_disconnected(&e) {
+            DeviceError::ServiceLost(e)
+        } else {
+            DeviceError::Dbus(e)
+        }
+    }
+}
+
+impl DeviceError {
+    /// Name `username` (empty for the current user) in a polkit denial.
+    fn for_user(self, username: &str) -> Self {
+        match self {
+            DeviceError::PermissionDenied(_) => DeviceError::PermissionDenied(username.to_string()),
+            other => other,
+        }
+    }
+}
+
+/// Steps of a deletion, reported so the UI can show progress on slow devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalStep {
+    Claiming,
+    Deleting,
+    Releasing,
+}
 
 /// RAII-style device manager for fprintd operations.
 pub struct DeviceManager {
@@ -32,12 +180,24 @@ pub struct DeviceManager {
 
 impl DeviceManager {
     /// Acquire a fingerprint device with automatic cleanup.
-    pub async fn acquire() -> Result<Self, DeviceError> {
+    /// Uses the given device path, or the first available device when `None`, and claims it
+    /// for `username` (empty for the current user).
+    pub async fn acquire(
+        path: Option<OwnedObjectPath>,
+        username: &str,
+    ) -> Result<Self, DeviceError> {
         info!("Acquiring fingerprint device for operation");
 
         let client = Self::connect_to_fprintd().await?;
-        let device = Self::get_first_device(&client).await?;
-        Self::claim_device(&device).await?;
+        let device = match path {
+            Some(path) => {
+                info!("Using selected fingerprint device: {}", path.as_str());
+                client.device(path)
+            }
+            None => Self::get_first_device(&client).await?,
+        };
+        Self::claim_device(&device, username).await?;
+        set_active_device(&device);
 
         info!("Successfully acquired and claimed fingerprint device");
         Ok(Self {
@@ -50,8 +210,21 @@ impl DeviceManager {
         self.device.as_ref()
     }
 
+    /// Release the device now instead of in the background when dropped.
+    pub async fn release(mut self) {
+        if let Some(device) = self.device.take() {
+            clear_active_device(&device);
+            info!("Releasing fingerprint device");
+            if let Err(e) = device.release().await {
+                warn!("Failed to release device: {}", e);
+            } else {
+                info!("Successfully released fingerprint device");
+            }
+        }
+    }
+
     /// Connect to fprintd system bus.
-    async fn connect_to_fprintd() -> Result<fprintd::Client, DeviceError> {
+    pub async fn connect_to_fprintd() -> Result<fprintd::Client, DeviceError> {
         info!("Connecting to fprintd system bus");
         match fprintd::Client::system().await {
             Ok(client) => {
@@ -60,13 +233,15 @@ impl DeviceManager {
             }
             Err(e) => {
                 error!("Failed to connect to fprintd system bus: {}", e);
-                Err(DeviceError::ConnectionFailed(e.to_string()))
+                Err(DeviceError::ConnectionFailed(e))
             }
         }
     }
 
     /// Get the first available fingerprint device.
-    async fn get_first_device(client: &fprintd::Client) -> Result<fprintd::Device, DeviceError> {
+    pub async fn get_first_device(
+        client: &fprintd::Client,
+    ) -> Result<fprintd::Device, DeviceError> {
         info!("Looking for available fingerprint devices");
         match fprintd::first_device(client).await {
             Ok(Some(device)) => {
@@ -80,36 +255,50 @@ impl DeviceManager {
             }
             Err(e) => {
                 error!("Failed to enumerate devices: {}", e);
-                Err(DeviceError::OperationFailed(format!(
-                    "Failed to enumerate devices: {}",
-                    e
-                )))
+                Err(e.into())
             }
         }
     }
 
-    /// Claim the device for exclusive access.
-    async fn claim_device(device: &fprintd::Device) -> Result<(), DeviceError> {
+    /// Claim the device for exclusive access on behalf of `username`.
+    async fn claim_device(device: &fprintd::Device, username: &str) -> Result<(), DeviceError> {
         info!("Claiming fingerprint device for exclusive access");
-        match device.claim("").await {
+        match device.claim(username).await {
             Ok(_) => {
                 info!("Successfully claimed device");
                 Ok(())
             }
-            Err(e) => {
-                error!("Failed to claim device: {}", e);
-                Err(DeviceError::ClaimFailed(e.to_string()))
-            }
+            Err(e) => match DeviceError::from(e).for_user(username) {
+                DeviceError::Dbus(e) => {
+                    error!("Failed to claim device: {}", e);
+                    Err(DeviceError::ClaimFailed(e))
+                }
+                err @ DeviceError::PermissionDenied(_) => {
+                    error!("Polkit denied claiming device for '{}'", username);
+                    Err(err)
+                }
+                err => {
+                    warn!("Could not claim device: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
 
 impl Drop for DeviceManager {
-    /// Automatic cleanup when DeviceManager goes out of scope.
+    /// Fallback for a manager dropped without [`DeviceManager::release`], e.g. when its task
+    /// was cancelled; the release is attempted in the background if a runtime is still running,
+    /// otherwise the device stays registered for [`release_active_device`] on shutdown.
     fn drop(&mut self) {
         if let Some(device) = self.device.take() {
-            info!("Cleaning up device in destructor");
-            tokio::spawn(async move {
+            warn!("Device dropped without being released");
+            let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+                warn!("No async runtime left, leaving the release to shutdown cleanup");
+                return;
+            };
+            clear_active_device(&device);
+            runtime.spawn(async move {
                 if let Err(e) = device.release().await {
                     warn!("Failed to release device during cleanup: {}", e);
                 } else {
@@ -122,55 +311,417 @@ impl Drop for DeviceManager {
 
 /// Convenience functions for common device operations.
 impl DeviceManager {
+    /// Claim the device at `device_path` (the first device when `None`) for `username`, run
+    /// `operation` on it and release it again, whether the operation succeeded or not.
+    pub async fn with_device<T>(
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        operation: impl AsyncFnOnce(&fprintd::Device) -> Result<T, DeviceError>,
+    ) -> Result<T, DeviceError> {
+        let manager = Self::acquire(device_path, username).await?;
+
+        let result = match manager.device() {
+            Some(device) => operation(device).await,
+            None => Err(DeviceError::OperationFailed(
+                "Device not available".to_string(),
+            )),
+        };
+
+        manager.release().await;
+        result
+    }
+
     /// Execute enrollment operation with automatic device management.
-    /// Returns the DeviceManager which must be kept alive until enrollment completes.
-    pub async fn enroll_finger<F>(
-        finger_key: String,
-        setup_listener: F,
-    ) -> Result<Self, DeviceError>
+    /// Reports every status update to `on_status` and sensor state changes to
+    /// `on_sensor`, resolving once enrollment has finished or no status arrived for `timeout`.
+    pub async fn enroll_finger<F, N>(
+        finger: Finger,
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        timeout: Option<Duration>,
+        on_status: F,
+        on_sensor: N,
+    ) -> Result<(), DeviceError>
+    where
+        F: FnMut(&fprintd::EnrollStatusEvent) + Send,
+        N: Fn(fprintd::SensorEvent) + Send + Sync + 'static,
+    {
+        Self::with_device(device_path, username, async |device| {
+            info!("Starting enrollment process for finger: '{}'", finger);
+            Self::finish_enrollment(device, finger, username, timeout, on_status, on_sensor).await
+        })
+        .await
+    }
+
+    /// Replace an enrolled finger by deleting it and enrolling it again on the same
+    /// claimed device, reporting progress like [`Self::enroll_finger`].
+    /// `on_deleted` runs once the old print is gone, before enrollment starts.
+    pub async fn reenroll_finger<D, F, N>(
+        finger: Finger,
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        timeout: Option<Duration>,
+        on_deleted: D,
+        on_status: F,
+        on_sensor: N,
+    ) -> Result<(), DeviceError>
     where
-        F: FnOnce(&fprintd::Device) -> Result<(), DeviceError>,
+        D: FnOnce() + Send,
+        F: FnMut(&fprintd::EnrollStatusEvent) + Send,
+        N: Fn(fprintd::SensorEvent) + Send + Sync + 'static,
     {
-        let manager = Self::acquire().await?;
+        Self::with_device(device_path, username, async |device| {
+            info!("Deleting '{}' before re-enrollment", finger);
+            if let Err(e) = device.delete_enrolled_finger(finger).await {
+                error!("Failed to delete enrolled finger '{}': {}", finger, e);
+                return Err(operation_error(e, username, "Failed to delete finger"));
+            }
+            on_deleted();
+
+            info!("Starting re-enrollment for finger: '{}'", finger);
+            Self::finish_enrollment(device, finger, username, timeout, on_status, on_sensor).await
+        })
+        .await
+    }
 
-        let device = manager
-            .device()
-            .ok_or_else(|| DeviceError::OperationFailed("Device not available".to_string()))?;
+    /// Enroll several fingers back-to-back while keeping the device claimed.
+    /// `on_finger` is called with the index of each finger before its enrollment starts and
+    /// `on_status` receives that index with every status update; stops at the first failure.
+    pub async fn enroll_fingers<S, F, N>(
+        fingers: &[Finger],
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        timeout: Option<Duration>,
+        mut on_finger: S,
+        mut on_status: F,
+        on_sensor: N,
+    ) -> Result<(), DeviceError>
+    where
+        S: FnMut(usize, Finger) + Send,
+        F: FnMut(usize, &fprintd::EnrollStatusEvent) + Send,
+        N: Fn(fprintd::SensorEvent) + Send + Sync + 'static,
+    {
+        let on_sensor = Arc::new(on_sensor);
+        Self::with_device(device_path, username, async |device| {
+            for (index, &finger) in fingers.iter().enumerate() {
+                info!(
+                    "Starting enrollment {} of {} for finger: '{}'",
+                    index + 1,
+                    fingers.len(),
+                    finger
+                );
+                on_finger(index, finger);
 
-        setup_listener(device)?;
+                let on_sensor = on_sensor.clone();
+                Self::finish_enrollment(
+                    device,
+                    finger,
+                    username,
+                    timeout,
+                    |evt: &fprintd::EnrollStatusEvent| on_status(index, evt),
+                    move |evt| on_sensor(evt),
+                )
+                .await?;
+            }
 
-        info!("Starting enrollment process for finger: '{}'", finger_key);
-        if let Err(e) = device.enroll_start(&finger_key).await {
-            error!("Failed to start enrollment for '{}': {}", finger_key, e);
-            let _ = device.enroll_stop().await;
-            return Err(DeviceError::OperationFailed(format!(
-                "Failed to start enrollment: {}",
-                e
-            )));
+            Ok(())
+        })
+        .await
+    }
+
+    /// Run enrollment on an already claimed device and map the outcome.
+    /// The sensor state is watched only while the enrollment runs.
+    /// Press sensors also report their stage count, which is only readable while claimed.
+    async fn finish_enrollment<F, N>(
+        device: &fprintd::Device,
+        finger: Finger,
+        username: &str,
+        timeout: Option<Duration>,
+        on_status: F,
+        on_sensor: N,
+    ) -> Result<(), DeviceError>
+    where
+        F: FnMut(&fprintd::EnrollStatusEvent) + Send,
+        N: Fn(fprintd::SensorEvent) + Send + Sync + 'static,
+    {
+        if device
+            .scan_type()
+            .await
+            .is_ok_and(|scan_type| scan_type == "press")
+        {
+            let stages = device.num_enroll_stages().await.ok();
+            if let Some(stages) = stages.and_then(fingerprints::plausible_enroll_stages) {
+                on_sensor(fprintd::SensorEvent::PressStages(stages as usize));
+            }
         }
 
-        info!("Enrollment started successfully, waiting for finger scans...");
-        Ok(manager)
+        let on_sensor = Arc::new(on_sensor);
+
+        let needed_watcher = {
+            let device = device.clone();
+            let on_sensor = on_sensor.clone();
+            tokio::spawn(async move {
+                let on_needed = |needed| on_sensor(fprintd::SensorEvent::FingerNeeded(needed));
+                if let Err(e) = device.listen_finger_needed(on_needed).await {
+                    warn!("Failed to watch finger-needed property: {}", e);
+                }
+            })
+        };
+
+        let present_poller = {
+            let device = device.clone();
+            let on_sensor = on_sensor.clone();
+            tokio::spawn(async move {
+                let on_present = |present| on_sensor(fprintd::SensorEvent::FingerPresent(present));
+                if let Err(e) = device.poll_finger_present(on_present).await {
+                    warn!("Failed to poll finger-present property: {}", e);
+                }
+            })
+        };
+
+        let result = device
+            .enroll_finger_with_progress(
+                finger,
+                Some(config::enrollment::MAX_CONSECUTIVE_RETRIES),
+                timeout,
+                on_status,
+            )
+            .await;
+        needed_watcher.abort();
+        present_poller.abort();
+
+        match result {
+            Ok(()) => {
+                info!("Enrollment of '{}' finished successfully", finger);
+                Ok(())
+            }
+            Err(fprintd::EnrollError::Failed(status)) => {
+                warn!("Enrollment of '{}' ended with '{}'", finger, status);
+                Err(DeviceError::EnrollFailed(status))
+            }
+            Err(fprintd::EnrollError::CaptureIncomplete) => {
+                warn!(
+                    "Enrollment of '{}' reported data-full but never completed",
+                    finger
+                );
+                Err(DeviceError::CaptureIncomplete)
+            }
+            Err(fprintd::EnrollError::TooManyRetries(count)) => {
+                warn!(
+                    "Enrollment of '{}' abandoned after {} rejected scans in a row",
+                    finger, count
+                );
+                Err(DeviceError::TooManyRetries)
+            }
+            Err(fprintd::EnrollError::TimedOut(timeout)) => {
+                warn!(
+                    "Enrollment of '{}' stopped after {} seconds without a scan",
+                    finger,
+                    timeout.as_secs()
+                );
+                Err(DeviceError::EnrollTimedOut(timeout))
+            }
+            Err(fprintd::EnrollError::Dbus(e)) if fprintd::is_permission_denied(&e) => {
+                error!("Polkit denied enrolling '{}': {}", finger, e);
+                Err(DeviceError::PermissionDenied(username.to_string()))
+            }
+            Err(e) => {
+                error!("Failed to enroll finger '{}': {}", finger, e);
+                Err(DeviceError::OperationFailed(format!(
+                    "Failed to start enrollment: {}",
+                    e
+                )))
+            }
+        }
     }
 
     /// Execute removal operation with automatic device management.
-    pub async fn delete_finger(finger_key: String) -> Result<(), DeviceError> {
-        let manager = Self::acquire().await?;
-
-        let device = manager
-            .device()
-            .ok_or_else(|| DeviceError::OperationFailed("Device not available".to_string()))?;
-
-        info!("Executing deletion of enrolled finger: '{}'", finger_key);
-        if let Err(e) = device.delete_enrolled_finger(&finger_key).await {
-            error!("Failed to delete enrolled finger '{}': {}", finger_key, e);
-            return Err(DeviceError::OperationFailed(format!(
-                "Failed to delete finger: {}",
-                e
-            )));
-        }
+    /// Reports each step to `on_step` before it starts.
+    pub async fn delete_finger<S>(
+        finger: Finger,
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        mut on_step: S,
+    ) -> Result<(), DeviceError>
+    where
+        S: FnMut(RemovalStep) + Send,
+    {
+        on_step(RemovalStep::Claiming);
+        Self::with_device(device_path, username, async |device| {
+            info!("Executing deletion of enrolled finger: '{}'", finger);
+            on_step(RemovalStep::Deleting);
+            if let Err(e) = device.delete_enrolled_finger(finger).await {
+                if fprintd::is_unsupported_method(&e) {
+                    warn!("Device does not support deleting a single finger: {}", e);
+                    return Err(DeviceError::DeleteUnsupported);
+                }
+                error!("Failed to delete enrolled finger '{}': {}", finger, e);
+                return Err(operation_error(e, username, "Failed to delete finger"));
+            }
+
+            info!("Successfully deleted fingerprint '{}'", finger);
+            on_step(RemovalStep::Releasing);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Delete every enrolled finger of the current user on the device.
+    /// Falls back to the legacy per-user call when DeleteEnrolledFingers2 is unavailable.
+    /// Reports each step to `on_step` before it starts.
+    pub async fn delete_all_fingers<S>(
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        mut on_step: S,
+    ) -> Result<(), DeviceError>
+    where
+        S: FnMut(RemovalStep) + Send,
+    {
+        on_step(RemovalStep::Claiming);
+        Self::with_device(device_path, username, async |device| {
+            info!("Executing deletion of all enrolled fingers");
+            on_step(RemovalStep::Deleting);
+            let result = match device.delete_enrolled_fingers().await {
+                Err(e) if fprintd::is_unsupported_method(&e) => {
+                    warn!(
+                        "DeleteEnrolledFingers2 unsupported, using legacy call: {}",
+                        e
+                    );
+                    device.delete_enrolled_fingers_for_user(username).await
+                }
+                other => other,
+            };
+
+            if let Err(e) = result {
+                error!("Failed to delete all enrolled fingers: {}", e);
+                return Err(operation_error(
+                    e,
+                    username,
+                    "Failed to delete fingerprints",
+                ));
+            }
+
+            info!("Successfully deleted all enrolled fingerprints");
+            on_step(RemovalStep::Releasing);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Verify every enrolled finger once, keeping the device claimed for the whole sequence.
+    /// `on_finger` is called with the index, the total and the finger before each prompt.
+    /// Returns whether each finger, by fprintd name, matched.
+    pub async fn verify_fingers<S>(
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+        mut on_finger: S,
+    ) -> Result<HashMap<String, bool>, DeviceError>
+    where
+        S: FnMut(usize, usize, Finger) + Send,
+    {
+        Self::with_device(device_path, username, async |device| {
+            let fingers: Vec<Finger> = match device.list_enrolled_fingers(username).await {
+                Ok(names) => names
+                    .iter()
+                    .filter_map(|name| {
+                        let finger = Finger::from_dbus_name(name);
+                        if finger.is_none() {
+                            warn!("Skipping unknown finger name '{}'", name);
+                        }
+                        finger
+                    })
+                    .collect(),
+                Err(e) => {
+                    error!("Failed to list enrolled fingers: {}", e);
+                    return Err(operation_error(e, username, "Failed to list fingerprints"));
+                }
+            };
+
+            let mut results = HashMap::new();
+            for (index, &finger) in fingers.iter().enumerate() {
+                info!(
+                    "Verifying finger {} of {}: '{}'",
+                    index + 1,
+                    fingers.len(),
+                    finger
+                );
+                on_finger(index, fingers.len(), finger);
+
+                let matched = match device.verify_finger_once(Some(finger)).await {
+                    Ok(matched) => matched,
+                    Err(fprintd::VerifyError::Failed(status)) => {
+                        warn!("Verification of '{}' ended with '{}'", finger, status);
+                        false
+                    }
+                    Err(fprintd::VerifyError::Dbus(e)) => {
+                        error!("Failed to verify finger '{}': {}", finger, e);
+                        return Err(operation_error(e, username, "Failed to verify finger"));
+                    }
+                    Err(e) => {
+                        error!("Failed to verify finger '{}': {}", finger, e);
+                        return Err(DeviceError::OperationFailed(format!(
+                            "Failed to verify finger: {}",
+                            e
+                        )));
+                    }
+                };
+                info!(
+                    "Finger '{}' {}",
+                    finger,
+                    if matched { "matched" } else { "did not match" }
+                );
+                results.insert(finger.to_dbus_name().to_string(), matched);
+            }
+
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Verify any enrolled finger once and report whether it matched, together with the
+    /// finger fprintd selected (`None` when it did not announce one).
+    pub async fn identify_finger(
+        device_path: Option<OwnedObjectPath>,
+        username: &str,
+    ) -> Result<(bool, Option<String>), DeviceError> {
+        Self::with_device(device_path, username, async |device| {
+            info!("Verifying any enrolled finger");
+            match device.verify_any_finger_once().await {
+                Ok((matched, selected)) => {
+                    let selected = selected.map(|evt| evt.finger_name);
+                    info!(
+                        "Verification {} (selected finger: {})",
+                        if matched { "matched" } else { "did not match" },
+                        selected.as_deref().unwrap_or("none")
+                    );
+                    Ok((matched, selected))
+                }
+                Err(fprintd::VerifyError::Failed(status)) => {
+                    warn!("Verification ended with '{}'", status);
+                    Ok((false, None))
+                }
+                Err(fprintd::VerifyError::Dbus(e)) => {
+                    error!("Failed to verify: {}", e);
+                    Err(operation_error(e, username, "Failed to verify"))
+                }
+                Err(e) => {
+                    error!("Failed to verify: {}", e);
+                    Err(DeviceError::OperationFailed(format!(
+                        "Failed to verify: {}",
+                        e
+                    )))
+                }
+            }
+        })
+        .await
+    }
+}
 
-        info!("Successfully deleted fingerprint '{}'", finger_key);
-        Ok(())
+/// Map a failed D-Bus call to a device error, describing errors fprintd gives no
+/// meaning to with `context`.
+fn operation_error(e: zbus::Error, username: &str, context: &str) -> DeviceError {
+    match DeviceError::from(e).for_user(username) {
+        DeviceError::Dbus(e) => DeviceError::OperationFailed(format!("{}: {}", context, e)),
+        err => err,
     }
 }
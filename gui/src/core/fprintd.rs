@@ -2,12 +2,19 @@
 //! Async helpers for fprintd D-Bus interface.
 
 use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use futures_util::StreamExt;
+use futures_util::{Stream, StreamExt};
+use log::{info, warn};
 use serde::{de::DeserializeOwned, Serialize};
 use zbus::zvariant::{OwnedObjectPath, Type};
 use zbus::{Connection, Proxy};
 
+use crate::core::util::Finger;
+
 // D-Bus API Reference:
 // BUS_NAME = 'net.reactivated.Fprint'
 // MAIN_OBJ = '/net/reactivated/Fprint/Manager'
@@ -71,24 +78,165 @@ pub const IFACE_MANAGER: &str = "net.reactivated.Fprint.Manager";
 /// Device interface name.
 pub const IFACE_DEVICE: &str = "net.reactivated.Fprint.Device";
 
-/// Supported finger names.
-pub const FINGERS: &[&str] = &[
-    "left-thumb",
-    "left-index-finger",
-    "left-middle-finger",
-    "left-ring-finger",
-    "left-little-finger",
-    "right-thumb",
-    "right-index-finger",
-    "right-middle-finger",
-    "right-ring-finger",
-    "right-little-finger",
+/// How many times a dropped system bus connection is reopened before giving up.
+pub const RECONNECT_ATTEMPTS: u32 = 4;
+
+/// Delay before the first reconnection attempt; doubled after every failed attempt.
+pub const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// Bus connection shared by a client and the helpers it creates, so a connection
+/// rebuilt by one helper is picked up by all of them.
+struct Bus {
+    conn: RwLock<Connection>,
+    /// Only the system bus can be reopened; wrapped connections are used as they are.
+    reconnectable: bool,
+    /// Serializes reconnection so concurrent failures open a single new connection.
+    reconnecting: tokio::sync::Mutex<()>,
+}
+
+impl Bus {
+    fn new(conn: Connection, reconnectable: bool) -> Arc<Self> {
+        Arc::new(Self {
+            conn: RwLock::new(conn),
+            reconnectable,
+            reconnecting: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn connection(&self) -> Connection {
+        self.conn
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Run `op` on the current connection. A closed connection is reopened and a
+    /// vanished fprintd, e.g. one being restarted, is given a moment to come back; `op`
+    /// then runs once more if it is `repeatable`.
+    /// Calls relying on the device claim are not: fprintd drops the claim with the old
+    /// connection or daemon, so they fail with the original error instead.
+    async fn with_recovery<T, F, Fut>(&self, repeatable: bool, op: F) -> zbus::Result<T>
+    where
+        F: Fn(Connection) -> Fut,
+        Fut: Future<Output = zbus::Result<T>>,
+    {
+        let conn = self.connection();
+        let err = match op(conn.clone()).await {
+            Err(e) if self.reconnectable && is_disconnected(&e) => e,
+            Err(e) if is_service_lost(&e) => e,
+            result => return result,
+        };
+
+        let conn = if is_disconnected(&err) {
+            warn!("System bus connection lost ({}); reconnecting", err);
+            self.reconnect(&conn).await?
+        } else {
+            warn!("fprintd went away ({}), it may be restarting", err);
+            conn
+        };
+
+        if !repeatable {
+            warn!("Not repeating a call that needs the device claim fprintd no longer holds");
+            return Err(err);
+        }
+        if is_service_lost(&err) {
+            // A restarted fprintd is activated again by the next call once its name is free
+            tokio::time::sleep(RECONNECT_INITIAL_DELAY).await;
+        }
+        op(conn).await
+    }
+
+    /// Replace `stale` with a new system bus connection, backing off exponentially
+    /// between attempts. Returns the connection another caller already rebuilt, if any.
+    async fn reconnect(&self, stale: &Connection) -> zbus::Result<Connection> {
+        let _reconnecting = self.reconnecting.lock().await;
+        let current = self.connection();
+        if current.unique_name() != stale.unique_name() {
+            return Ok(current);
+        }
+
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut attempt = 1;
+        loop {
+            tokio::time::sleep(delay).await;
+            match Connection::system().await {
+                Ok(conn) => {
+                    info!("Reconnected to the system bus after {} attempt(s)", attempt);
+                    *self
+                        .conn
+                        .write()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()) = conn.clone();
+                    return Ok(conn);
+                }
+                Err(e) if attempt < RECONNECT_ATTEMPTS => {
+                    warn!("Reconnection attempt {} failed: {}", attempt, e);
+                    attempt += 1;
+                    delay *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// D-Bus error names meaning fprintd left the bus, e.g. while being restarted.
+const SERVICE_LOST_ERROR_NAMES: &[&str] = &[
+    "org.freedesktop.DBus.Error.ServiceUnknown",
+    "org.freedesktop.DBus.Error.NameHasNoOwner",
+    "org.freedesktop.DBus.Error.NoReply",
+];
+
+/// Device methods that act on the device claim. fprintd drops the claim when the
+/// connection that made it closes or when it restarts, so these are never repeated.
+const CLAIMED_METHODS: &[&str] = &[
+    "Release",
+    "EnrollStart",
+    "EnrollStop",
+    "VerifyStart",
+    "VerifyStop",
+    "DeleteEnrolledFinger",
+    "DeleteEnrolledFingers2",
 ];
 
+/// Whether a call failed because fprintd left the bus or died before replying, while
+/// the bus connection itself is still open.
+pub fn is_service_lost(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, _, _) => SERVICE_LOST_ERROR_NAMES.contains(&name.as_str()),
+        zbus::Error::FDO(e) => matches!(
+            **e,
+            zbus::fdo::Error::ServiceUnknown(_)
+                | zbus::fdo::Error::NameHasNoOwner(_)
+                | zbus::fdo::Error::NoReply(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Whether an error means the bus connection itself was closed, as opposed to a failed call.
+pub fn is_disconnected(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::InputOutput(e) => matches!(
+            e.kind(),
+            io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+        ),
+        zbus::Error::MethodError(name, _, _) => {
+            name.as_str() == "org.freedesktop.DBus.Error.Disconnected"
+        }
+        _ => false,
+    }
+}
+
 /// Async client with system bus connection.
+/// Calls that fail because the connection dropped or fprintd restarted are retried once,
+/// unless they depend on the device claim.
 #[derive(Clone)]
 pub struct Client {
-    conn: Connection,
+    bus: Arc<Bus>,
 }
 
 impl fmt::Debug for Client {
@@ -101,25 +249,35 @@ impl Client {
     /// Connect to system bus.
     pub async fn system() -> zbus::Result<Self> {
         let conn = Connection::system().await?;
-        Ok(Self { conn })
+        Ok(Self {
+            bus: Bus::new(conn, true),
+        })
+    }
+
+    /// Wrap an existing connection (e.g. a private bus running the fprintd mock).
+    /// Such a connection is not reopened when it drops.
+    pub fn from_connection(conn: Connection) -> Self {
+        Self {
+            bus: Bus::new(conn, false),
+        }
     }
 
     /// Get underlying connection.
-    pub fn connection(&self) -> &Connection {
-        &self.conn
+    pub fn connection(&self) -> Connection {
+        self.bus.connection()
     }
 
     /// Create Manager helper.
     pub fn manager(&self) -> Manager {
         Manager {
-            conn: self.conn.clone(),
+            bus: self.bus.clone(),
         }
     }
 
     /// Create Device helper for specific path.
     pub fn device(&self, object_path: OwnedObjectPath) -> Device {
         Device {
-            conn: self.conn.clone(),
+            bus: self.bus.clone(),
             object_path,
         }
     }
@@ -128,7 +286,7 @@ impl Client {
 /// Manager interface helper.
 #[derive(Clone)]
 pub struct Manager {
-    conn: Connection,
+    bus: Arc<Bus>,
 }
 
 impl fmt::Debug for Manager {
@@ -138,11 +296,7 @@ impl fmt::Debug for Manager {
 }
 
 impl Manager {
-    async fn proxy(&self) -> zbus::Result<Proxy<'_>> {
-        Proxy::new(&self.conn, SERVICE, MANAGER_PATH, IFACE_MANAGER).await
-    }
-
-    /// Generic method call.
+    /// Generic method call, failing after [`CALL_TIMEOUT`].
     async fn call<R>(
         &self,
         method: &str,
@@ -151,9 +305,15 @@ impl Manager {
     where
         R: DeserializeOwned + Type,
     {
-        let proxy = self.proxy().await?;
-
-        proxy.call(method, args).await
+        with_timeout(
+            method,
+            CALL_TIMEOUT,
+            self.bus.with_recovery(true, |conn| async move {
+                let proxy = Proxy::new(&conn, SERVICE, MANAGER_PATH, IFACE_MANAGER).await?;
+                proxy.call(method, args).await
+            }),
+        )
+        .await
     }
 
     /// Get device object paths.
@@ -172,7 +332,7 @@ impl Manager {
 /// Device interface helper.
 #[derive(Clone)]
 pub struct Device {
-    conn: Connection,
+    bus: Arc<Bus>,
     object_path: OwnedObjectPath,
 }
 
@@ -186,7 +346,8 @@ impl fmt::Debug for Device {
 
 impl Device {
     async fn proxy(&self) -> zbus::Result<Proxy<'_>> {
-        Proxy::new(&self.conn, SERVICE, self.object_path.as_str(), IFACE_DEVICE).await
+        let conn = self.bus.connection();
+        Proxy::new(&conn, SERVICE, self.object_path.as_str(), IFACE_DEVICE).await
     }
 
     /// Get device object path.
@@ -194,7 +355,12 @@ impl Device {
         self.object_path.as_str()
     }
 
-    /// Generic method call.
+    /// Get owned device object path.
+    pub fn owned_object_path(&self) -> OwnedObjectPath {
+        self.object_path.clone()
+    }
+
+    /// Generic method call, failing after [`CALL_TIMEOUT`].
     async fn call<R>(
         &self,
         method: &str,
@@ -203,9 +369,62 @@ impl Device {
     where
         R: DeserializeOwned + Type,
     {
-        let proxy = self.proxy().await?;
+        with_timeout(method, CALL_TIMEOUT, self.call_with_recovery(method, args)).await
+    }
+
+    /// Method call that may wait for a polkit prompt, failing after [`AUTHORIZED_CALL_TIMEOUT`].
+    async fn call_authorized<R>(
+        &self,
+        method: &str,
+        args: &(impl Serialize + Type + fmt::Debug),
+    ) -> zbus::Result<R>
+    where
+        R: DeserializeOwned + Type,
+    {
+        with_timeout(
+            method,
+            AUTHORIZED_CALL_TIMEOUT,
+            self.call_with_recovery(method, args),
+        )
+        .await
+    }
+
+    /// Read a property, failing after [`CALL_TIMEOUT`].
+    async fn property<R>(&self, name: &str) -> zbus::Result<R>
+    where
+        R: TryFrom<zbus::zvariant::OwnedValue>,
+        R::Error: Into<zbus::Error>,
+    {
+        with_timeout(
+            name,
+            CALL_TIMEOUT,
+            self.bus.with_recovery(true, |conn| async move {
+                let proxy =
+                    Proxy::new(&conn, SERVICE, self.object_path.as_str(), IFACE_DEVICE).await?;
+                proxy.get_property::<R>(name).await
+            }),
+        )
+        .await
+    }
 
-        proxy.call(method, args).await
+    /// Call `method` on a fresh proxy, retrying once after the bus connection dropped or
+    /// fprintd restarted unless the method needs the device claim.
+    async fn call_with_recovery<R>(
+        &self,
+        method: &str,
+        args: &(impl Serialize + Type + fmt::Debug),
+    ) -> zbus::Result<R>
+    where
+        R: DeserializeOwned + Type,
+    {
+        let repeatable = !CLAIMED_METHODS.contains(&method);
+        self.bus
+            .with_recovery(repeatable, |conn| async move {
+                let proxy =
+                    Proxy::new(&conn, SERVICE, self.object_path.as_str(), IFACE_DEVICE).await?;
+                proxy.call(method, args).await
+            })
+            .await
     }
 
     /// List enrolled fingers for user ("" for current user).
@@ -216,25 +435,31 @@ impl Device {
 
     /// Delete all enrolled fingers (requires device claim).
     pub async fn delete_enrolled_fingers(&self) -> zbus::Result<()> {
-        let _: () = self.call("DeleteEnrolledFingers2", &()).await?;
+        let _: () = self.call_authorized("DeleteEnrolledFingers2", &()).await?;
         Ok(())
     }
 
     /// Delete all enrolled fingers for specific user (legacy).
     pub async fn delete_enrolled_fingers_for_user(&self, username: &str) -> zbus::Result<()> {
-        let _: () = self.call("DeleteEnrolledFingers", &(username,)).await?;
+        let _: () = self
+            .call_authorized("DeleteEnrolledFingers", &(username,))
+            .await?;
         Ok(())
     }
 
     /// Delete single enrolled finger (requires device claim).
-    pub async fn delete_enrolled_finger(&self, finger: &str) -> zbus::Result<()> {
-        let _: () = self.call("DeleteEnrolledFinger", &(finger,)).await?;
+    pub async fn delete_enrolled_finger(&self, finger: Finger) -> zbus::Result<()> {
+        let _: () = self
+            .call_authorized("DeleteEnrolledFinger", &(finger.to_dbus_name(),))
+            .await?;
         Ok(())
     }
 
     /// Start enrollment for finger.
-    pub async fn enroll_start(&self, finger: &str) -> zbus::Result<()> {
-        let _: () = self.call("EnrollStart", &(finger,)).await?;
+    pub async fn enroll_start(&self, finger: Finger) -> zbus::Result<()> {
+        let _: () = self
+            .call_authorized("EnrollStart", &(finger.to_dbus_name(),))
+            .await?;
         Ok(())
     }
 
@@ -244,9 +469,10 @@ impl Device {
         Ok(())
     }
 
-    /// Start verification for finger.
-    pub async fn verify_start(&self, finger: &str) -> zbus::Result<()> {
-        let _: () = self.call("VerifyStart", &(finger,)).await?;
+    /// Start verification for finger (`None` lets fprintd accept any enrolled finger).
+    pub async fn verify_start(&self, finger: Option<Finger>) -> zbus::Result<()> {
+        let name = finger.map_or("any", Finger::to_dbus_name);
+        let _: () = self.call_authorized("VerifyStart", &(name,)).await?;
         Ok(())
     }
 
@@ -258,7 +484,7 @@ impl Device {
 
     /// Claim device for user ("" for current user).
     pub async fn claim(&self, username: &str) -> zbus::Result<()> {
-        let _: () = self.call("Claim", &(username,)).await?;
+        let _: () = self.call_authorized("Claim", &(username,)).await?;
         Ok(())
     }
 
@@ -270,32 +496,63 @@ impl Device {
 
     /// Get device name.
     pub async fn name(&self) -> zbus::Result<String> {
-        let proxy = self.proxy().await?;
-        proxy.get_property::<String>("name").await
+        self.property("name").await
     }
 
     /// Get enrollment stages count (requires claimed device).
     pub async fn num_enroll_stages(&self) -> zbus::Result<i32> {
-        let proxy = self.proxy().await?;
-        proxy.get_property::<i32>("num-enroll-stages").await
+        self.property("num-enroll-stages").await
     }
 
     /// Get scan type ("press" or "swipe").
     pub async fn scan_type(&self) -> zbus::Result<String> {
-        let proxy = self.proxy().await?;
-        proxy.get_property::<String>("scan-type").await
+        self.property("scan-type").await
     }
 
     /// Check if finger is present on sensor.
     pub async fn finger_present(&self) -> zbus::Result<bool> {
-        let proxy = self.proxy().await?;
-        proxy.get_property::<bool>("finger-present").await
+        self.property("finger-present").await
     }
 
     /// Check if sensor needs finger.
     pub async fn finger_needed(&self) -> zbus::Result<bool> {
+        self.property("finger-needed").await
+    }
+
+    /// Poll the finger-present property every [`FINGER_PRESENT_POLL_INTERVAL`],
+    /// reporting changes until the task is dropped.
+    pub async fn poll_finger_present<F>(&self, mut handler: F) -> zbus::Result<()>
+    where
+        F: FnMut(bool) + Send,
+    {
+        let mut interval = tokio::time::interval(FINGER_PRESENT_POLL_INTERVAL);
+        let mut last = None;
+
+        loop {
+            interval.tick().await;
+            let present = self.finger_present().await?;
+            if last != Some(present) {
+                last = Some(present);
+                handler(present);
+            }
+        }
+    }
+
+    /// Listen for changes of the finger-needed property.
+    pub async fn listen_finger_needed<F>(&self, mut handler: F) -> zbus::Result<()>
+    where
+        F: FnMut(bool) + Send,
+    {
         let proxy = self.proxy().await?;
-        proxy.get_property::<bool>("finger-needed").await
+        let mut stream = proxy
+            .receive_property_changed::<bool>("finger-needed")
+            .await;
+
+        while let Some(change) = stream.next().await {
+            handler(change.get().await?);
+        }
+
+        Ok(())
     }
 
     /// Listen for VerifyFingerSelected signal.
@@ -345,6 +602,299 @@ impl Device {
 
         Ok(())
     }
+
+    /// Enroll finger and wait for the terminal status (requires claimed device).
+    pub async fn enroll_finger_to_completion(&self, finger: Finger) -> Result<(), EnrollError> {
+        self.enroll_finger_with_progress(finger, None, None, |_| {})
+            .await
+    }
+
+    /// Enroll finger, reporting every status update, and wait for the terminal status.
+    /// Subscribes before EnrollStart so no early signal is missed and always calls EnrollStop.
+    /// Some drivers send "enroll-data-full" before "enroll-completed"; if completion does not
+    /// follow within [`DATA_FULL_GRACE`] the capture is reported as incomplete.
+    /// With a `retry_limit`, enrollment is abandoned after that many scans in a row were
+    /// rejected without progress; with an `idle_timeout`, once no status arrives for that long.
+    pub async fn enroll_finger_with_progress<F>(
+        &self,
+        finger: Finger,
+        retry_limit: Option<usize>,
+        idle_timeout: Option<Duration>,
+        on_status: F,
+    ) -> Result<(), EnrollError>
+    where
+        F: FnMut(&EnrollStatusEvent) + Send,
+    {
+        let proxy = self.proxy().await?;
+        let stream = proxy.receive_signal("EnrollStatus").await?;
+
+        self.enroll_start(finger).await?;
+
+        let events = stream.map(|msg| {
+            msg.body()
+                .deserialize::<(String, bool)>()
+                .map(|(result, done)| EnrollStatusEvent { result, done })
+        });
+        let outcome = wait_for_enroll_outcome(events, retry_limit, idle_timeout, on_status).await;
+
+        // Runs on every exit, including a stream that ended without a terminal status
+        let _ = self.enroll_stop().await;
+        outcome
+    }
+
+    /// Verify finger once (`None` for any enrolled finger) and report whether it matched
+    /// (requires claimed device).
+    pub async fn verify_finger_once(&self, finger: Option<Finger>) -> Result<bool, VerifyError> {
+        let proxy = self.proxy().await?;
+        let mut stream = proxy.receive_signal("VerifyStatus").await?;
+
+        self.verify_start(finger).await?;
+
+        let outcome = loop {
+            let Some(msg) = stream.next().await else {
+                break Err(VerifyError::Interrupted);
+            };
+            let (result, done): (String, bool) = match msg.body().deserialize() {
+                Ok(body) => body,
+                Err(e) => break Err(e.into()),
+            };
+
+            if done {
+                break verify_outcome(result);
+            }
+        };
+
+        let _ = self.verify_stop().await;
+        outcome
+    }
+
+    /// Verify any enrolled finger once and report whether it matched, along with the finger
+    /// fprintd announced through VerifyFingerSelected (requires claimed device).
+    /// Devices able to identify announce "any" rather than a specific finger.
+    pub async fn verify_any_finger_once(
+        &self,
+    ) -> Result<(bool, Option<VerifyFingerSelectedEvent>), VerifyError> {
+        let proxy = self.proxy().await?;
+        // Subscribe to both before VerifyStart, the selection is announced right away
+        let mut selected_stream = proxy.receive_signal("VerifyFingerSelected").await?;
+        let mut status_stream = proxy.receive_signal("VerifyStatus").await?;
+
+        self.verify_start(None).await?;
+
+        let mut selected = None;
+        let outcome = loop {
+            tokio::select! {
+                // Handle the selection first if both are ready, it precedes the status
+                biased;
+                Some(msg) = selected_stream.next() => {
+                    match msg.body().deserialize::<(String,)>() {
+                        Ok((finger_name,)) => {
+                            selected = Some(VerifyFingerSelectedEvent { finger_name });
+                        }
+                        Err(e) => break Err(e.into()),
+                    }
+                }
+                next = status_stream.next() => {
+                    let Some(msg) = next else {
+                        break Err(VerifyError::Interrupted);
+                    };
+                    let (result, done): (String, bool) = match msg.body().deserialize() {
+                        Ok(body) => body,
+                        Err(e) => break Err(e.into()),
+                    };
+                    if done {
+                        break verify_outcome(result);
+                    }
+                }
+            }
+        };
+
+        let _ = self.verify_stop().await;
+        outcome.map(|matched| (matched, selected))
+    }
+}
+
+/// Map a terminal verify status to whether the finger matched.
+fn verify_outcome(result: String) -> Result<bool, VerifyError> {
+    match result.as_str() {
+        "verify-match" => Ok(true),
+        "verify-no-match" => Ok(false),
+        _ => Err(VerifyError::Failed(result)),
+    }
+}
+
+/// D-Bus error names meaning the called method is not implemented by the daemon.
+const UNSUPPORTED_ERROR_NAMES: &[&str] = &[
+    "org.freedesktop.DBus.Error.UnknownMethod",
+    "org.freedesktop.DBus.Error.NotSupported",
+];
+
+/// Whether a D-Bus error reports that the called method is not implemented.
+pub fn is_unsupported_method(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, _, _) => UNSUPPORTED_ERROR_NAMES.contains(&name.as_str()),
+        zbus::Error::FDO(e) => matches!(
+            **e,
+            zbus::fdo::Error::UnknownMethod(_) | zbus::fdo::Error::NotSupported(_)
+        ),
+        _ => false,
+    }
+}
+
+/// D-Bus error name fprintd uses when another client holds the device claim.
+const ALREADY_IN_USE_ERROR_NAME: &str = "net.reactivated.Fprint.Error.AlreadyInUse";
+
+/// Whether a D-Bus error reports that the device is claimed by another client.
+pub fn is_already_in_use(err: &zbus::Error) -> bool {
+    matches!(err, zbus::Error::MethodError(name, _, _) if name.as_str() == ALREADY_IN_USE_ERROR_NAME)
+}
+
+/// D-Bus error name fprintd returns when polkit denies an operation.
+const PERMISSION_DENIED_ERROR_NAME: &str = "net.reactivated.Fprint.Error.PermissionDenied";
+
+/// Whether a D-Bus error reports that polkit denied the operation.
+pub fn is_permission_denied(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, _, _) => name.as_str() == PERMISSION_DENIED_ERROR_NAME,
+        zbus::Error::FDO(e) => matches!(**e, zbus::fdo::Error::AccessDenied(_)),
+        _ => false,
+    }
+}
+
+/// D-Bus error name fprintd returns instead of an empty list of enrolled fingers.
+const NO_ENROLLED_PRINTS_ERROR_NAME: &str = "net.reactivated.Fprint.Error.NoEnrolledPrints";
+
+/// Whether a D-Bus error reports that the user has no enrolled fingerprints.
+pub fn is_no_enrolled_prints(err: &zbus::Error) -> bool {
+    matches!(err, zbus::Error::MethodError(name, _, _) if name.as_str() == NO_ENROLLED_PRINTS_ERROR_NAME)
+}
+
+/// D-Bus error names meaning the device is gone, e.g. the reader was unplugged.
+const NO_SUCH_DEVICE_ERROR_NAMES: &[&str] = &[
+    "net.reactivated.Fprint.Error.NoSuchDevice",
+    "org.freedesktop.DBus.Error.UnknownObject",
+];
+
+/// Whether a D-Bus error reports that the device no longer exists.
+pub fn is_no_such_device(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, _, _) => NO_SUCH_DEVICE_ERROR_NAMES.contains(&name.as_str()),
+        zbus::Error::FDO(e) => matches!(**e, zbus::fdo::Error::UnknownObject(_)),
+        _ => false,
+    }
+}
+
+/// How long to wait for "enroll-completed" after "enroll-data-full".
+pub const DATA_FULL_GRACE: Duration = Duration::from_secs(5);
+
+/// How long a D-Bus call may take before fprintd is considered unresponsive.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout for calls that may wait for the user to answer a polkit prompt.
+pub const AUTHORIZED_CALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Run a D-Bus call, failing with an error naming `what` if it takes longer than `timeout`.
+async fn with_timeout<T>(
+    what: &str,
+    timeout: Duration,
+    call: impl std::future::Future<Output = zbus::Result<T>>,
+) -> zbus::Result<T> {
+    tokio::time::timeout(timeout, call)
+        .await
+        .unwrap_or_else(|_| {
+            Err(zbus::Error::Failure(format!(
+                "{} timed out after {}s; fprintd is not responding",
+                what,
+                timeout.as_secs()
+            )))
+        })
+}
+
+/// How often finger-present is polled while enrolling.
+pub const FINGER_PRESENT_POLL_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Sensor state changes reported while enrolling.
+#[derive(Debug, Clone, Copy)]
+pub enum SensorEvent {
+    /// The sensor started or stopped waiting for a finger.
+    FingerNeeded(bool),
+    /// A finger was placed on or lifted from the sensor.
+    FingerPresent(bool),
+    /// The reader is a press sensor needing this many scans; sent before enrollment starts.
+    PressStages(usize),
+}
+
+/// Error returned by [`Device::enroll_finger_to_completion`].
+#[derive(Debug)]
+pub enum EnrollError {
+    /// D-Bus call or signal subscription failed.
+    Dbus(zbus::Error),
+    /// Enrollment finished with a non-success status (e.g. "enroll-failed").
+    Failed(String),
+    /// "enroll-data-full" was reported but "enroll-completed" never followed.
+    CaptureIncomplete,
+    /// Signal stream ended before a terminal status was received.
+    Interrupted,
+    /// This many scans in a row were rejected, so enrollment was abandoned.
+    TooManyRetries(usize),
+    /// No status update arrived within this long, so enrollment was abandoned.
+    TimedOut(Duration),
+}
+
+impl fmt::Display for EnrollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnrollError::Dbus(e) => write!(f, "D-Bus error: {}", e),
+            EnrollError::Failed(status) => write!(f, "Enrollment ended with '{}'", status),
+            EnrollError::CaptureIncomplete => {
+                write!(f, "Enrollment data was captured but never completed")
+            }
+            EnrollError::Interrupted => write!(f, "Enrollment status stream ended unexpectedly"),
+            EnrollError::TooManyRetries(count) => {
+                write!(f, "Gave up after {} rejected scans in a row", count)
+            }
+            EnrollError::TimedOut(timeout) => {
+                write!(f, "No scan within {} seconds", timeout.as_secs())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnrollError {}
+
+impl From<zbus::Error> for EnrollError {
+    fn from(e: zbus::Error) -> Self {
+        EnrollError::Dbus(e)
+    }
+}
+
+/// Error returned by [`Device::verify_finger_once`].
+#[derive(Debug)]
+pub enum VerifyError {
+    /// D-Bus call or signal subscription failed.
+    Dbus(zbus::Error),
+    /// Verification finished with neither match nor no-match (e.g. "verify-disconnected").
+    Failed(String),
+    /// Signal stream ended before a terminal status was received.
+    Interrupted,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Dbus(e) => write!(f, "D-Bus error: {}", e),
+            VerifyError::Failed(status) => write!(f, "Verification ended with '{}'", status),
+            VerifyError::Interrupted => write!(f, "Verify status stream ended unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<zbus::Error> for VerifyError {
+    fn from(e: zbus::Error) -> Self {
+        VerifyError::Dbus(e)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -364,6 +914,88 @@ pub struct EnrollStatusEvent {
     pub done: bool,
 }
 
+/// Statuses asking for another scan without counting one as captured.
+const RETRY_STATUSES: &[&str] = &[
+    "enroll-retry-scan",
+    "enroll-remove-and-retry",
+    "enroll-swipe-too-short",
+    "enroll-finger-not-centered",
+    "enroll-too-fast",
+];
+
+/// Whether an enroll status asks for another scan without capturing one.
+pub fn is_retry_status(result: &str) -> bool {
+    RETRY_STATUSES.contains(&result)
+}
+
+/// Consume enrollment status events until a terminal status arrives or the stream ends.
+/// A stream that ends without `done` is reported as [`EnrollError::Interrupted`], or as
+/// [`EnrollError::CaptureIncomplete`] after "enroll-data-full".
+/// `retry_limit` retry statuses in a row end it with [`EnrollError::TooManyRetries`], and
+/// `idle_timeout` without any status with [`EnrollError::TimedOut`].
+async fn wait_for_enroll_outcome<S, F>(
+    mut events: S,
+    retry_limit: Option<usize>,
+    idle_timeout: Option<Duration>,
+    mut on_status: F,
+) -> Result<(), EnrollError>
+where
+    S: Stream<Item = zbus::Result<EnrollStatusEvent>> + Unpin,
+    F: FnMut(&EnrollStatusEvent),
+{
+    let mut data_full_seen = false;
+    let mut retries = 0;
+
+    loop {
+        let next = if data_full_seen {
+            match tokio::time::timeout(DATA_FULL_GRACE, events.next()).await {
+                Ok(next) => next,
+                Err(_) => return Err(EnrollError::CaptureIncomplete),
+            }
+        } else if let Some(timeout) = idle_timeout {
+            match tokio::time::timeout(timeout, events.next()).await {
+                Ok(next) => next,
+                Err(_) => return Err(EnrollError::TimedOut(timeout)),
+            }
+        } else {
+            events.next().await
+        };
+
+        let Some(evt) = next else {
+            return Err(if data_full_seen {
+                EnrollError::CaptureIncomplete
+            } else {
+                EnrollError::Interrupted
+            });
+        };
+        let evt = evt?;
+        on_status(&evt);
+
+        if is_retry_status(&evt.result) && !evt.done {
+            retries += 1;
+            if retry_limit.is_some_and(|limit| retries >= limit) {
+                return Err(EnrollError::TooManyRetries(retries));
+            }
+        } else if evt.result == "enroll-stage-passed" {
+            retries = 0;
+        }
+
+        if evt.result == "enroll-data-full" {
+            // Wait for the real completion even if the driver flags this as final
+            data_full_seen = true;
+            continue;
+        }
+
+        if evt.done {
+            return if evt.result == "enroll-completed" {
+                Ok(())
+            } else {
+                Err(EnrollError::Failed(evt.result))
+            };
+        }
+    }
+}
+
 /// Find first available device.
 pub async fn first_device(client: &Client) -> zbus::Result<Option<Device>> {
     let mgr = client.manager();
@@ -385,3 +1017,562 @@ pub async fn first_device(client: &Client) -> zbus::Result<Option<Device>> {
         Err(e) => Err(e),
     }
 }
+
+/// List all available devices.
+pub async fn all_devices(client: &Client) -> zbus::Result<Vec<Device>> {
+    let paths = client.manager().get_devices().await?;
+    Ok(paths.into_iter().map(|path| client.device(path)).collect())
+}
+
+/// Tests against python-dbusmock's fprintd template on a private bus.
+/// Run with `cargo test -p xfprintd-gui -- --ignored` where `dbus-daemon` and
+/// `python3 -m dbusmock` are available.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    use zbus::fdo::DBusProxy;
+
+    const MANAGER_MOCK_IFACE: &str = "net.reactivated.Fprint.Manager.Mock";
+    const DEVICE_MOCK_IFACE: &str = "net.reactivated.Fprint.Device.Mock";
+    const USER: &str = "toto";
+
+    /// Private dbus-daemon with the fprintd mock registered on it.
+    struct MockBus {
+        daemon: Child,
+        mock: Child,
+        client: Client,
+    }
+
+    impl Drop for MockBus {
+        fn drop(&mut self) {
+            let _ = self.mock.kill();
+            let _ = self.mock.wait();
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    impl MockBus {
+        async fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("failed to start dbus-daemon");
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .expect("failed to read bus address");
+            let address = address.trim().to_string();
+
+            // The template asks for the system bus; point both at the private daemon
+            let mock = Command::new("python3")
+                .args(["-m", "dbusmock", "--template", "fprintd"])
+                .env("DBUS_SYSTEM_BUS_ADDRESS", &address)
+                .env("DBUS_SESSION_BUS_ADDRESS", &address)
+                .stdout(Stdio::null())
+                .spawn()
+                .expect("failed to start python-dbusmock");
+
+            let conn = zbus::connection::Builder::address(address.as_str())
+                .expect("invalid bus address")
+                .build()
+                .await
+                .expect("failed to connect to private bus");
+
+            let dbus = DBusProxy::new(&conn).await.unwrap();
+            let mut ready = false;
+            for _ in 0..50 {
+                if dbus
+                    .name_has_owner(SERVICE.try_into().unwrap())
+                    .await
+                    .unwrap_or(false)
+                {
+                    ready = true;
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert!(ready, "fprintd mock never appeared on the bus");
+
+            MockBus {
+                daemon,
+                mock,
+                client: Client::from_connection(conn),
+            }
+        }
+
+        async fn call_mock<B>(&self, path: &str, iface: &str, method: &str, body: &B)
+        where
+            B: Serialize + Type,
+        {
+            self.client
+                .connection()
+                .call_method(Some(SERVICE), path, Some(iface), method, body)
+                .await
+                .unwrap_or_else(|e| panic!("{} failed: {}", method, e));
+        }
+
+        /// Add a mock reader and return it.
+        async fn add_device(&self, name: &str, stages: i32, scan_type: &str) -> Device {
+            self.call_mock(
+                MANAGER_PATH,
+                MANAGER_MOCK_IFACE,
+                "AddDevice",
+                &(name, stages, scan_type),
+            )
+            .await;
+
+            let paths = self.client.manager().get_devices().await.unwrap();
+            self.client.device(paths.last().cloned().unwrap())
+        }
+
+        async fn emit(&self, device: &Device, signal: &str, body: &(&str, bool)) {
+            self.call_mock(device.object_path(), DEVICE_MOCK_IFACE, signal, body)
+                .await;
+        }
+    }
+
+    /// Give a spawned operation time to subscribe and start before signals are emitted.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    /// Receive the next event forwarded by a listener, failing the test if none arrives.
+    async fn next_event<T>(rx: &mut tokio::sync::mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for a signal")
+            .expect("listener ended early")
+    }
+
+    fn status(result: &str, done: bool) -> zbus::Result<EnrollStatusEvent> {
+        Ok(EnrollStatusEvent {
+            result: result.to_string(),
+            done,
+        })
+    }
+
+    #[tokio::test]
+    async fn enroll_stream_ending_without_done_is_interrupted() {
+        let events = futures_util::stream::iter(vec![
+            status("enroll-stage-passed", false),
+            status("enroll-retry-scan", false),
+        ]);
+        let mut seen = Vec::new();
+
+        let result =
+            wait_for_enroll_outcome(events, None, None, |evt| seen.push(evt.result.clone())).await;
+
+        assert!(matches!(result, Err(EnrollError::Interrupted)));
+        assert_eq!(seen, ["enroll-stage-passed", "enroll-retry-scan"]);
+    }
+
+    #[tokio::test]
+    async fn enroll_stream_ending_after_data_full_is_incomplete() {
+        let events = futures_util::stream::iter(vec![status("enroll-data-full", true)]);
+
+        let result = wait_for_enroll_outcome(events, None, None, |_| {}).await;
+
+        assert!(matches!(result, Err(EnrollError::CaptureIncomplete)));
+    }
+
+    #[tokio::test]
+    async fn enroll_gives_up_after_consecutive_retries() {
+        let events = futures_util::stream::iter(vec![
+            status("enroll-retry-scan", false),
+            status("enroll-finger-not-centered", false),
+            status("enroll-stage-passed", false),
+            status("enroll-remove-and-retry", false),
+            status("enroll-swipe-too-short", false),
+            status("enroll-too-fast", false),
+            status("enroll-completed", true),
+        ]);
+        let mut seen = 0;
+
+        let result = wait_for_enroll_outcome(events, Some(3), None, |_| seen += 1).await;
+
+        assert!(matches!(result, Err(EnrollError::TooManyRetries(3))));
+        assert_eq!(seen, 6);
+    }
+
+    #[tokio::test]
+    async fn enroll_times_out_without_status() {
+        let events = futures_util::stream::iter(vec![status("enroll-stage-passed", false)])
+            .chain(futures_util::stream::pending());
+        let timeout = Duration::from_millis(50);
+        let mut seen = 0;
+
+        let result = wait_for_enroll_outcome(events, None, Some(timeout), |_| seen += 1).await;
+
+        assert!(matches!(result, Err(EnrollError::TimedOut(t)) if t == timeout));
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn only_closed_connections_count_as_disconnected() {
+        let io_error =
+            |kind| zbus::Error::InputOutput(Arc::new(io::Error::new(kind, "socket closed")));
+        assert!(is_disconnected(&io_error(io::ErrorKind::BrokenPipe)));
+        assert!(is_disconnected(&io_error(io::ErrorKind::UnexpectedEof)));
+        assert!(!is_disconnected(&io_error(io::ErrorKind::PermissionDenied)));
+        assert!(!is_disconnected(&zbus::Error::Failure(
+            "Enroll timed out".to_string()
+        )));
+    }
+
+    #[test]
+    fn vanished_fprintd_counts_as_service_lost() {
+        let fdo = |e| zbus::Error::FDO(Box::new(e));
+        assert!(is_service_lost(&fdo(zbus::fdo::Error::ServiceUnknown(
+            "The name is not activatable".to_string()
+        ))));
+        assert!(is_service_lost(&fdo(zbus::fdo::Error::NoReply(
+            "Remote peer disconnected".to_string()
+        ))));
+        assert!(!is_service_lost(&fdo(zbus::fdo::Error::AccessDenied(
+            "Not authorized".to_string()
+        ))));
+        assert!(!is_service_lost(&zbus::Error::InputOutput(Arc::new(
+            io::Error::new(io::ErrorKind::BrokenPipe, "socket closed")
+        ))));
+    }
+
+    #[tokio::test]
+    async fn enroll_stream_stops_at_terminal_status() {
+        let events = futures_util::stream::iter(vec![
+            status("enroll-completed", true),
+            status("enroll-stage-passed", false),
+        ]);
+        let mut seen = 0;
+
+        let result = wait_for_enroll_outcome(events, None, None, |_| seen += 1).await;
+
+        assert!(result.is_ok());
+        assert_eq!(seen, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn no_devices_yields_none() {
+        let bus = MockBus::start().await;
+        assert!(bus.client.manager().get_devices().await.unwrap().is_empty());
+        assert!(first_device(&bus.client).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn device_properties_are_read() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "swipe").await;
+
+        assert_eq!(device.name().await.unwrap(), "Mock Reader");
+        assert_eq!(device.num_enroll_stages().await.unwrap(), 5);
+        assert_eq!(device.scan_type().await.unwrap(), "swipe");
+
+        let first = first_device(&bus.client).await.unwrap().unwrap();
+        assert_eq!(first.object_path(), device.object_path());
+        assert_eq!(all_devices(&bus.client).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn list_enrolled_fingers_returns_mock_state() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["left-thumb", "right-index-finger"]),
+        )
+        .await;
+
+        let mut fingers = device.list_enrolled_fingers(USER).await.unwrap();
+        fingers.sort();
+        assert_eq!(fingers, ["left-thumb", "right-index-finger"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn listing_does_not_need_the_claim() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["left-thumb"]),
+        )
+        .await;
+
+        assert_eq!(
+            device.list_enrolled_fingers(USER).await.unwrap(),
+            ["left-thumb"]
+        );
+        // Still readable while the device is claimed, e.g. by a login screen
+        device.claim(USER).await.unwrap();
+        assert_eq!(
+            device.list_enrolled_fingers(USER).await.unwrap(),
+            ["left-thumb"]
+        );
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn second_claim_is_already_in_use() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+
+        device.claim(USER).await.unwrap();
+        let err = device.claim(USER).await.unwrap_err();
+        assert!(is_already_in_use(&err), "unexpected error: {}", err);
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn enroll_reports_progress_until_completed() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 2, "press").await;
+        device.claim(USER).await.unwrap();
+
+        let task = {
+            let device = device.clone();
+            tokio::spawn(async move {
+                let mut seen = Vec::new();
+                let result = device
+                    .enroll_finger_with_progress(Finger::RightIndex, None, None, |evt| {
+                        seen.push(evt.result.clone())
+                    })
+                    .await;
+                (result, seen)
+            })
+        };
+
+        settle().await;
+        bus.emit(&device, "EmitEnrollStatus", &("enroll-stage-passed", false))
+            .await;
+        bus.emit(&device, "EmitEnrollStatus", &("enroll-completed", true))
+            .await;
+
+        let (result, seen) = task.await.unwrap();
+        assert!(result.is_ok(), "enrollment failed: {:?}", result);
+        assert_eq!(seen, ["enroll-stage-passed", "enroll-completed"]);
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn enroll_failure_is_reported() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 2, "press").await;
+        device.claim(USER).await.unwrap();
+
+        let task = {
+            let device = device.clone();
+            tokio::spawn(async move { device.enroll_finger_to_completion(Finger::LeftThumb).await })
+        };
+
+        settle().await;
+        bus.emit(&device, "EmitEnrollStatus", &("enroll-failed", true))
+            .await;
+
+        match task.await.unwrap() {
+            Err(EnrollError::Failed(status)) => assert_eq!(status, "enroll-failed"),
+            other => panic!("unexpected enroll result: {:?}", other),
+        }
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn verify_reports_match_and_no_match() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["right-index-finger"]),
+        )
+        .await;
+        device.claim(USER).await.unwrap();
+
+        for (status, expected) in [("verify-match", true), ("verify-no-match", false)] {
+            let task = {
+                let device = device.clone();
+                tokio::spawn(async move { device.verify_finger_once(None).await })
+            };
+
+            settle().await;
+            bus.emit(&device, "EmitVerifyStatus", &(status, true)).await;
+
+            assert_eq!(task.await.unwrap().unwrap(), expected);
+        }
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn verify_any_reports_selected_finger() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["right-index-finger"]),
+        )
+        .await;
+        device.claim(USER).await.unwrap();
+
+        let task = {
+            let device = device.clone();
+            tokio::spawn(async move { device.verify_any_finger_once().await })
+        };
+
+        settle().await;
+        bus.emit(&device, "EmitVerifyStatus", &("verify-match", true))
+            .await;
+
+        let (matched, selected) = task.await.unwrap().unwrap();
+        assert!(matched);
+        assert!(selected.is_some(), "VerifyFingerSelected was not reported");
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn default_device_is_the_added_reader() {
+        let bus = MockBus::start().await;
+        assert!(bus.client.manager().get_default_device().await.is_err());
+
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+        let default = bus.client.manager().get_default_device().await.unwrap();
+        assert_eq!(default.as_str(), device.object_path());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn delete_enrolled_fingers_clears_mock_state() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["left-thumb", "right-index-finger"]),
+        )
+        .await;
+
+        device.claim(USER).await.unwrap();
+        device.delete_enrolled_fingers().await.unwrap();
+        device.release().await.unwrap();
+
+        // fprintd reports an empty list as NoEnrolledPrints
+        let err = device.list_enrolled_fingers(USER).await.unwrap_err();
+        assert!(is_no_enrolled_prints(&err), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn enroll_status_listener_decodes_signals() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 2, "press").await;
+        device.claim(USER).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let listener = {
+            let device = device.clone();
+            tokio::spawn(async move {
+                device
+                    .listen_enroll_status(|evt| {
+                        let _ = tx.send((evt.result, evt.done));
+                    })
+                    .await
+            })
+        };
+
+        settle().await;
+        device.enroll_start(Finger::RightIndex).await.unwrap();
+        bus.emit(&device, "EmitEnrollStatus", &("enroll-stage-passed", false))
+            .await;
+        bus.emit(&device, "EmitEnrollStatus", &("enroll-completed", true))
+            .await;
+
+        assert_eq!(
+            next_event(&mut rx).await,
+            ("enroll-stage-passed".to_string(), false)
+        );
+        assert_eq!(
+            next_event(&mut rx).await,
+            ("enroll-completed".to_string(), true)
+        );
+
+        listener.abort();
+        device.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon and python-dbusmock"]
+    async fn verify_listeners_decode_signals() {
+        let bus = MockBus::start().await;
+        let device = bus.add_device("Mock Reader", 5, "press").await;
+        bus.call_mock(
+            device.object_path(),
+            DEVICE_MOCK_IFACE,
+            "SetEnrolledFingers",
+            &(USER, vec!["right-index-finger"]),
+        )
+        .await;
+        device.claim(USER).await.unwrap();
+
+        let (selected_tx, mut selected_rx) = tokio::sync::mpsc::unbounded_channel();
+        let selected_listener = {
+            let device = device.clone();
+            tokio::spawn(async move {
+                device
+                    .listen_verify_finger_selected(|evt| {
+                        let _ = selected_tx.send(evt.finger_name);
+                    })
+                    .await
+            })
+        };
+        let (status_tx, mut status_rx) = tokio::sync::mpsc::unbounded_channel();
+        let status_listener = {
+            let device = device.clone();
+            tokio::spawn(async move {
+                device
+                    .listen_verify_status(|evt| {
+                        let _ = status_tx.send((evt.result, evt.done));
+                    })
+                    .await
+            })
+        };
+
+        settle().await;
+        device.verify_start(Some(Finger::RightIndex)).await.unwrap();
+        assert_eq!(next_event(&mut selected_rx).await, "right-index-finger");
+
+        bus.emit(&device, "EmitVerifyStatus", &("verify-no-match", true))
+            .await;
+        assert_eq!(
+            next_event(&mut status_rx).await,
+            ("verify-no-match".to_string(), true)
+        );
+
+        selected_listener.abort();
+        status_listener.abort();
+        device.verify_stop().await.unwrap();
+        device.release().await.unwrap();
+    }
+}
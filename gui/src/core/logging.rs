@@ -0,0 +1,95 @@
+//! Logger that keeps recent lines in memory for bug reports and can tee them to a file.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use simple_logger::SimpleLogger;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Maximum number of log lines kept for diagnostics.
+const MAX_LINES: usize = 500;
+
+/// Most recent log lines, oldest first.
+static LINES: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(MAX_LINES)));
+
+/// `SimpleLogger` wrapper that also records every emitted line.
+struct BufferedLogger {
+    inner: SimpleLogger,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+
+        let line = format!(
+            "{:<5} [{}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+
+        if let Ok(mut lines) = LINES.lock() {
+            if lines.len() == MAX_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Install the buffered logger as the global logger.
+/// `level` overrides `RUST_LOG`, which in turn overrides `default`; lines are
+/// appended to `log_file` when given.
+pub fn init(
+    default: LevelFilter,
+    level: Option<LevelFilter>,
+    log_file: Option<&Path>,
+) -> io::Result<()> {
+    let mut inner = SimpleLogger::new().with_level(default).env();
+    if let Some(level) = level {
+        inner = inner.with_level(level);
+    }
+
+    let file = log_file
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose()?
+        .map(Mutex::new);
+
+    log::set_max_level(inner.max_level());
+    log::set_boxed_logger(Box::new(BufferedLogger { inner, file })).map_err(io::Error::other)
+}
+
+/// Get the recorded log lines, oldest first.
+pub fn recent_lines() -> Vec<String> {
+    LINES
+        .lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
@@ -3,6 +3,7 @@
 pub mod context;
 pub mod device_manager;
 pub mod fprintd;
+pub mod logging;
 pub mod system;
 pub mod util;
 
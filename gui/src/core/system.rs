@@ -1,49 +1,128 @@
+use crate::config::{self, DistributionCheck};
 use crate::core::util;
+use crate::pam::helper::{self, run_pkexec, PkexecOutcome};
 use gtk4::prelude::*;
-use gtk4::{ApplicationWindow, Builder, Button, Label};
-use log::{error, info};
+use gtk4::{ApplicationWindow, Box as GtkBox, Builder, Button, CheckButton, Label};
+use log::{error, info, warn};
+use std::os::unix::fs::PermissionsExt;
 
-/// Check if current distribution is supported and show error dialog if not.
-pub fn check_distribution_support(main_window: &ApplicationWindow) {
+/// Check if current distribution is supported and warn if not.
+/// `force` skips the check and `allow_unsupported` only shows a dismissible banner;
+/// otherwise the configured [`DistributionCheck`] decides whether the user may continue.
+pub fn check_distribution_support(
+    main_window: &ApplicationWindow,
+    builder: &Builder,
+    force: bool,
+    allow_unsupported: bool,
+) {
     info!("Checking Linux distribution compatibility");
-    if !util::is_supported_distribution() {
-        error!("Unsupported distribution detected");
-        let distro_name = util::get_distribution_name().unwrap_or_else(|| "Unknown".to_string());
-        error!("Current distribution: {}", distro_name);
-        error!("This application is designed specifically for XeroLinux");
-        error!("Visit https://xerolinux.xyz/ to learn more about XeroLinux");
-
-        // Load error dialog from UI file
-        let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/error_dialog.ui");
-
-        let error_window: gtk4::Window = builder
-            .object("error_window")
-            .expect("Failed to get error_window");
-
-        let distro_label: Label = builder
-            .object("distro_label")
-            .expect("Failed to get distro_label");
-
-        let ok_button: Button = builder
-            .object("ok_button")
-            .expect("Failed to get ok_button");
-
-        distro_label.set_label(&format!("Current distribution: {}", distro_name));
-        error_window.set_transient_for(Some(main_window));
-        let main_window_clone = main_window.clone();
-        ok_button.connect_clicked(move |_| {
-            main_window_clone.close();
-            std::process::exit(1);
-        });
-
-        error_window.show();
-    } else {
+    if util::is_supported_distribution() {
         info!("XeroLinux detected - proceeding with application startup");
+        return;
+    }
+
+    let distro_name = util::get_distribution_name().unwrap_or_else(|| "Unknown".to_string());
+    let mode = config::distribution_check();
+    if force || mode == DistributionCheck::Off {
+        warn!(
+            "Running on unsupported distribution '{}' (check skipped)",
+            distro_name
+        );
+        return;
+    }
+    if allow_unsupported {
+        warn!(
+            "Running on unsupported distribution '{}' (allowed by override)",
+            distro_name
+        );
+        show_unsupported_banner(builder, &distro_name);
+        return;
+    }
+
+    warn!("Unsupported distribution detected: {}", distro_name);
+    warn!("This application is designed specifically for XeroLinux");
+    warn!("Visit https://xerolinux.xyz/ to learn more about XeroLinux");
+
+    // Load warning dialog from UI file
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/error_dialog.ui");
+
+    let error_window: gtk4::Window = builder
+        .object("error_window")
+        .expect("Failed to get error_window");
+
+    let distro_label: Label = builder
+        .object("distro_label")
+        .expect("Failed to get distro_label");
+
+    let quit_button: Button = builder
+        .object("quit_button")
+        .expect("Failed to get quit_button");
+
+    let continue_button: Button = builder
+        .object("continue_button")
+        .expect("Failed to get continue_button");
+
+    let dont_warn_check: CheckButton = builder
+        .object("dont_warn_check")
+        .expect("Failed to get dont_warn_check");
+
+    distro_label.set_label(&format!("Current distribution: {}", distro_name));
+    error_window.set_transient_for(Some(main_window));
+
+    if mode == DistributionCheck::Strict {
+        error!("Strict distribution check is enabled, refusing to continue");
+        continue_button.set_visible(false);
+        dont_warn_check.set_visible(false);
     }
+
+    let main_window_clone = main_window.clone();
+    quit_button.connect_clicked(move |_| {
+        main_window_clone.close();
+        std::process::exit(1);
+    });
+
+    let error_window_clone = error_window.clone();
+    continue_button.connect_clicked(move |_| {
+        info!("User chose to continue on unsupported distribution");
+        if dont_warn_check.is_active() {
+            if let Err(e) = config::disable_distribution_check() {
+                warn!("Failed to save distribution check preference: {}", e);
+            }
+        }
+        error_window_clone.close();
+    });
+
+    error_window.show();
 }
 
-/// Check fprintd service status.
-pub fn check_fprintd_service() {
+/// Show the unsupported distribution banner above the main page until it is dismissed.
+fn show_unsupported_banner(builder: &Builder, distro_name: &str) {
+    let banner: GtkBox = builder
+        .object("distro_banner")
+        .expect("Failed to get distro_banner");
+    let label: Label = builder
+        .object("distro_banner_label")
+        .expect("Failed to get distro_banner_label");
+    let dismiss_button: Button = builder
+        .object("distro_banner_dismiss")
+        .expect("Failed to get distro_banner_dismiss");
+
+    label.set_label(&format!(
+        "{} is not a supported distribution. XFPrintD GUI is built for XeroLinux and may not work as expected.",
+        distro_name
+    ));
+
+    let banner_clone = banner.clone();
+    dismiss_button.connect_clicked(move |_| {
+        info!("User dismissed the unsupported distribution banner");
+        banner_clone.set_visible(false);
+    });
+
+    banner.set_visible(true);
+}
+
+/// Check fprintd service status, returning whether it is running.
+pub fn check_fprintd_service() -> bool {
     match std::process::Command::new("systemctl")
         .args(["is-active", "fprintd"])
         .output()
@@ -53,43 +132,154 @@ pub fn check_fprintd_service() {
             let status = status_output.trim();
             if status == "active" {
                 info!("fprintd service is running");
+                true
             } else {
                 log::warn!("fprintd service status: {}", status);
                 log::warn!("You may need to start fprintd: sudo systemctl start fprintd");
+                false
             }
         }
         Err(e) => {
+            // Without systemctl we cannot tell, so don't nag the user
             log::warn!("Cannot check fprintd service status: {}", e);
+            true
         }
     }
 }
 
-/// Check for helper tool availability.
-pub fn check_helper_tool() {
-    let username = std::env::var("USER").unwrap_or_default();
-    info!("Running as user: '{}'", username);
+/// Check that the fingerprint PAM module is installed, returning whether it was found.
+pub fn check_pam_fprintd_module() -> bool {
+    match helper::find_fprintd_module() {
+        Some(path) => {
+            info!("Fingerprint PAM module found at {}", path.display());
+            true
+        }
+        None => {
+            log::warn!("{} not found", helper::FPRINTD_MODULE);
+            log::warn!("PAM switches are disabled until fprintd-pam is installed");
+            false
+        }
+    }
+}
 
-    let helper_path = "/opt/xfprintd-gui/xfprintd-gui-helper";
-    if std::path::Path::new(helper_path).exists() {
-        info!("Helper tool found at: {}", helper_path);
+/// Start the fprintd service via pkexec, optionally enabling it on boot.
+pub fn start_fprintd_service(enable_on_boot: bool) -> PkexecOutcome {
+    if enable_on_boot {
+        info!("Enabling and starting fprintd service");
+        run_pkexec("systemctl", &["enable", "--now", "fprintd"])
     } else {
-        log::warn!("Helper tool not found at: {}", helper_path);
-        log::warn!("PAM configuration features may not work");
+        info!("Starting fprintd service");
+        run_pkexec("systemctl", &["start", "fprintd"])
+    }
+}
+
+/// Check for helper tool availability, returning whether it exists and is executable.
+pub fn check_helper_tool() -> bool {
+    let username = util::current_username();
+    info!("Running as user: '{}'", username);
+
+    let helper_path = config::helper::BINARY_PATH;
+    match std::fs::metadata(helper_path) {
+        Ok(metadata) if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 => {
+            info!("Helper tool found at: {}", helper_path);
+            true
+        }
+        Ok(_) => {
+            log::warn!("Helper tool at {} is not executable", helper_path);
+            log::warn!("PAM configuration features may not work");
+            false
+        }
+        Err(_) => {
+            log::warn!("Helper tool not found at: {}", helper_path);
+            log::warn!("PAM configuration features may not work");
+            false
+        }
+    }
+}
+
+/// Get the version the helper tool reports with `--version`, e.g. "0.1.0".
+pub fn get_helper_version() -> Option<String> {
+    let output = std::process::Command::new(config::helper::BINARY_PATH)
+        .arg("--version")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
     }
+
+    // Printed as "xfprintd-gui-helper 0.1.0" on the first line
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
+/// Installed helper version when it differs from the GUI's, as after a partial upgrade.
+/// Both are built from the same workspace, so anything but an exact match is a mismatch.
+/// Returns None when the versions match or the helper reports none.
+pub fn helper_version_mismatch() -> Option<String> {
+    let version = get_helper_version()?;
+    (version != config::app_info::VERSION).then_some(version)
+}
+
+/// Warn with a banner when the helper tool comes from another release than the GUI.
+pub fn check_helper_version(builder: &Builder) {
+    let Some(helper_version) = helper_version_mismatch() else {
+        return;
+    };
+
+    warn!(
+        "Helper tool version {} does not match GUI version {}; PAM changes may fail",
+        helper_version,
+        config::app_info::VERSION
+    );
+    show_helper_version_banner(builder, &helper_version);
+}
+
+/// Show the dismissible banner suggesting a reinstall after a partial upgrade.
+fn show_helper_version_banner(builder: &Builder, helper_version: &str) {
+    let banner: GtkBox = builder
+        .object("helper_banner")
+        .expect("Failed to get helper_banner");
+    let label: Label = builder
+        .object("helper_banner_label")
+        .expect("Failed to get helper_banner_label");
+    let dismiss_button: Button = builder
+        .object("helper_banner_dismiss")
+        .expect("Failed to get helper_banner_dismiss");
+
+    label.set_label(&format!(
+        "The installed helper tool is version {} but XFPrintD GUI is version {}. Reinstall xfprintd-gui so both come from the same release.",
+        helper_version,
+        config::app_info::VERSION
+    ));
+
+    let banner_clone = banner.clone();
+    dismiss_button.connect_clicked(move |_| {
+        info!("User dismissed the helper version banner");
+        banner_clone.set_visible(false);
+    });
+
+    banner.set_visible(true);
 }
 
-/// Check for pkexec availability.
-pub fn check_pkexec_availability() {
+/// Check for pkexec availability, returning whether it was found.
+pub fn check_pkexec_availability() -> bool {
     match std::process::Command::new("which").arg("pkexec").output() {
         Ok(output) => {
             if output.status.success() {
                 info!("pkexec is available for privilege escalation");
+                true
             } else {
                 log::warn!("pkexec not found - PAM configuration will not work");
+                false
             }
         }
         Err(_) => {
             log::warn!("Cannot check for pkexec availability");
+            false
         }
     }
 }
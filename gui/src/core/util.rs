@@ -1,30 +1,139 @@
-/// Format finger name for display (replace dashes, capitalize).
-pub fn display_finger_name(name: &str) -> String {
-    if name.is_empty() {
-        return String::new();
-    }
-    let mut s = name.replace('-', " ");
-    let mut chars = s.chars();
-    if let Some(first) = chars.next() {
-        let upper = first.to_ascii_uppercase().to_string();
-        s.replace_range(0..first.len_utf8(), &upper);
+use log::warn;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Hand a finger belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Hand {
+    /// Human readable hand name.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Hand::Left => "Left Hand",
+            Hand::Right => "Right Hand",
+        }
     }
-    s
 }
 
-/// Create short finger name by removing common words.
-pub fn create_short_finger_name(display_name: &str) -> String {
-    let mut short_name = display_name
-        .replace(" finger", "")
-        .replace("Left ", "")
-        .replace("Right ", "");
+/// Finger as understood by fprintd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Finger {
+    LeftThumb,
+    LeftIndex,
+    LeftMiddle,
+    LeftRing,
+    LeftLittle,
+    RightThumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightLittle,
+}
+
+/// All fingers in fprintd order (left hand first).
+pub const FINGERS: &[Finger] = &[
+    Finger::LeftThumb,
+    Finger::LeftIndex,
+    Finger::LeftMiddle,
+    Finger::LeftRing,
+    Finger::LeftLittle,
+    Finger::RightThumb,
+    Finger::RightIndex,
+    Finger::RightMiddle,
+    Finger::RightRing,
+    Finger::RightLittle,
+];
 
-    if let Some(first_char) = short_name.chars().next() {
-        short_name =
-            first_char.to_uppercase().collect::<String>() + &short_name[first_char.len_utf8()..];
+impl Finger {
+    /// Parse an fprintd finger name (e.g. "left-index-finger").
+    /// Names with or without the "-finger" suffix are accepted, since fprintd versions differ.
+    pub fn from_dbus_name(name: &str) -> Option<Self> {
+        let name = normalize_finger_name(name);
+        FINGERS
+            .iter()
+            .copied()
+            .find(|finger| normalize_finger_name(finger.to_dbus_name()) == name)
     }
 
-    short_name
+    /// Finger name used on the fprintd D-Bus API.
+    pub fn to_dbus_name(self) -> &'static str {
+        match self {
+            Finger::LeftThumb => "left-thumb",
+            Finger::LeftIndex => "left-index-finger",
+            Finger::LeftMiddle => "left-middle-finger",
+            Finger::LeftRing => "left-ring-finger",
+            Finger::LeftLittle => "left-little-finger",
+            Finger::RightThumb => "right-thumb",
+            Finger::RightIndex => "right-index-finger",
+            Finger::RightMiddle => "right-middle-finger",
+            Finger::RightRing => "right-ring-finger",
+            Finger::RightLittle => "right-little-finger",
+        }
+    }
+
+    /// Full name for display (e.g. "Left index finger").
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Finger::LeftThumb => "Left thumb",
+            Finger::LeftIndex => "Left index finger",
+            Finger::LeftMiddle => "Left middle finger",
+            Finger::LeftRing => "Left ring finger",
+            Finger::LeftLittle => "Left little finger",
+            Finger::RightThumb => "Right thumb",
+            Finger::RightIndex => "Right index finger",
+            Finger::RightMiddle => "Right middle finger",
+            Finger::RightRing => "Right ring finger",
+            Finger::RightLittle => "Right little finger",
+        }
+    }
+
+    /// Short name without hand or "finger" (e.g. "Index").
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Finger::LeftThumb | Finger::RightThumb => "Thumb",
+            Finger::LeftIndex | Finger::RightIndex => "Index",
+            Finger::LeftMiddle | Finger::RightMiddle => "Middle",
+            Finger::LeftRing | Finger::RightRing => "Ring",
+            Finger::LeftLittle | Finger::RightLittle => "Little",
+        }
+    }
+
+    /// Whether this is a thumb rather than one of the four fingers.
+    pub fn is_thumb(self) -> bool {
+        matches!(self, Finger::LeftThumb | Finger::RightThumb)
+    }
+
+    /// Hand the finger belongs to.
+    pub fn hand(self) -> Hand {
+        match self {
+            Finger::LeftThumb
+            | Finger::LeftIndex
+            | Finger::LeftMiddle
+            | Finger::LeftRing
+            | Finger::LeftLittle => Hand::Left,
+            _ => Hand::Right,
+        }
+    }
+}
+
+/// Reduce a finger name to a canonical form (e.g. "Left-Index-Finger" -> "left-index").
+fn normalize_finger_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    match name.strip_suffix("-finger") {
+        Some(stripped) => stripped.to_string(),
+        None => name,
+    }
+}
+
+impl fmt::Display for Finger {
+    /// Formats as the fprintd name so logs match the D-Bus traffic.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_dbus_name())
+    }
 }
 
 /// Check if current distribution is supported (XeroLinux).
@@ -85,3 +194,127 @@ fn parse_os_release_name(content: &str) -> Option<String> {
     }
     None
 }
+
+/// Locations of the fprintd daemon across distributions.
+const FPRINTD_DAEMON_PATHS: &[&str] = &["/usr/lib/fprintd", "/usr/libexec/fprintd"];
+
+/// Get the installed fprintd version from its daemon binary, e.g. "1.94.2".
+pub fn get_fprintd_version() -> Option<String> {
+    let daemon = FPRINTD_DAEMON_PATHS
+        .iter()
+        .find(|path| std::path::Path::new(path).is_file())?;
+    let output = std::process::Command::new(daemon)
+        .arg("--version")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Printed as "fprintd version 1.94.2"
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .map(str::to_string)
+}
+
+/// Known fprintd clients that keep the reader claimed, by process name.
+const FPRINTD_CLIENTS: &[(&str, &str)] = &[
+    ("gnome-control-c", "GNOME Settings"),
+    ("systemsettings", "KDE System Settings"),
+    ("kcmshell6", "KDE System Settings"),
+    ("kcmshell5", "KDE System Settings"),
+    ("fprintd-enroll", "fprintd-enroll"),
+    ("fprintd-verify", "fprintd-verify"),
+    ("fprintd-delete", "fprintd-delete"),
+    ("fprintd-list", "fprintd-list"),
+];
+
+/// Find a running application known to claim the fingerprint reader.
+/// Returns its display name; process names are truncated to 15 characters by the kernel.
+pub fn find_running_fprintd_client() -> Option<&'static str> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+
+    for entry in entries.flatten() {
+        let Ok(comm) = std::fs::read_to_string(entry.path().join("comm")) else {
+            continue;
+        };
+        let comm = comm.trim();
+        if let Some((_, name)) = FPRINTD_CLIENTS.iter().find(|(process, _)| *process == comm) {
+            return Some(name);
+        }
+    }
+
+    None
+}
+
+/// Whether `username` names an account known to the system (local or through NSS).
+pub fn user_exists(username: &str) -> bool {
+    let Ok(name) = std::ffi::CString::new(username) else {
+        return false;
+    };
+    // Only the null check is needed, so the static result is never read
+    !unsafe { libc::getpwnam(name.as_ptr()) }.is_null()
+}
+
+/// Environment variable naming an account to act as instead of the current user, for
+/// testing against a mock daemon or reproducing a user-specific issue.
+pub const USER_OVERRIDE_VAR: &str = "XFPRINTD_GUI_USER";
+
+/// Account named by [`USER_OVERRIDE_VAR`], if set; logs a warning the first time it is used.
+pub fn username_override() -> Option<String> {
+    static OVERRIDE: OnceLock<Option<String>> = OnceLock::new();
+    OVERRIDE
+        .get_or_init(|| {
+            let name = std::env::var(USER_OVERRIDE_VAR)
+                .ok()
+                .filter(|name| !name.is_empty())?;
+            warn!(
+                "{} override in effect: acting as user '{}' instead of the current user",
+                USER_OVERRIDE_VAR, name
+            );
+            Some(name)
+        })
+        .clone()
+}
+
+/// Name of the account the application runs as, which is the user fprintd assumes for an
+/// empty username. Resolved from the effective uid so it stays right under sudo or pkexec,
+/// falling back to `$USER` and then `$LOGNAME`; empty when none of them is known.
+/// [`USER_OVERRIDE_VAR`] takes precedence over all of them.
+pub fn current_username() -> String {
+    username_override()
+        .or_else(|| username_for_uid(unsafe { libc::geteuid() }))
+        .or_else(|| {
+            ["USER", "LOGNAME"]
+                .into_iter()
+                .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()))
+        })
+        .unwrap_or_default()
+}
+
+/// Look up the account name of `uid`; `None` when it has no passwd entry.
+fn username_for_uid(uid: libc::uid_t) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        // The reentrant variant, as scans run on several runtime threads
+        let rc =
+            unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if rc != 0 || result.is_null() {
+            return None;
+        }
+        let name = unsafe { std::ffi::CStr::from_ptr(pwd.pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+/// Whether the application runs as root.
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
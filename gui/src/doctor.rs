@@ -0,0 +1,165 @@
+//! End to end health check of the fingerprint stack (`--doctor`).
+//!
+//! Runs the same checks the GUI performs at startup in sequence and prints a
+//! pass/fail line for each, so support requests can start from one command.
+
+use crate::config;
+use crate::core::{fprintd, system};
+use crate::pam::helper::{self, PamHelper};
+use crate::pam::switch::{services, PamService};
+use std::path::Path;
+
+/// Outcome of a single check: `Ok` or `Err` with a short detail.
+type CheckResult = Result<String, String>;
+
+/// Run all checks, print the report and return the process exit code.
+pub fn run() -> i32 {
+    let rt = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("Failed to build Tokio runtime: {}", e);
+            return 1;
+        }
+    };
+
+    let mut results: Vec<(String, CheckResult)> = Vec::new();
+
+    results.push(("fprintd service active".to_string(), check_service()));
+
+    let client = rt.block_on(fprintd::Client::system());
+    results.push((
+        "System bus reachable".to_string(),
+        client
+            .as_ref()
+            .map(|_| "connected".to_string())
+            .map_err(|e| e.to_string()),
+    ));
+
+    let devices = match &client {
+        Ok(client) => check_devices(&rt, client),
+        Err(_) => Err("skipped, system bus unavailable".to_string()),
+    };
+    results.push(("Fingerprint device present".to_string(), devices));
+
+    results.push(("Helper tool installed".to_string(), check_helper()));
+    results.push(("pkexec available".to_string(), check_pkexec()));
+    results.push((
+        format!("{} installed", helper::FPRINTD_MODULE),
+        check_pam_module(),
+    ));
+
+    match services::list() {
+        Ok(listed) if listed.is_empty() => results.push((
+            "PAM services listed".to_string(),
+            Err("the helper has no PAM patches installed".to_string()),
+        )),
+        Ok(listed) => {
+            for service in listed {
+                results.push((
+                    format!("PAM file {} manageable", service.path),
+                    check_pam_file(&service),
+                ));
+            }
+        }
+        Err(e) => results.push(("PAM services listed".to_string(), Err(e.to_string()))),
+    }
+
+    for (name, result) in &results {
+        match result {
+            Ok(detail) => println!("[PASS] {} ({})", name, detail),
+            Err(detail) => println!("[FAIL] {}: {}", name, detail),
+        }
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed == 0 {
+        println!("\nAll {} checks passed", results.len());
+        0
+    } else {
+        println!("\n{} of {} checks failed", failed, results.len());
+        1
+    }
+}
+
+/// Check that the fprintd systemd service is running.
+fn check_service() -> CheckResult {
+    if system::check_fprintd_service() {
+        Ok("active".to_string())
+    } else {
+        Err("not running; start it with 'sudo systemctl start fprintd'".to_string())
+    }
+}
+
+/// Check that fprintd reports at least one device.
+fn check_devices(rt: &tokio::runtime::Runtime, client: &fprintd::Client) -> CheckResult {
+    let devices = rt
+        .block_on(fprintd::all_devices(client))
+        .map_err(|e| e.to_string())?;
+
+    let Some(first) = devices.first() else {
+        return Err("no fingerprint reader detected".to_string());
+    };
+
+    let name = rt
+        .block_on(first.name())
+        .unwrap_or_else(|_| first.object_path().to_string());
+    if devices.len() == 1 {
+        Ok(name)
+    } else {
+        Ok(format!("{} and {} more", name, devices.len() - 1))
+    }
+}
+
+/// Check that the helper binary exists, is executable and matches the GUI version.
+fn check_helper() -> CheckResult {
+    if !system::check_helper_tool() {
+        return Err(format!(
+            "{} is missing or not executable",
+            config::helper::BINARY_PATH
+        ));
+    }
+
+    match system::helper_version_mismatch() {
+        Some(version) => Err(format!(
+            "version {} does not match the GUI version {}; reinstall xfprintd-gui",
+            version,
+            config::app_info::VERSION
+        )),
+        None => Ok(config::helper::BINARY_PATH.to_string()),
+    }
+}
+
+/// Check that pkexec can be found for privilege escalation.
+fn check_pkexec() -> CheckResult {
+    if system::check_pkexec_availability() {
+        Ok("found".to_string())
+    } else {
+        Err("not found; install polkit".to_string())
+    }
+}
+
+/// Check that the fingerprint PAM module can be loaded.
+fn check_pam_module() -> CheckResult {
+    helper::find_fprintd_module()
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| helper::MODULE_MISSING_MESSAGE.to_string())
+}
+
+/// Check that the helper accepts the PAM file and that it can be written.
+fn check_pam_file(service: &PamService) -> CheckResult {
+    // The helper refuses non-allowlisted paths and paths without a patch file
+    PamHelper::show_configuration(&service.path).map_err(|e| e.to_string())?;
+
+    let path = Path::new(&service.path);
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().readonly() => Err("file is read-only".to_string()),
+        Ok(_) => Ok("allowlisted, writable".to_string()),
+        Err(_) if path.parent().is_some_and(Path::is_dir) => {
+            Ok("allowlisted, will be created".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
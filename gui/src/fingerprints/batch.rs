@@ -0,0 +1,200 @@
+//! Guided setup that enrolls several fingers in one session.
+
+use super::enroll::{self, EnrollmentEvent};
+use crate::config;
+use crate::core::context::FingerprintContext;
+use crate::core::device_manager::DeviceManager;
+use crate::core::fprintd::EnrollStatusEvent;
+use crate::core::util::{Finger, Hand, FINGERS};
+use gtk4::prelude::*;
+use gtk4::{ApplicationWindow, Box as GtkBox, Builder, Button, CheckButton, Label, Window};
+use log::info;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc;
+use tokio::sync::OwnedMutexGuard;
+
+/// Show the finger picker and start enrolling the chosen fingers.
+pub fn show_guided_setup(ctx: FingerprintContext) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/guided_setup_dialog.ui");
+    let dialog: Window = builder
+        .object("guided_setup_window")
+        .expect("Failed to get guided_setup_window");
+
+    if let Some(toplevel) = ctx.ui.stack.root() {
+        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
+            dialog.set_transient_for(Some(app_window));
+        }
+    }
+
+    let right_hand: GtkBox = builder
+        .object("guided_setup_right_hand")
+        .expect("Failed to get guided_setup_right_hand");
+    let left_hand: GtkBox = builder
+        .object("guided_setup_left_hand")
+        .expect("Failed to get guided_setup_left_hand");
+    let cancel_button: Button = builder
+        .object("guided_setup_cancel_button")
+        .expect("Failed to get guided_setup_cancel_button");
+    let start_button: Button = builder
+        .object("guided_setup_start_button")
+        .expect("Failed to get guided_setup_start_button");
+
+    let enrolled = ctx.get_enrolled();
+    let selected: Rc<RefCell<Vec<Finger>>> = Rc::new(RefCell::new(Vec::new()));
+
+    for (hand, column) in [(Hand::Right, &right_hand), (Hand::Left, &left_hand)] {
+        let title = Label::new(Some(hand.display_name()));
+        title.set_css_classes(&["hand-title"]);
+        column.append(&title);
+
+        for &finger in FINGERS.iter().filter(|finger| finger.hand() == hand) {
+            let check = CheckButton::with_label(finger.display_name());
+            if enrolled.contains(&finger) {
+                check.set_label(Some(&format!("{} (enrolled)", finger.display_name())));
+                check.set_sensitive(false);
+            }
+
+            let selected = selected.clone();
+            let start_button = start_button.clone();
+            check.connect_toggled(move |check| {
+                let mut fingers = selected.borrow_mut();
+                if check.is_active() {
+                    fingers.push(finger);
+                } else {
+                    fingers.retain(|&f| f != finger);
+                }
+                update_start_button(&start_button, fingers.len());
+            });
+            column.append(&check);
+        }
+    }
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        info!("User cancelled guided setup");
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    start_button.connect_clicked(move |_| {
+        // Enroll in the order the fingers appear on the hands, not in click order
+        let mut fingers = selected.borrow().clone();
+        fingers.sort();
+        info!("User started guided setup for {} finger(s)", fingers.len());
+        dialog_clone.close();
+        start_batch(fingers, ctx.clone());
+    });
+
+    dialog.present();
+}
+
+/// Label the start button with the number of picked fingers.
+fn update_start_button(button: &Button, count: usize) {
+    button.set_sensitive(count > 0);
+    button.set_label(&match count {
+        0 => "Start".to_string(),
+        1 => "Enroll 1 Finger".to_string(),
+        n => format!("Enroll {} Fingers", n),
+    });
+}
+
+/// Switch to the finger page and enroll `fingers` one after another.
+fn start_batch(fingers: Vec<Finger>, ctx: FingerprintContext) {
+    let Some(guard) = ctx.try_begin_operation() else {
+        return;
+    };
+    let (tx, rx) = mpsc::channel::<EnrollmentEvent>();
+
+    let ctx_retry = ctx.clone();
+    let fingers_retry = fingers.clone();
+    enroll::setup_ui_listener(rx, ctx.clone(), move || {
+        info!("User retried guided setup");
+        start_batch(fingers_retry.clone(), ctx_retry.clone());
+    });
+
+    ctx.set_busy(true);
+    ctx.ui.stack.set_visible_child_name("finger");
+
+    let _ = tx.send(EnrollmentEvent::Prompt(enroll::first_scan_markup()));
+    spawn_batch_task(fingers, tx, ctx, guard);
+}
+
+/// Spawn async task enrolling every finger on a single device claim,
+/// holding the operation lock until it finishes.
+fn spawn_batch_task(
+    fingers: Vec<Finger>,
+    tx: mpsc::Sender<EnrollmentEvent>,
+    ctx: FingerprintContext,
+    guard: OwnedMutexGuard<()>,
+) {
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+    let settings = ctx.settings();
+
+    ctx.rt.spawn(async move {
+        let _guard = guard;
+        info!("Starting guided setup for {} finger(s)", fingers.len());
+        let total = fingers.len();
+
+        let tx_finger = tx.clone();
+        let on_finger = move |index: usize, finger: Finger| {
+            let _ = tx_finger.send(EnrollmentEvent::FingerStarted {
+                finger,
+                index,
+                total,
+            });
+            if index > 0 {
+                let _ = tx_finger.send(EnrollmentEvent::Prompt(format!(
+                    "<b><span foreground='{}'>🔍 Next finger</span> - Place your {} on the scanner…</b>",
+                    config::colors().progress,
+                    finger.display_name().to_lowercase()
+                )));
+            }
+        };
+
+        let tx_status = tx.clone();
+        let enrolled_fingers = fingers.clone();
+        let enrolled_user = username.clone();
+        // Scans captured for the current finger, reset whenever the next finger starts
+        let mut current = 0usize;
+        let mut progress = enroll::EnrollProgress::new(settings.retry_advice_after);
+        let on_status = move |index: usize, evt: &EnrollStatusEvent| {
+            if index != current {
+                current = index;
+                progress = enroll::EnrollProgress::new(settings.retry_advice_after);
+            }
+            if evt.done && evt.result == "enroll-completed" {
+                super::metadata::record_enrolled(&enrolled_user, enrolled_fingers[index]);
+            }
+            let text = enroll::status_markup(evt, &mut progress);
+            let _ = tx_status.send(EnrollmentEvent::SetText(text));
+            let _ = tx_status.send(EnrollmentEvent::StagesCaptured(progress.stages()));
+        };
+
+        let result = DeviceManager::enroll_fingers(
+            &fingers,
+            device_path,
+            &username,
+            settings.enroll_timeout(),
+            on_finger,
+            on_status,
+            enroll::sensor_forwarder(tx.clone()),
+        )
+        .await;
+        super::invalidate_scan_cache();
+
+        if result.is_ok() {
+            info!("Guided setup enrolled all {} finger(s)", total);
+            let _ = tx.send(EnrollmentEvent::SetText(format!(
+                "<span foreground='{}'><b>🎉 Guided setup complete!</b> Enrolled {} finger(s).</span>",
+                config::colors().success,
+                total
+            )));
+        } else {
+            enroll::report_result(result, &tx);
+        }
+        // Fingers enrolled before a failure are kept, so refresh either way
+        let _ = tx.send(EnrollmentEvent::EnrollCompleted);
+    });
+}
@@ -3,243 +3,611 @@
 use crate::config;
 use crate::core::context::FingerprintContext;
 use crate::core::device_manager::{DeviceError, DeviceManager};
-use crate::core::fprintd;
+use crate::core::fprintd::{self, EnrollStatusEvent, SensorEvent};
+use crate::core::util::Finger;
 use gtk4::glib;
 
+use gtk4::{prelude::*, ApplicationWindow, Box as GtkBox, Button, Label, Orientation, Window};
 use log::{info, warn};
 use std::sync::mpsc::{self, TryRecvError};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedMutexGuard;
+
+/// How long a scan prompt waits for the sensor to report finger-needed before it is shown
+/// anyway, for drivers that never update the property.
+const PROMPT_FALLBACK: Duration = Duration::from_secs(3);
 
 /// Events sent during enrollment process.
 #[derive(Clone)]
 pub enum EnrollmentEvent {
     SetText(String),
+    /// Scan instruction held back until the sensor is ready to capture.
+    Prompt(String),
+    /// The sensor started (`true`) or stopped (`false`) waiting for a finger.
+    FingerNeeded(bool),
+    /// A finger was placed on (`true`) or lifted from (`false`) the sensor.
+    FingerPresent(bool),
+    /// The press sensor needs this many scans; shows one stage dot per scan.
+    PressStages(usize),
+    /// Scans captured so far for the current finger.
+    StagesCaptured(usize),
+    /// Another application holds the reader; carries its name when known.
+    DeviceBusy(Option<String>),
+    /// Guided setup moved on to the finger at `index` (zero-based) of `total`.
+    FingerStarted {
+        finger: Finger,
+        index: usize,
+        total: usize,
+    },
     EnrollCompleted,
 }
 
-/// Holds the DeviceManager during enrollment to keep device claimed.
-type SharedDeviceManager = Arc<Mutex<Option<DeviceManager>>>;
-
 /// Start fingerprint enrollment process for specified finger.
-pub fn start_enrollment(finger_key: String, ctx: FingerprintContext) {
+pub fn start_enrollment(finger: Finger, ctx: FingerprintContext) {
+    begin_enrollment(finger, ctx, false);
+}
+
+/// Ask for confirmation, then replace the enrolled print for the specified finger.
+pub fn confirm_reenrollment(finger: Finger, ctx: FingerprintContext) {
+    let builder = gtk4::Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/reenroll_dialog.ui");
+    let dialog: Window = builder
+        .object("reenroll_window")
+        .expect("Failed to get reenroll_window");
+
+    if let Some(toplevel) = ctx.ui.stack.root() {
+        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
+            dialog.set_transient_for(Some(app_window));
+        }
+    }
+
+    let message: Label = builder
+        .object("reenroll_message")
+        .expect("Failed to get reenroll_message");
+    message.set_markup(&format!(
+        "Replace the stored print for <b>{}</b> with a fresh scan?",
+        glib::markup_escape_text(finger.display_name())
+    ));
+
+    let cancel_button: Button = builder
+        .object("reenroll_cancel_button")
+        .expect("Failed to get reenroll_cancel_button");
+    let proceed_button: Button = builder
+        .object("reenroll_proceed_button")
+        .expect("Failed to get reenroll_proceed_button");
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        info!("User cancelled re-enrollment");
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    proceed_button.connect_clicked(move |_| {
+        info!("User confirmed re-enrollment of '{}'", finger);
+        dialog_clone.close();
+        begin_enrollment(finger, ctx.clone(), true);
+    });
+
+    dialog.present();
+}
+
+/// Set up the status listener and start enrolling, replacing the existing print if requested.
+fn begin_enrollment(finger: Finger, ctx: FingerprintContext, replace: bool) {
+    let Some(guard) = ctx.try_begin_operation() else {
+        return;
+    };
     let (tx, rx) = mpsc::channel::<EnrollmentEvent>();
+    ctx.set_busy(true);
 
-    setup_ui_listener(rx, ctx.clone());
-    // We don't yet know required stages (varies by device), so we show a generic Step 1 message.
-    let _ = tx.send(EnrollmentEvent::SetText(format!(
+    let ctx_retry = ctx.clone();
+    setup_ui_listener(rx, ctx.clone(), move || {
+        info!("User retried enrollment of '{}'", finger);
+        begin_enrollment(finger, ctx_retry.clone(), replace);
+    });
+    let _ = tx.send(if replace {
+        EnrollmentEvent::SetText(format!(
+            "<span foreground='{}'><b>🗑 Removing old scan…</b></span>",
+            config::colors().process
+        ))
+    } else {
+        EnrollmentEvent::Prompt(first_scan_markup())
+    });
+    spawn_enrollment_task(finger, replace, tx, ctx, guard);
+}
+
+/// Prompt for the first scan.
+/// We don't yet know required stages (varies by device), so we show a generic Step 1 message.
+pub fn first_scan_markup() -> String {
+    format!(
         "<b><span foreground='{}'>🔍 Scan 1</span> - Place your finger firmly on the scanner…</b>",
         config::colors().progress
-    )));
-    spawn_enrollment_task(finger_key, tx, ctx);
+    )
 }
 
 /// Set up UI listener for enrollment status updates.
-fn setup_ui_listener(rx: mpsc::Receiver<EnrollmentEvent>, ctx: FingerprintContext) {
+/// `retry` is offered when the reader is busy; the busy state is cleared once the task ends.
+pub fn setup_ui_listener(
+    rx: mpsc::Receiver<EnrollmentEvent>,
+    ctx: FingerprintContext,
+    retry: impl Fn() + Clone + 'static,
+) {
     let lbl = ctx.ui.labels.action.clone();
+    let icon = ctx.ui.labels.icon.clone();
+    let dots = ctx.ui.labels.stage_dots.clone();
     let ctx_for_refresh = ctx.clone();
+    // Last status message, kept so the finger-needed hint can be shown below it
+    let mut status = String::new();
+    // The initial finger-needed value arrives before EnrollStart, so wait for the sensor to ask first
+    let mut needed_seen = false;
+    // Scan instruction waiting for the sensor, with when to show it regardless
+    let mut pending_prompt: Option<(String, Instant)> = None;
+
+    icon.add_css_class("sensor-waiting");
+    show_stage_dots(&dots, 0);
 
     glib::idle_add_local(move || {
         loop {
             match rx.try_recv() {
                 Ok(EnrollmentEvent::SetText(text)) => {
+                    pending_prompt = None;
+                    set_status(&lbl, &mut status, text);
+                }
+                Ok(EnrollmentEvent::Prompt(text)) => {
+                    set_status(&lbl, &mut status, reader_preparing_markup());
+                    pending_prompt = Some((text, Instant::now() + PROMPT_FALLBACK));
+                }
+                Ok(EnrollmentEvent::FingerNeeded(needed)) => {
+                    needed_seen |= needed;
+                    let ready = if needed { pending_prompt.take() } else { None };
+                    if let Some((text, _)) = ready {
+                        set_status(&lbl, &mut status, text);
+                    } else if needed_seen {
+                        lbl.set_use_markup(true);
+                        lbl.set_markup(&format!("{}\n{}", status, finger_needed_hint(needed)));
+                    }
+                }
+                Ok(EnrollmentEvent::FingerPresent(present)) => {
+                    if present {
+                        icon.add_css_class("sensor-touched");
+                    } else {
+                        icon.remove_css_class("sensor-touched");
+                    }
+                    // A finger placed before the sensor listens would only cause a retry
+                    if pending_prompt.is_some() {
+                        lbl.set_use_markup(true);
+                        lbl.set_markup(&if present {
+                            format!("{}\n{}", status, too_early_hint())
+                        } else {
+                            status.clone()
+                        });
+                    }
+                }
+                Ok(EnrollmentEvent::PressStages(total)) => show_stage_dots(&dots, total),
+                Ok(EnrollmentEvent::StagesCaptured(captured)) => fill_stage_dots(&dots, captured),
+                Ok(EnrollmentEvent::DeviceBusy(holder)) => {
                     lbl.set_use_markup(true);
-                    lbl.set_markup(&text);
+                    lbl.set_markup(&super::device_busy_markup(holder.as_deref()));
+                    ctx_for_refresh.toast.show(
+                        "Fingerprint reader busy",
+                        "Retry",
+                        super::RETRY_WINDOW,
+                        retry.clone(),
+                    );
+                }
+                Ok(EnrollmentEvent::FingerStarted {
+                    finger,
+                    index,
+                    total,
+                }) => {
+                    ctx_for_refresh.set_selected_finger(Some(finger));
+                    ctx_for_refresh.ui.labels.finger.set_label(&format!(
+                        "Finger {} of {}: {}",
+                        index + 1,
+                        total,
+                        finger.display_name()
+                    ));
+                    icon.remove_css_class("sensor-touched");
+                    show_stage_dots(&dots, 0);
+                    needed_seen = false;
                 }
                 Ok(EnrollmentEvent::EnrollCompleted) => {
                     crate::ui::fingerprint_ui::refresh_fingerprint_display(ctx_for_refresh.clone());
                 }
                 Err(TryRecvError::Empty) => break,
-                Err(TryRecvError::Disconnected) => return glib::ControlFlow::Break,
+                Err(TryRecvError::Disconnected) => {
+                    icon.remove_css_class("sensor-waiting");
+                    icon.remove_css_class("sensor-touched");
+                    ctx_for_refresh.set_busy(false);
+                    return glib::ControlFlow::Break;
+                }
+            }
+        }
+
+        if pending_prompt
+            .as_ref()
+            .is_some_and(|(_, deadline)| Instant::now() >= *deadline)
+        {
+            info!("Sensor did not report finger-needed, showing the scan prompt anyway");
+            if let Some((text, _)) = pending_prompt.take() {
+                set_status(&lbl, &mut status, text);
             }
         }
         glib::ControlFlow::Continue
     });
 }
 
-/// Spawn async enrollment task.
+/// Show `text` as the current status message.
+fn set_status(lbl: &Label, status: &mut String, text: String) {
+    lbl.set_use_markup(true);
+    lbl.set_markup(&text);
+    *status = text;
+}
+
+/// Shown while the sensor gets ready for the next scan.
+fn reader_preparing_markup() -> String {
+    format!(
+        "<span foreground='{}'><b>⏳ Getting the reader ready…</b> Wait before placing your finger.</span>",
+        config::colors().process
+    )
+}
+
+/// Hint shown when a finger is placed before the sensor is ready for it.
+fn too_early_hint() -> String {
+    format!(
+        "<span foreground='{}'>Lift your finger, the reader is not listening yet.</span>",
+        config::colors().warning
+    )
+}
+
+/// Replace the stage dots with `total` empty ones, hiding the row when there are none.
+fn show_stage_dots(dots: &GtkBox, total: usize) {
+    while let Some(child) = dots.first_child() {
+        dots.remove(&child);
+    }
+    for _ in 0..total {
+        let dot = GtkBox::new(Orientation::Horizontal, 0);
+        dot.add_css_class("stage-dot");
+        dots.append(&dot);
+    }
+    dots.set_visible(total > 0);
+}
+
+/// Fill the first `captured` stage dots; extra scans beyond the last dot change nothing.
+fn fill_stage_dots(dots: &GtkBox, captured: usize) {
+    let mut child = dots.first_child();
+    let mut index = 0;
+    while let Some(dot) = child {
+        if index < captured {
+            dot.add_css_class("stage-dot-filled");
+        } else {
+            dot.remove_css_class("stage-dot-filled");
+        }
+        index += 1;
+        child = dot.next_sibling();
+    }
+}
+
+/// Hint shown below the status message while the sensor waits for or processes a finger.
+fn finger_needed_hint(needed: bool) -> String {
+    if needed {
+        format!(
+            "<span foreground='{}'><b>👆 Place your finger now</b></span>",
+            config::colors().progress
+        )
+    } else {
+        format!(
+            "<span foreground='{}'>Processing…</span>",
+            config::colors().process
+        )
+    }
+}
+
+/// Spawn async enrollment task, holding the operation lock until it finishes.
 fn spawn_enrollment_task(
-    finger_key: String,
+    finger: Finger,
+    replace: bool,
     tx: mpsc::Sender<EnrollmentEvent>,
     ctx: FingerprintContext,
+    guard: OwnedMutexGuard<()>,
 ) {
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+    let settings = ctx.settings();
+
     ctx.rt.spawn(async move {
+        let _guard = guard;
         info!(
             "Starting fingerprint enrollment process for finger: {}",
-            finger_key
+            finger
         );
 
-        // Create shared storage for DeviceManager to keep device claimed during enrollment
-        let device_manager: SharedDeviceManager = Arc::new(Mutex::new(None));
-        let device_manager_for_cleanup = device_manager.clone();
+        let tx_status = tx.clone();
+        // Track progressive successful stages (we only show how many good scans were captured so far).
+        // The text counter stays open-ended since some devices report 0 or -1 stages, see
+        // `plausible_enroll_stages`; press sensors with a usable count also get stage dots.
+        let mut progress = EnrollProgress::new(settings.retry_advice_after);
 
-        let result = DeviceManager::enroll_finger(finger_key.clone(), |device| {
-            setup_enrollment_listener_sync(device, &tx, device_manager_for_cleanup)
-        })
-        .await;
+        let on_status = move |evt: &EnrollStatusEvent| {
+            let text = status_markup(evt, &mut progress);
+            let _ = tx_status.send(EnrollmentEvent::SetText(text));
+            let _ = tx_status.send(EnrollmentEvent::StagesCaptured(progress.stages()));
+        };
 
-        match result {
-            Ok(manager) => {
-                // Store the DeviceManager to keep the device claimed
-                *device_manager.lock().await = Some(manager);
-            }
-            Err(e) => {
-                let error_msg = match e {
-                    DeviceError::NoDeviceAvailable => {
-                        format!(
-                            "<span foreground='{}'>No fingerprint devices available.</span>",
-                            config::colors().warning
-                        )
-                    }
-                    _ => format!("Failed to start enrollment: {}", e),
-                };
-                let _ = tx.send(EnrollmentEvent::SetText(error_msg));
-            }
+        let result = if replace {
+            let tx_deleted = tx.clone();
+            let username_deleted = username.clone();
+            let on_deleted = move || {
+                // The old print is gone even if the new enrollment fails
+                super::metadata::forget(&username_deleted, finger);
+                let _ = tx_deleted.send(EnrollmentEvent::Prompt(first_scan_markup()));
+            };
+            DeviceManager::reenroll_finger(
+                finger,
+                device_path,
+                &username,
+                settings.enroll_timeout(),
+                on_deleted,
+                on_status,
+                sensor_forwarder(tx.clone()),
+            )
+            .await
+        } else {
+            DeviceManager::enroll_finger(
+                finger,
+                device_path,
+                &username,
+                settings.enroll_timeout(),
+                on_status,
+                sensor_forwarder(tx.clone()),
+            )
+            .await
+        };
+        super::invalidate_scan_cache();
+
+        if result.is_ok() {
+            super::metadata::record_enrolled(&username, finger);
+            let _ = tx.send(EnrollmentEvent::EnrollCompleted);
+        } else {
+            report_result(result, &tx);
         }
     });
 }
 
-/// Set up enrollment status listener (synchronous wrapper for DeviceManager).
-fn setup_enrollment_listener_sync(
-    device: &fprintd::Device,
-    tx: &mpsc::Sender<EnrollmentEvent>,
-    device_manager: SharedDeviceManager,
-) -> Result<(), DeviceError> {
-    let device_clone = device.clone();
-    let tx_clone = tx.clone();
+/// Forward sensor state changes to the enrollment listener.
+pub fn sensor_forwarder(
+    tx: mpsc::Sender<EnrollmentEvent>,
+) -> impl Fn(SensorEvent) + Send + Sync + 'static {
+    move |evt: SensorEvent| {
+        info!("Sensor state changed: {:?}", evt);
+        let _ = tx.send(match evt {
+            SensorEvent::FingerNeeded(needed) => EnrollmentEvent::FingerNeeded(needed),
+            SensorEvent::FingerPresent(present) => EnrollmentEvent::FingerPresent(present),
+            SensorEvent::PressStages(total) => EnrollmentEvent::PressStages(total),
+        });
+    }
+}
 
-    tokio::spawn(async move {
-        setup_enrollment_listener(&device_clone, &tx_clone, device_manager).await;
-    });
+/// Scans of the finger being enrolled, as far as the status updates tell.
+#[derive(Debug)]
+pub struct EnrollProgress {
+    /// Rejected scans in a row before advice is shown.
+    advice_after: usize,
+    /// Scans captured so far.
+    stages: usize,
+    /// Scans rejected in a row since the last captured one.
+    retries: usize,
+    /// Scans rejected over the whole enrollment.
+    total_retries: usize,
+    /// Scans rejected for not being centered.
+    not_centered: usize,
+}
+
+impl EnrollProgress {
+    /// No scans yet; advice is shown after `advice_after` rejected scans in a row.
+    pub fn new(advice_after: usize) -> Self {
+        Self {
+            advice_after,
+            stages: 0,
+            retries: 0,
+            total_retries: 0,
+            not_centered: 0,
+        }
+    }
 
-    Ok(())
+    /// Scans captured so far.
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
 }
 
-/// Set up enrollment status listener.
-async fn setup_enrollment_listener(
-    device: &fprintd::Device,
-    tx: &mpsc::Sender<EnrollmentEvent>,
-    device_manager: SharedDeviceManager,
-) {
-    let device_for_listener = device.clone();
-    let device_for_cleanup = device.clone();
-    let tx_status = tx.clone();
+/// Build the status message for an enrollment status update and advance `progress`.
+/// After several rejected scans in a row, advice on fixing the problem is appended.
+pub fn status_markup(evt: &EnrollStatusEvent, progress: &mut EnrollProgress) -> String {
+    info!(
+        "Enrollment status update: result='{}', done={}",
+        evt.result, evt.done
+    );
 
-    info!("Setting up enrollment status listener for real-time feedback");
-    // Track progressive successful stages (we only show how many good scans were captured so far).
-    let mut stage_count: usize = 0usize;
+    if fprintd::is_retry_status(&evt.result) {
+        progress.retries += 1;
+        progress.total_retries += 1;
+        if evt.result == "enroll-finger-not-centered" {
+            progress.not_centered += 1;
+        }
+    } else if evt.result == "enroll-stage-passed" {
+        progress.retries = 0;
+    }
 
-    let _ = device_for_listener
-        .listen_enroll_status(move |evt| {
-            info!(
-                "Enrollment status update: result='{}', done={}",
-                evt.result, evt.done
-            );
+    if evt.result == "enroll-completed" {
+        return completed_markup(progress);
+    }
 
-            let mut _message: Option<String> = None;
+    let text = scan_markup(evt, &mut progress.stages);
+    match retry_advice(progress.retries, progress.advice_after) {
+        Some(advice) => format!(
+            "{}\n<span foreground='{}'>💡 {}</span>",
+            text,
+            config::colors().neutral,
+            advice
+        ),
+        None => text,
+    }
+}
 
+/// Summary of a finished enrollment: scans captured, scans rejected and whether any were
+/// off-center, recommending to re-enroll when rejections outnumbered captured scans.
+fn completed_markup(progress: &EnrollProgress) -> String {
+    info!(
+        "Fingerprint enrollment completed successfully after {} stages ({} retries, {} not centered)",
+        progress.stages, progress.total_retries, progress.not_centered
+    );
 
+    let mut text = format!(
+        "<span foreground='{}'><b>🎉 Enrollment complete!</b> Captured {} quality scans.</span>",
+        config::colors().success,
+        progress.stages
+    );
 
-            match evt.result.as_str() {
-                "enroll-stage-passed" => {
-                    stage_count += 1;
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>✅ Scan {} captured.</b> Lift your finger, then place it again…</span>",
-                        config::colors().progress,
-                        stage_count
-                    ));
-                }
-                "enroll-remove-and-retry" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>⚠️  Retry scan {}.</b> Lift your finger completely, reposition (centered & flat), then place again…</span>",
-                        config::colors().warning,
-                        stage_count + 1
-                    ));
-                }
-                "enroll-swipe-too-short" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>👆 Swipe too short.</b> Try a longer, smoother swipe (still on scan {}).</span>",
-                        config::colors().warning,
-                        stage_count + 1
-                    ));
-                }
-                "enroll-finger-not-centered" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>🎯 Not centered.</b> Re‑place finger centered & flat (scan {}).</span>",
-                        config::colors().warning,
-                        stage_count + 1
-                    ));
-                }
-                "enroll-duplicate" => {
-                    _message = Some(
-                        format!(
-                            "<span foreground='{}'><b>🔄 Already enrolled!</b> Choose a different finger.</span>",
-                            config::colors().warning
-                        )
-                    );
-                }
-                "enroll-data-full" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>📊 Processing captured data…</b> ({} scans so far)</span>",
-                        config::colors().process,
-                        stage_count
-                    ));
-                }
-                "enroll-failed" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>❌ Enrollment failed.</b> Please try again.</span>",
-                        config::colors().error
-                    ));
-                }
-                "enroll-completed" => {
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>🎉 Enrollment complete!</b> Captured {} quality scans.</span>",
-                        config::colors().success,
-                        stage_count
-                    ));
-                }
-                other => {
-                    // Fallback / unknown statuses
-                    _message = Some(format!(
-                        "<span foreground='{}'><b>📊 Status:</b> {} (scan {})</span>",
-                        config::colors().neutral,
-                        other,
-                        stage_count.max(1)
-                    ));
-                }
-            }
+    if progress.total_retries > 0 {
+        let mut details = format!("{} scan(s) had to be repeated", progress.total_retries);
+        if progress.not_centered > 0 {
+            details.push_str(&format!(", {} of them off-center", progress.not_centered));
+        }
+        text.push_str(&format!(
+            "\n<span foreground='{}'>{}.</span>",
+            config::colors().neutral,
+            details
+        ));
+    }
 
-            if let Some(text) = _message {
-                let _ = tx_status.send(EnrollmentEvent::SetText(text));
-            }
+    let ratio = config::enrollment::REENROLL_RETRY_RATIO;
+    if progress.stages > 0 && progress.total_retries >= progress.stages * ratio {
+        text.push_str(&format!(
+            "\n<span foreground='{}'>💡 Lots of retries — consider re-enrolling for better accuracy.</span>",
+            config::colors().warning
+        ));
+    }
 
-            if evt.result == "enroll-completed" {
-                info!(
-                    "Fingerprint enrollment completed successfully after {} stages",
-                    stage_count
-                );
-                let _ = tx_status.send(EnrollmentEvent::EnrollCompleted);
-            }
+    text
+}
 
-            if evt.done {
-                info!("Enrollment process finished, cleaning up device");
-                let device_clone = device_for_cleanup.clone();
-                let manager_clone = device_manager.clone();
-                tokio::spawn(async move {
-                    cleanup_enrollment_device(device_clone, manager_clone).await;
-                });
-            }
-        })
-        .await;
+/// Advice for `retries` rejected scans in a row once there were `after`, escalating the
+/// longer it goes on.
+fn retry_advice(retries: usize, after: usize) -> Option<&'static str> {
+    match retries {
+        r if r < after => None,
+        r if r == after => Some("Clean the sensor and make sure your finger is dry."),
+        r if r == after + 1 => Some("Still no luck? Try a different finger."),
+        _ => Some("Your sensor may need recalibration or a driver update."),
+    }
 }
 
-/// Clean up enrollment device.
-async fn cleanup_enrollment_device(device: fprintd::Device, device_manager: SharedDeviceManager) {
-    if let Err(e) = device.enroll_stop().await {
-        warn!("Failed to stop enrollment: {}", e);
+/// Message for a single status update; `stage_count` is advanced on every passed stage.
+fn scan_markup(evt: &EnrollStatusEvent, stage_count: &mut usize) -> String {
+    match evt.result.as_str() {
+        "enroll-stage-passed" => {
+            *stage_count += 1;
+            format!(
+                "<span foreground='{}'><b>✅ Scan {} captured.</b> Lift your finger, then place it again…</span>",
+                config::colors().progress,
+                stage_count
+            )
+        }
+        "enroll-retry-scan" => format!(
+            "<span foreground='{}'><b>🔁 Didn't catch that.</b> Place your finger again (still on scan {}).</span>",
+            config::colors().warning,
+            *stage_count + 1
+        ),
+        "enroll-too-fast" => format!(
+            "<span foreground='{}'><b>🐢 You moved too quickly.</b> Hold steady on the sensor (still on scan {}).</span>",
+            config::colors().warning,
+            *stage_count + 1
+        ),
+        "enroll-remove-and-retry" => format!(
+            "<span foreground='{}'><b>⚠️  Retry scan {}.</b> Lift your finger completely, reposition (centered & flat), then place again…</span>",
+            config::colors().warning,
+            *stage_count + 1
+        ),
+        "enroll-swipe-too-short" => format!(
+            "<span foreground='{}'><b>👆 Swipe too short.</b> Try a longer, smoother swipe (still on scan {}).</span>",
+            config::colors().warning,
+            *stage_count + 1
+        ),
+        "enroll-finger-not-centered" => format!(
+            "<span foreground='{}'><b>🎯 Not centered.</b> Re‑place finger centered & flat (scan {}).</span>",
+            config::colors().warning,
+            *stage_count + 1
+        ),
+        "enroll-duplicate" => format!(
+            "<span foreground='{}'><b>🔄 Already enrolled!</b> Choose a different finger.</span>",
+            config::colors().warning
+        ),
+        "enroll-data-full" => format!(
+            "<span foreground='{}'><b>📊 Processing captured data…</b> ({} scans so far)</span>",
+            config::colors().process,
+            stage_count
+        ),
+        "enroll-failed" => format!(
+            "<span foreground='{}'><b>❌ Enrollment failed.</b> Please try again.</span>",
+            config::colors().error
+        ),
+        // Fallback / unknown statuses
+        other => format!(
+            "<span foreground='{}'><b>📊 Status:</b> {} (scan {})</span>",
+            config::colors().neutral,
+            other,
+            (*stage_count).max(1)
+        ),
     }
+}
 
-    // Release the DeviceManager to properly clean up the device
-    *device_manager.lock().await = None;
-    info!("Enrollment cleanup completed");
+/// Report a failed enrollment to the listener; successful results send nothing.
+pub fn report_result(result: Result<(), DeviceError>, tx: &mpsc::Sender<EnrollmentEvent>) {
+    match result {
+        Ok(()) => {}
+        Err(DeviceError::EnrollFailed(status)) => {
+            // The terminal status message has already been shown
+            warn!("Enrollment did not complete: {}", status);
+        }
+        Err(DeviceError::CaptureIncomplete) => {
+            // Replace the stale "processing" message left by enroll-data-full
+            let _ = tx.send(EnrollmentEvent::SetText(format!(
+                "<span foreground='{}'><b>⚠️  Capture incomplete</b> — please retry.</span>",
+                config::colors().warning
+            )));
+        }
+        Err(DeviceError::TooManyRetries) => {
+            let _ = tx.send(EnrollmentEvent::SetText(format!(
+                "<span foreground='{}'><b>Having trouble?</b> Clean the sensor and your finger, then try again.</span>",
+                config::colors().warning
+            )));
+        }
+        Err(DeviceError::EnrollTimedOut(timeout)) => {
+            let _ = tx.send(EnrollmentEvent::SetText(format!(
+                "<span foreground='{}'><b>⏱ No scan for {} seconds.</b> Enrollment stopped, try again when ready.</span>",
+                config::colors().warning,
+                timeout.as_secs()
+            )));
+        }
+        Err(DeviceError::DeviceBusy(holder)) => {
+            let _ = tx.send(EnrollmentEvent::DeviceBusy(holder));
+        }
+        Err(e) => {
+            let error_msg = match e {
+                DeviceError::NoDeviceAvailable => {
+                    format!(
+                        "<span foreground='{}'>No fingerprint devices available.</span>",
+                        config::colors().warning
+                    )
+                }
+                DeviceError::PermissionDenied(_) => format!(
+                    "<span foreground='{}'><b>Not authorized.</b> {}</span>",
+                    config::colors().error,
+                    glib::markup_escape_text(&e.to_string())
+                ),
+                _ => format!("Failed to start enrollment: {}", e),
+            };
+            let _ = tx.send(EnrollmentEvent::SetText(error_msg));
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Enrollment dates recorded by the app, since fprintd does not track them.
+//!
+//! Stored in `$XDG_DATA_HOME/xfprintd-gui/enrollments.json` as
+//! `{ "<username>": { "<finger>": <unix seconds> } }`.
+
+use crate::core::util::{self, Finger};
+use log::{debug, warn};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Enrollment timestamps by username, then fprintd finger name.
+type Store = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// File name of the store inside the data directory.
+const STORE_FILE: &str = "enrollments.json";
+
+/// Path of the enrollment date store.
+pub fn store_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    Some(base.join("xfprintd-gui").join(STORE_FILE))
+}
+
+/// Record that `finger` of `username` (empty for the current user) was enrolled now.
+pub fn record_enrolled(username: &str, finger: Finger) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    update(|store| {
+        store
+            .entry(resolve_username(username))
+            .or_default()
+            .insert(finger.to_dbus_name().to_string(), now);
+    });
+}
+
+/// Drop the recorded enrollment date of `finger` for `username`.
+pub fn forget(username: &str, finger: Finger) {
+    let username = resolve_username(username);
+    update(|store| {
+        if let Some(fingers) = store.get_mut(&username) {
+            fingers.remove(finger.to_dbus_name());
+            if fingers.is_empty() {
+                store.remove(&username);
+            }
+        }
+    });
+}
+
+/// Drop every recorded enrollment date of `username`.
+pub fn forget_all(username: &str) {
+    let username = resolve_username(username);
+    update(|store| {
+        store.remove(&username);
+    });
+}
+
+/// When `finger` of `username` was enrolled, if it was enrolled through this app.
+pub fn enrolled_at(username: &str, finger: Finger) -> Option<SystemTime> {
+    load()
+        .get(&resolve_username(username))?
+        .get(finger.to_dbus_name())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(*secs))
+}
+
+/// Describe when `finger` was enrolled, e.g. "Enrolled 3 days ago".
+pub fn describe_enrolled(username: &str, finger: Finger) -> String {
+    match enrolled_at(username, finger) {
+        Some(at) => format!(
+            "Enrolled {}",
+            format_age(SystemTime::now().duration_since(at).unwrap_or_default())
+        ),
+        None => "Enrolled (date unknown)".to_string(),
+    }
+}
+
+/// Format an elapsed time as "just now", "5 minutes ago", "3 days ago" and so on.
+fn format_age(age: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    let secs = age.as_secs();
+    let (count, unit) = match secs {
+        s if s < MINUTE => return "just now".to_string(),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < 2 * MONTH => (s / DAY, "day"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s => (s / YEAR, "year"),
+    };
+
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+/// Resolve an empty username to the current user, as fprintd does.
+fn resolve_username(username: &str) -> String {
+    if username.is_empty() {
+        util::current_username()
+    } else {
+        username.to_string()
+    }
+}
+
+/// Load the store; a missing or invalid file counts as empty.
+fn load() -> Store {
+    let Some(path) = store_path() else {
+        return Store::new();
+    };
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Store::new(),
+        Err(e) => {
+            warn!("Could not read {}: {}", path.display(), e);
+            return Store::new();
+        }
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!(
+            "Ignoring invalid enrollment dates {}: {}",
+            path.display(),
+            e
+        );
+        Store::new()
+    })
+}
+
+/// Apply `change` to the store and write it back.
+/// The dates are informational only, so failures are logged and otherwise ignored.
+fn update(change: impl FnOnce(&mut Store)) {
+    let Some(path) = store_path() else {
+        warn!("No data directory; enrollment dates are not recorded");
+        return;
+    };
+
+    let mut store = load();
+    change(&mut store);
+
+    let result = (|| -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&store).map_err(io::Error::other)?;
+        std::fs::write(&path, content)
+    })();
+
+    match result {
+        Ok(()) => debug!("Updated enrollment dates in {}", path.display()),
+        Err(e) => warn!(
+            "Failed to save enrollment dates to {}: {}",
+            path.display(),
+            e
+        ),
+    }
+}
@@ -1,96 +1,301 @@
 //! Fingerprint management functionality.
 
+pub mod batch;
 pub mod enroll;
+pub mod metadata;
 pub mod remove;
+pub mod verify;
 
+use crate::config;
+use crate::core::device_manager::{DeviceError, DeviceManager};
 use crate::core::fprintd;
-use log::{error, info, warn};
-use std::collections::HashSet;
+use crate::core::util::{self, Finger};
+use gtk4::glib;
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use zbus::zvariant::OwnedObjectPath;
 
-/// Scan for enrolled fingerprints on the system.
-/// Returns HashSet of enrolled fingerprint names for current user.
-pub async fn scan_enrolled_fingerprints() -> HashSet<String> {
-    let mut enrolled_fingerprints = HashSet::new();
+/// How long the Retry action stays available after the reader was busy.
+pub const RETRY_WINDOW: Duration = Duration::from_secs(10);
 
-    info!("Connecting to fprintd system bus for fingerprint scan");
-    let client = match fprintd::Client::system().await {
-        Ok(client) => {
-            info!("Successfully connected to fprintd system bus");
-            client
-        }
-        Err(e) => {
-            error!("Failed to connect to fprintd system bus: {}", e);
-            error!("This usually means fprintd service is not running or not installed");
-            return enrolled_fingerprints;
-        }
+/// How long a device scan result is reused before listing the fingerprints again.
+const SCAN_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// A successful device scan.
+struct CachedScan {
+    scanned_at: Instant,
+    generation: u64,
+    enrolled: HashSet<Finger>,
+}
+
+/// Recent scan results by (device object path, username).
+/// Held across a scan so concurrent scans of the same device wait and reuse the result
+/// instead of each asking fprintd.
+static SCAN_CACHE: Lazy<Mutex<HashMap<(String, String), CachedScan>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Bumped on every enroll or delete; cached scans from an older generation are stale.
+static SCAN_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Drop cached scan results; call after anything that changes enrolled fingerprints.
+pub fn invalidate_scan_cache() {
+    SCAN_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Enrolled fingerprints of a single device.
+#[derive(Debug, Clone)]
+pub struct DeviceEnrollment {
+    pub path: OwnedObjectPath,
+    pub name: String,
+    pub enrolled: HashSet<Finger>,
+}
+
+/// Properties of a fingerprint reader; `None` when a property could not be read.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub path: Option<String>,
+    pub name: Option<String>,
+    pub scan_type: Option<String>,
+    pub enroll_stages: Option<i32>,
+    pub is_default: Option<bool>,
+}
+
+/// Guidance shown when another application holds the fingerprint reader.
+pub fn device_busy_markup(holder: Option<&str>) -> String {
+    let close = match holder {
+        Some(holder) => format!("Close <b>{}</b>", glib::markup_escape_text(holder)),
+        None => "Close Settings/System Settings or any other fingerprint tool".to_string(),
     };
+    format!(
+        "<span foreground='{}'><b>Another app is using the fingerprint reader.</b> {} and retry.</span>",
+        config::colors().warning,
+        close
+    )
+}
 
-    info!("Searching for available fingerprint devices");
-    let device = match fprintd::first_device(&client).await {
-        Ok(Some(device)) => {
-            info!("Found fingerprint device, proceeding with enrollment scan");
-            device
-        }
-        Ok(None) => {
-            warn!("No fingerprint devices detected on this system");
+/// Message shown when a device operation is refused because another one is running.
+pub fn operation_in_progress_markup() -> String {
+    format!(
+        "<span foreground='{}'><b>Another operation is in progress.</b> Wait for it to finish and try again.</span>",
+        config::colors().warning
+    )
+}
+
+/// Stage counts above this are treated as a driver bug rather than a real requirement.
+const MAX_ENROLL_STAGES: i32 = 100;
+
+/// Keep a reported enroll stage count only if it is usable for "Scan N of M" arithmetic.
+/// fprintd reports -1 when the count is not known; zero, other negative and absurdly large
+/// values (seen on virtual and mock devices) are logged and treated as unknown as well.
+pub fn plausible_enroll_stages(stages: i32) -> Option<i32> {
+    match stages {
+        -1 => None,
+        1..=MAX_ENROLL_STAGES => Some(stages),
+        _ => {
             warn!(
-                "Please ensure your fingerprint reader is connected and recognized by the system"
+                "Device reported an implausible enroll stage count ({}), treating it as unknown",
+                stages
             );
-            return enrolled_fingerprints;
-        }
-        Err(e) => {
-            error!("Failed to enumerate fingerprint devices: {}", e);
-            error!("Check if fprintd service has proper permissions");
-            return enrolled_fingerprints;
+            None
         }
+    }
+}
+
+/// Read the properties of the device at `path` (the first device when `None`).
+/// The number of enroll stages is only known while claimed, so the device is claimed briefly
+/// for `username` (empty for the current user); each property that fails is left `None`.
+pub async fn read_device_info(
+    path: Option<OwnedObjectPath>,
+    username: &str,
+) -> Result<DeviceInfo, DeviceError> {
+    let client = DeviceManager::connect_to_fprintd().await?;
+    let default_path = client.manager().get_default_device().await.ok();
+    let device = match path {
+        Some(path) => client.device(path),
+        None => DeviceManager::get_first_device(&client).await?,
     };
 
-    let username = std::env::var("USER").unwrap_or_default();
-    info!("Scanning enrolled fingerprints for user: '{}'", username);
+    let mut info = DeviceInfo {
+        path: Some(device.object_path().to_string()),
+        name: device.name().await.ok(),
+        scan_type: device.scan_type().await.ok(),
+        is_default: default_path.map(|default| default.as_str() == device.object_path()),
+        ..DeviceInfo::default()
+    };
 
-    info!("Claiming fingerprint device for exclusive access");
-    if let Err(e) = device.claim(&username).await {
-        warn!("Failed to claim device for user '{}': {}", username, e);
-        warn!("Device might be in use by another process");
-    } else {
-        info!("Successfully claimed fingerprint device");
+    let path = Some(device.owned_object_path());
+    let stages = DeviceManager::with_device(path, username, async |claimed| {
+        Ok(claimed.num_enroll_stages().await.ok())
+    })
+    .await;
+    match stages {
+        Ok(stages) => info.enroll_stages = stages.and_then(plausible_enroll_stages),
+        Err(e) => warn!("Could not claim device to read enroll stages: {}", e),
     }
 
-    info!("Retrieving list of enrolled fingerprints");
-    match device.list_enrolled_fingers(&username).await {
-        Ok(list) => {
-            if list.is_empty() {
-                info!("No enrolled fingerprints found for user '{}'", username);
-                info!("User will need to enroll fingerprints before using authentication");
-            } else {
-                info!(
-                    "Found {} enrolled fingerprint(s) for user '{}':",
-                    list.len(),
-                    username
-                );
-                for (i, finger) in list.iter().enumerate() {
-                    info!("{}. {}", i + 1, finger);
-                    enrolled_fingerprints.insert(finger.clone());
-                }
+    Ok(info)
+}
+
+/// Scan for enrolled fingerprints on the system.
+/// Returns the enrolled fingers of `username` (empty for the current user) on the first
+/// device, or why they could not be listed.
+pub async fn scan_enrolled_fingerprints(username: &str) -> Result<HashSet<Finger>, DeviceError> {
+    info!("Connecting to fprintd system bus for fingerprint scan");
+    let client = DeviceManager::connect_to_fprintd().await.inspect_err(|_| {
+        error!("This usually means fprintd service is not running or not installed");
+    })?;
+
+    let device = DeviceManager::get_first_device(&client)
+        .await
+        .inspect_err(|e| {
+            if !matches!(e, DeviceError::NoDeviceAvailable) {
+                error!("Check if fprintd service has proper permissions");
             }
+        })?;
+
+    scan_device(&device, username).await
+}
+
+/// Scan enrolled fingerprints of `username` (empty for the current user) on every available
+/// device. Fails when fprintd can't be reached or any device's list can't be retrieved, so
+/// a failure is never mistaken for no fingerprints being enrolled.
+pub async fn scan_enrolled_fingerprints_by_device(
+    username: &str,
+) -> Result<Vec<DeviceEnrollment>, DeviceError> {
+    info!("Connecting to fprintd system bus for per-device fingerprint scan");
+    let client = DeviceManager::connect_to_fprintd().await.inspect_err(|_| {
+        error!("This usually means fprintd service is not running or not installed");
+    })?;
+
+    let devices = fprintd::all_devices(&client).await.map_err(|e| {
+        error!("Failed to enumerate fingerprint devices: {}", e);
+        DeviceError::from(e)
+    })?;
+
+    if devices.is_empty() {
+        warn!("No fingerprint devices detected on this system");
+    }
+
+    let mut results = Vec::with_capacity(devices.len());
+    for device in devices {
+        let name = device
+            .name()
+            .await
+            .unwrap_or_else(|_| device.object_path().to_string());
+        info!("Scanning device '{}' ({})", name, device.object_path());
+        let enrolled = scan_device(&device, username).await?;
+        results.push(DeviceEnrollment {
+            path: device.owned_object_path(),
+            name,
+            enrolled,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Scan enrolled fingerprints for a user (empty for the current user) on a single device,
+/// reusing a result from the last [`SCAN_CACHE_TTL`] when nothing changed since.
+async fn scan_device(
+    device: &fprintd::Device,
+    username: &str,
+) -> Result<HashSet<Finger>, DeviceError> {
+    let username = if username.is_empty() {
+        util::current_username()
+    } else {
+        username.to_string()
+    };
+
+    let key = (device.object_path().to_string(), username);
+    let mut cache = SCAN_CACHE.lock().await;
+    let generation = SCAN_GENERATION.load(Ordering::SeqCst);
+
+    if let Some(cached) = cache.get(&key) {
+        if cached.generation == generation && cached.scanned_at.elapsed() < SCAN_CACHE_TTL {
+            debug!(
+                "Reusing fingerprint scan of {} for '{}' from {:?} ago",
+                key.0,
+                key.1,
+                cached.scanned_at.elapsed()
+            );
+            return Ok(cached.enrolled.clone());
+        }
+    }
+
+    match scan_device_uncached(device, &key.1).await {
+        Ok(enrolled) => {
+            cache.insert(
+                key,
+                CachedScan {
+                    scanned_at: Instant::now(),
+                    generation,
+                    enrolled: enrolled.clone(),
+                },
+            );
+            Ok(enrolled)
+        }
+        Err(e) => {
+            cache.remove(&key);
+            Err(e)
+        }
+    }
+}
+
+/// List the enrolled fingerprints of `username`.
+/// Listing needs no claim, so a reader held by the login screen or another client is not
+/// contended.
+async fn scan_device_uncached(
+    device: &fprintd::Device,
+    username: &str,
+) -> Result<HashSet<Finger>, DeviceError> {
+    let mut enrolled_fingerprints = HashSet::new();
+    info!("Scanning enrolled fingerprints for user: '{}'", username);
+
+    let list = match device.list_enrolled_fingers(username).await {
+        Ok(list) => list,
+        // fprintd reports an empty list as an error
+        Err(e) if fprintd::is_no_enrolled_prints(&e) => Vec::new(),
+        Err(e) if fprintd::is_permission_denied(&e) => {
+            error!(
+                "Not authorized to access fingerprints of user '{}': {}",
+                username, e
+            );
+            return Err(DeviceError::PermissionDenied(username.to_string()));
         }
         Err(e) => {
             error!("Failed to retrieve enrolled fingerprints: {}", e);
             error!("This might indicate permission issues or device problems");
+            return Err(e.into());
         }
-    }
+    };
 
-    info!("Releasing fingerprint device");
-    if let Err(e) = device.release().await {
-        warn!("Failed to release device: {}", e);
-        warn!("Device might remain locked until fprintd service restart");
+    if list.is_empty() {
+        info!("No enrolled fingerprints found for user '{}'", username);
+        info!("User will need to enroll fingerprints before using authentication");
     } else {
-        info!("Successfully released fingerprint device");
+        info!(
+            "Found {} enrolled fingerprint(s) for user '{}':",
+            list.len(),
+            username
+        );
+        for (i, name) in list.iter().enumerate() {
+            info!("{}. {}", i + 1, name);
+            match Finger::from_dbus_name(name) {
+                Some(finger) => {
+                    enrolled_fingerprints.insert(finger);
+                }
+                None => warn!("Ignoring unknown finger name '{}'", name),
+            }
+        }
     }
 
     info!(
         "Fingerprint scan completed. Found {} enrolled fingerprint(s)",
         enrolled_fingerprints.len()
     );
-    enrolled_fingerprints
+    Ok(enrolled_fingerprints)
 }
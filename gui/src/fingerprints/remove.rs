@@ -1,48 +1,75 @@
 //! Fingerprint removal functionality.
 
+use crate::config;
 use crate::core::context::FingerprintContext;
-use crate::core::device_manager::{DeviceError, DeviceManager};
+use crate::core::device_manager::{DeviceError, DeviceManager, RemovalStep};
+use crate::core::util::Finger;
+use crate::ui::dialogs;
 
 use gtk4::glib;
 
-use gtk4::{prelude::*, ApplicationWindow, Button, CheckButton, Window};
-use log::info;
+use gtk4::Label;
+use log::{info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+
+/// Warning shown before deleting the last fingerprint while fingerprint authentication is on.
+const LOCKOUT_WARNING_MARKUP: &str = "You are attempting to remove your last enrolled fingerprint while fingerprint authentication is still enabled.
+
+<b>This could lock you out of your system!</b>
+
+Please disable all fingerprint authentication toggles on the main page before removing your last fingerprint:
+• Login Authentication
+• Terminal Authentication
+• System Prompts";
+
+/// How long a deletion can be undone before it is sent to fprintd.
+const UNDO_GRACE: Duration = Duration::from_secs(5);
 
 /// Events sent during removal process.
 #[derive(Clone)]
 pub enum RemovalEvent {
-    Success,
+    /// The deletion is no longer undoable and is being sent to fprintd.
+    Started,
+    /// Intermediate progress message.
+    Status(String),
+    Success(&'static str),
+    Unsupported,
+    /// Another application holds the reader; carries its name when known.
+    DeviceBusy(Option<String>),
     Error(String),
 }
 
 /// Start fingerprint removal process for specified finger.
-pub fn start_removal(finger_key: String, ctx: FingerprintContext) {
-    info!("User clicked 'Delete' button for finger: '{}'", finger_key);
+pub fn start_removal(finger: Finger, ctx: FingerprintContext) {
+    info!("User clicked 'Delete' button for finger: '{}'", finger);
 
     // Check toggles state before async operation
     let any_toggle_active = ctx.has_active_pam_switches();
 
     // Only proceed with check if toggles are active
     if !any_toggle_active {
-        proceed_with_removal(finger_key, ctx);
+        proceed_with_removal(finger, ctx);
         return;
     }
 
     // Check if this would be the last fingerprint
     let rt_clone = ctx.rt.clone();
-    let finger_key_clone = finger_key.clone();
+    let finger_to_check = finger;
 
     let (tx, rx) = mpsc::channel::<bool>();
 
     let ctx_for_check = ctx.clone();
-    let finger_key_for_check = finger_key.clone();
+    let finger_for_check = finger;
     glib::idle_add_local(move || match rx.try_recv() {
         Ok(is_last_fingerprint) => {
             if is_last_fingerprint {
-                show_lockout_warning_dialog(finger_key_for_check.clone(), ctx_for_check.clone());
+                show_lockout_warning_dialog(finger_for_check, ctx_for_check.clone());
             } else {
-                proceed_with_removal(finger_key_for_check.clone(), ctx_for_check.clone());
+                proceed_with_removal(finger_for_check, ctx_for_check.clone());
             }
             glib::ControlFlow::Break
         }
@@ -50,86 +77,158 @@ pub fn start_removal(finger_key: String, ctx: FingerprintContext) {
         Err(TryRecvError::Disconnected) => glib::ControlFlow::Break,
     });
 
+    let username = ctx.username();
+    let lock = ctx.operation_lock.clone();
     rt_clone.spawn(async move {
-        let enrolled = crate::fingerprints::scan_enrolled_fingerprints().await;
-        let is_last_fingerprint = enrolled.len() == 1 && enrolled.contains(&finger_key_clone);
+        let _guard = lock.lock().await;
+        // Warn when unsure rather than risk locking the user out
+        let is_last_fingerprint =
+            match crate::fingerprints::scan_enrolled_fingerprints(&username).await {
+                Ok(enrolled) => enrolled.len() == 1 && enrolled.contains(&finger_to_check),
+                Err(e) => {
+                    warn!(
+                        "Could not check whether this is the last fingerprint: {}",
+                        e
+                    );
+                    true
+                }
+            };
 
         let _ = tx.send(is_last_fingerprint);
     });
 }
 
 /// Show lockout warning dialog when attempting to remove last fingerprint with toggles enabled.
-fn show_lockout_warning_dialog(finger_key: String, ctx: FingerprintContext) {
+fn show_lockout_warning_dialog(finger: Finger, ctx: FingerprintContext) {
     info!("Showing lockout warning dialog - last fingerprint with active auth toggles");
 
-    let builder =
-        gtk4::Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/lockout_warning_dialog.ui");
-    let dialog: Window = builder
-        .object("lockout_warning_window")
-        .expect("Failed to get lockout_warning_window");
-
-    // Get parent window for modal behavior
-    if let Some(toplevel) = ctx.ui.stack.root() {
-        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
-            dialog.set_transient_for(Some(app_window));
+    let confirmed = dialogs::confirm(
+        &ctx.ui.stack,
+        "Potential System Lockout",
+        LOCKOUT_WARNING_MARKUP,
+        "Delete Anyway",
+        Some("I understand the risk and want to delete anyway"),
+    );
+    glib::spawn_future_local(async move {
+        if confirmed.await {
+            info!("User chose to proceed with deletion despite lockout warning");
+            proceed_with_removal(finger, ctx);
+        } else {
+            info!("User cancelled deletion to avoid lockout");
         }
-    }
-
-    let cancel_button: Button = builder
-        .object("cancel_button")
-        .expect("Failed to get cancel_button");
-    let proceed_button: Button = builder
-        .object("proceed_button")
-        .expect("Failed to get proceed_button");
-    let confirmation_check: CheckButton = builder
-        .object("confirmation_check")
-        .expect("Failed to get confirmation_check");
-
-    // Enable/disable proceed button based on checkbox state
-    let proceed_button_clone = proceed_button.clone();
-    confirmation_check.connect_toggled(move |check| {
-        proceed_button_clone.set_sensitive(check.is_active());
     });
+}
 
-    let dialog_clone = dialog.clone();
-    cancel_button.connect_clicked(move |_| {
-        info!("User cancelled deletion to avoid lockout");
-        dialog_clone.close();
-    });
+/// Proceed with the removal, hiding the finger at once and deleting it after the undo window.
+fn proceed_with_removal(finger: Finger, ctx: FingerprintContext) {
+    info!(
+        "Scheduling deletion of '{}' in {}s unless undone",
+        finger,
+        UNDO_GRACE.as_secs()
+    );
 
-    let dialog_clone = dialog.clone();
-    proceed_button.connect_clicked(move |_| {
-        info!("User chose to proceed with deletion despite lockout warning");
-        dialog_clone.close();
-        proceed_with_removal(finger_key.clone(), ctx.clone());
-    });
+    ctx.forget_enrolled_finger(finger);
+    crate::ui::fingerprint_ui::update_fingerprint_ui(&ctx);
+    ctx.ui.labels.action.set_label("Fingerprint deleted.");
 
-    dialog.present();
-}
+    // Cleared by whichever comes first: the undo click or the deletion task
+    let pending = Arc::new(AtomicBool::new(true));
+    let (tx, rx) = mpsc::channel::<RemovalEvent>();
 
-/// Proceed with the actual removal process.
-fn proceed_with_removal(finger_key: String, ctx: FingerprintContext) {
-    info!("Starting fingerprint deletion process");
+    let ctx_retry = ctx.clone();
+    setup_removal_ui_listener(rx, ctx.clone(), move || {
+        info!("User retried deleting '{}'", finger);
+        proceed_with_removal(finger, ctx_retry.clone());
+    });
 
-    ctx.ui
-        .labels
-        .action
-        .set_label("Deleting enrolled fingerprint...");
-    let (tx, rx) = mpsc::channel::<RemovalEvent>();
+    {
+        let pending = pending.clone();
+        let ctx_undo = ctx.clone();
+        ctx.toast
+            .show("Fingerprint deleted", "Undo", UNDO_GRACE, move || {
+                if pending.swap(false, Ordering::SeqCst) {
+                    info!("User undid deletion of '{}'", finger);
+                    ctx_undo.ui.labels.action.set_label("Deletion undone.");
+                } else {
+                    warn!("Undo for '{}' arrived after deletion started", finger);
+                }
+                crate::ui::fingerprint_ui::refresh_fingerprint_display(ctx_undo);
+            });
+    }
 
-    setup_removal_ui_listener(rx, ctx.clone());
-    spawn_removal_task(finger_key, tx, ctx);
+    spawn_removal_task(finger, pending, tx, ctx);
 }
 
 /// Set up UI listener for removal status updates.
-fn setup_removal_ui_listener(rx: mpsc::Receiver<RemovalEvent>, ctx: FingerprintContext) {
+/// `retry` is offered when the reader is busy; the busy state set when the deletion starts
+/// is cleared once the task ends.
+fn setup_removal_ui_listener(
+    rx: mpsc::Receiver<RemovalEvent>,
+    ctx: FingerprintContext,
+    retry: impl Fn() + Clone + 'static,
+) {
     let action_label = ctx.ui.labels.action.clone();
     let _rt = ctx.rt.clone();
 
-    glib::idle_add_local(move || match rx.try_recv() {
-        Ok(RemovalEvent::Success) => {
+    // Only a deletion that got as far as starting set the busy state
+    let mut started = false;
+
+    glib::idle_add_local(move || {
+        let event = rx.try_recv();
+        started |= matches!(event, Ok(RemovalEvent::Started));
+        let flow = handle_removal_event(event, &ctx, &action_label, &retry);
+        if flow.is_break() && started {
+            ctx.set_busy(false);
+        }
+        flow
+    });
+}
+
+/// Apply one removal event to the UI and tell whether to keep listening.
+fn handle_removal_event(
+    event: Result<RemovalEvent, TryRecvError>,
+    ctx: &FingerprintContext,
+    action_label: &Label,
+    retry: &(impl Fn() + Clone + 'static),
+) -> glib::ControlFlow {
+    match event {
+        Ok(RemovalEvent::Started) => {
+            ctx.set_busy(true);
+            glib::ControlFlow::Continue
+        }
+        Ok(RemovalEvent::Status(text)) => {
             action_label.set_use_markup(true);
-            action_label.set_markup("<span color='orange'>Fingerprint deleted.</span>");
+            action_label.set_markup(&text);
+            glib::ControlFlow::Continue
+        }
+        Ok(RemovalEvent::Success(text)) => {
+            action_label.set_use_markup(true);
+            action_label.set_markup(&format!(
+                "<span foreground='{}'>{}</span>",
+                config::colors().warning,
+                text
+            ));
+            crate::ui::fingerprint_ui::refresh_fingerprint_display(ctx.clone());
+            glib::ControlFlow::Break
+        }
+        Ok(RemovalEvent::Unsupported) => {
+            action_label.set_use_markup(true);
+            action_label.set_markup(&format!(
+                "<span foreground='{}'><b>Per-finger deletion isn't supported on this device.</b></span>",
+                config::colors().warning
+            ));
+            show_delete_all_dialog(ctx.clone());
+            glib::ControlFlow::Break
+        }
+        Ok(RemovalEvent::DeviceBusy(holder)) => {
+            action_label.set_use_markup(true);
+            action_label.set_markup(&super::device_busy_markup(holder.as_deref()));
+            ctx.toast.show(
+                "Fingerprint reader busy",
+                "Retry",
+                super::RETRY_WINDOW,
+                retry.clone(),
+            );
             crate::ui::fingerprint_ui::refresh_fingerprint_display(ctx.clone());
             glib::ControlFlow::Break
         }
@@ -141,30 +240,176 @@ fn setup_removal_ui_listener(rx: mpsc::Receiver<RemovalEvent>, ctx: FingerprintC
         }
         Err(TryRecvError::Empty) => glib::ControlFlow::Continue,
         Err(TryRecvError::Disconnected) => glib::ControlFlow::Break,
-    });
+    }
 }
 
-/// Spawn async removal task.
-fn spawn_removal_task(finger_key: String, tx: mpsc::Sender<RemovalEvent>, ctx: FingerprintContext) {
+/// Spawn async removal task that waits out the undo window before deleting.
+fn spawn_removal_task(
+    finger: Finger,
+    pending: Arc<AtomicBool>,
+    tx: mpsc::Sender<RemovalEvent>,
+    ctx: FingerprintContext,
+) {
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+    let lock = ctx.operation_lock.clone();
+
     ctx.rt.spawn(async move {
-        info!("Starting fingerprint deletion process for '{}'", finger_key);
+        tokio::time::sleep(UNDO_GRACE).await;
+        if !pending.swap(false, Ordering::SeqCst) {
+            info!("Deletion of '{}' was undone", finger);
+            return;
+        }
+
+        let Ok(_guard) = lock.try_lock_owned() else {
+            warn!(
+                "Not deleting '{}' while another operation is running",
+                finger
+            );
+            let _ = tx.send(RemovalEvent::Error(super::operation_in_progress_markup()));
+            return;
+        };
 
-        let result = DeviceManager::delete_finger(finger_key.clone()).await;
+        info!("Starting fingerprint deletion process for '{}'", finger);
+        let _ = tx.send(RemovalEvent::Started);
+
+        let result =
+            DeviceManager::delete_finger(finger, device_path, &username, step_reporter(&tx)).await;
+        super::invalidate_scan_cache();
 
         match result {
             Ok(()) => {
                 info!("Fingerprint deletion completed successfully");
-                let _ = tx.send(RemovalEvent::Success);
+                super::metadata::forget(&username, finger);
+                let _ = tx.send(RemovalEvent::Success("Fingerprint deleted."));
+            }
+            Err(DeviceError::DeleteUnsupported) => {
+                warn!("Single finger deletion unsupported, offering to delete all");
+                let _ = tx.send(RemovalEvent::Unsupported);
+            }
+            Err(DeviceError::DeviceBusy(holder)) => {
+                let _ = tx.send(RemovalEvent::DeviceBusy(holder));
+            }
+            Err(e) => {
+                let _ = tx.send(RemovalEvent::Error(removal_error_markup(&e)));
+            }
+        }
+    });
+}
+
+/// Offer to delete all fingerprints when the device cannot delete a single one.
+fn show_delete_all_dialog(ctx: FingerprintContext) {
+    info!("Showing delete-all dialog - per-finger deletion unsupported");
+
+    let mut body = String::from(
+        "This fingerprint reader or fprintd version cannot delete individual fingerprints. \
+         You can delete all of your enrolled fingerprints on this device instead and enroll \
+         the ones you want to keep again.",
+    );
+    if ctx.has_active_pam_switches() {
+        body.push_str(
+            "\n\n<b>Fingerprint authentication is still enabled.</b> \
+             Disable it on the main page first to avoid being locked out.",
+        );
+    }
+
+    let confirmed = dialogs::confirm(
+        &ctx.ui.stack,
+        "Single Deletion Not Supported",
+        &body,
+        "Delete All",
+        None,
+    );
+    glib::spawn_future_local(async move {
+        if confirmed.await {
+            info!("User chose to delete all fingerprints");
+            start_delete_all(ctx);
+        } else {
+            info!("User declined deleting all fingerprints");
+        }
+    });
+}
+
+/// Delete every enrolled fingerprint on the selected device.
+fn start_delete_all(ctx: FingerprintContext) {
+    let Some(guard) = ctx.try_begin_operation() else {
+        return;
+    };
+    ctx.ui
+        .labels
+        .action
+        .set_label("Deleting all enrolled fingerprints...");
+    let (tx, rx) = mpsc::channel::<RemovalEvent>();
+
+    let ctx_retry = ctx.clone();
+    setup_removal_ui_listener(rx, ctx.clone(), move || {
+        info!("User retried deleting all fingerprints");
+        start_delete_all(ctx_retry.clone());
+    });
+    spawn_delete_all_task(tx, ctx, guard);
+}
+
+/// Spawn async task deleting every enrolled fingerprint on the selected device,
+/// holding the operation lock until it finishes.
+fn spawn_delete_all_task(
+    tx: mpsc::Sender<RemovalEvent>,
+    ctx: FingerprintContext,
+    guard: OwnedMutexGuard<()>,
+) {
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+
+    ctx.rt.spawn(async move {
+        let _guard = guard;
+        let _ = tx.send(RemovalEvent::Started);
+        let result =
+            DeviceManager::delete_all_fingers(device_path, &username, step_reporter(&tx)).await;
+        super::invalidate_scan_cache();
+
+        match result {
+            Ok(()) => {
+                super::metadata::forget_all(&username);
+                let _ = tx.send(RemovalEvent::Success("All fingerprints deleted."));
+            }
+            Err(DeviceError::DeviceBusy(holder)) => {
+                let _ = tx.send(RemovalEvent::DeviceBusy(holder));
             }
             Err(e) => {
-                let error_msg = match e {
-                    DeviceError::NoDeviceAvailable => {
-                        "<span color='orange'>No fingerprint devices available.</span>".to_string()
-                    }
-                    _ => format!("<span color='red'><b>Delete failed</b>: {}</span>", e),
-                };
-                let _ = tx.send(RemovalEvent::Error(error_msg));
+                let _ = tx.send(RemovalEvent::Error(removal_error_markup(&e)));
             }
         }
     });
 }
+
+/// Forward removal steps to the listener as status messages.
+fn step_reporter(tx: &mpsc::Sender<RemovalEvent>) -> impl FnMut(RemovalStep) + Send {
+    let tx = tx.clone();
+    move |step| {
+        let text = match step {
+            RemovalStep::Claiming => "Claiming fingerprint reader…",
+            RemovalStep::Deleting => "Deleting fingerprint data…",
+            RemovalStep::Releasing => "Releasing fingerprint reader…",
+        };
+        info!("Removal step: {:?}", step);
+        let _ = tx.send(RemovalEvent::Status(format!(
+            "<span foreground='{}'>{}</span>",
+            config::colors().process,
+            text
+        )));
+    }
+}
+
+/// Format a removal error for the action label.
+fn removal_error_markup(e: &DeviceError) -> String {
+    match e {
+        DeviceError::NoDeviceAvailable => format!(
+            "<span foreground='{}'>No fingerprint devices available.</span>",
+            config::colors().warning
+        ),
+        _ => format!(
+            "<span foreground='{}'><b>Delete failed</b>: {}</span>",
+            config::colors().error,
+            glib::markup_escape_text(&e.to_string())
+        ),
+    }
+}
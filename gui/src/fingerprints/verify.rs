@@ -0,0 +1,224 @@
+//! Self-tests verifying enrolled fingers.
+
+use crate::config;
+use crate::core::device_manager::{DeviceError, DeviceManager};
+use crate::core::util::{Finger, FINGERS};
+use crate::core::FingerprintContext;
+use gtk4::prelude::*;
+use gtk4::{glib, ApplicationWindow, Builder, Button, Grid, Label, Spinner, Window};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, TryRecvError};
+
+/// Progress of the verification sequence.
+enum VerifyEvent {
+    /// The user is asked to present `finger`, the `index`-th of `total`.
+    Prompt {
+        finger: Finger,
+        index: usize,
+        total: usize,
+    },
+    /// Whether each finger, by fprintd name, matched.
+    Finished(Result<HashMap<String, bool>, DeviceError>),
+}
+
+/// Verify every enrolled finger, reporting progress in `hint` and the results in a summary dialog.
+pub fn start_verify_all(ctx: FingerprintContext, button: Button, spinner: Spinner, hint: Label) {
+    let Some(guard) = ctx.try_begin_operation() else {
+        return;
+    };
+
+    button.set_sensitive(false);
+    spinner.set_visible(true);
+    spinner.start();
+    hint.set_label("Claiming the fingerprint reader…");
+
+    let (tx, rx) = mpsc::channel::<VerifyEvent>();
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+
+    ctx.rt.spawn(async move {
+        let _guard = guard;
+        let tx_prompt = tx.clone();
+        let on_finger = move |index, total, finger| {
+            let _ = tx_prompt.send(VerifyEvent::Prompt {
+                finger,
+                index,
+                total,
+            });
+        };
+
+        let result = DeviceManager::verify_fingers(device_path, &username, on_finger).await;
+        let _ = tx.send(VerifyEvent::Finished(result));
+    });
+
+    glib::idle_add_local(move || loop {
+        let result = match rx.try_recv() {
+            Ok(VerifyEvent::Prompt {
+                finger,
+                index,
+                total,
+            }) => {
+                hint.set_label(&format!(
+                    "Finger {} of {}: place your {} on the reader…",
+                    index + 1,
+                    total,
+                    finger.display_name().to_lowercase()
+                ));
+                continue;
+            }
+            Ok(VerifyEvent::Finished(result)) => result,
+            Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(TryRecvError::Disconnected) => Err(DeviceError::OperationFailed(
+                "Verification task ended unexpectedly".to_string(),
+            )),
+        };
+
+        spinner.stop();
+        spinner.set_visible(false);
+        button.set_sensitive(true);
+
+        match result {
+            Ok(results) if results.is_empty() => {
+                hint.set_label("No enrolled fingerprints to verify.");
+            }
+            Ok(results) => {
+                let matched = results.values().filter(|&&matched| matched).count();
+                info!("Verified {} of {} fingerprints", matched, results.len());
+                hint.set_label(&format!(
+                    "{} of {} fingerprints verified.",
+                    matched,
+                    results.len()
+                ));
+                show_summary_dialog(&button, &results);
+            }
+            Err(e) => {
+                warn!("Fingerprint verification failed: {}", e);
+                hint.set_label(&format!("Could not verify fingerprints: {}", e));
+            }
+        }
+
+        return glib::ControlFlow::Break;
+    });
+}
+
+/// Verify any enrolled finger once and report in `hint` which finger fprintd selected.
+pub fn start_identify(ctx: FingerprintContext, button: Button, spinner: Spinner, hint: Label) {
+    let Some(guard) = ctx.try_begin_operation() else {
+        return;
+    };
+
+    button.set_sensitive(false);
+    spinner.set_visible(true);
+    spinner.start();
+    hint.set_label("Place any enrolled finger on the reader…");
+
+    let (tx, rx) = mpsc::channel::<Result<(bool, Option<String>), DeviceError>>();
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+
+    ctx.rt.spawn(async move {
+        let _guard = guard;
+        let _ = tx.send(DeviceManager::identify_finger(device_path, &username).await);
+    });
+
+    glib::idle_add_local(move || {
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(TryRecvError::Disconnected) => Err(DeviceError::OperationFailed(
+                "Verification task ended unexpectedly".to_string(),
+            )),
+        };
+
+        spinner.stop();
+        spinner.set_visible(false);
+        button.set_sensitive(true);
+
+        // Devices that can identify announce "any" instead of a specific finger
+        let finger = |selected: Option<&str>| {
+            selected
+                .and_then(Finger::from_dbus_name)
+                .map(|finger| finger.display_name().to_lowercase())
+        };
+        match result {
+            Ok((true, selected)) => hint.set_label(&match finger(selected.as_deref()) {
+                Some(name) => format!("Matched your {}.", name),
+                None => "Matched an enrolled finger.".to_string(),
+            }),
+            Ok((false, selected)) => hint.set_label(&match finger(selected.as_deref()) {
+                Some(name) => format!("No match against your {}.", name),
+                None => "No enrolled finger matched.".to_string(),
+            }),
+            Err(e) => {
+                warn!("Any finger verification failed: {}", e);
+                hint.set_label(&format!("Could not verify: {}", e));
+            }
+        }
+
+        glib::ControlFlow::Break
+    });
+}
+
+/// Show which enrolled fingers matched, in finger order.
+fn show_summary_dialog(anchor: &Button, results: &HashMap<String, bool>) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/verify_summary_dialog.ui");
+    let dialog: Window = builder
+        .object("verify_summary_window")
+        .expect("Failed to get verify_summary_window");
+    let message: Label = builder
+        .object("verify_summary_message")
+        .expect("Failed to get verify_summary_message");
+    let grid: Grid = builder
+        .object("verify_summary_grid")
+        .expect("Failed to get verify_summary_grid");
+    let close_button: Button = builder
+        .object("verify_summary_close_button")
+        .expect("Failed to get verify_summary_close_button");
+
+    let failed = results.values().filter(|&&matched| !matched).count();
+    message.set_markup(&if failed == 0 {
+        "Every enrolled fingerprint was recognized.".to_string()
+    } else {
+        format!(
+            "<span foreground='{}'><b>{} fingerprint(s) were not recognized.</b></span>\nRe-enroll them so they keep working at the login prompt.",
+            config::colors().warning,
+            failed
+        )
+    });
+
+    let row_of = |finger: &Finger| results.get(finger.to_dbus_name()).map(|&m| (*finger, m));
+    for (row, (finger, matched)) in FINGERS.iter().filter_map(row_of).enumerate() {
+        let row = row as i32;
+        let name = Label::new(Some(finger.display_name()));
+        name.set_halign(gtk4::Align::Start);
+        let status = Label::new(None);
+        status.set_halign(gtk4::Align::Start);
+        status.set_markup(&if matched {
+            format!(
+                "<span foreground='{}'>✅ Verified</span>",
+                config::colors().success
+            )
+        } else {
+            format!(
+                "<span foreground='{}'>❌ Not recognized</span>",
+                config::colors().error
+            )
+        });
+        grid.attach(&name, 0, row, 1, 1);
+        grid.attach(&status, 1, row, 1, 1);
+    }
+
+    if let Some(toplevel) = anchor.root() {
+        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
+            dialog.set_transient_for(Some(app_window));
+        }
+    }
+
+    let dialog_clone = dialog.clone();
+    close_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
@@ -1,16 +1,49 @@
+use clap::Parser;
 use gtk4::prelude::*;
 use gtk4::Application;
 use log::info;
 
+mod cli;
 mod config;
 mod core;
+mod doctor;
 mod fingerprints;
 mod pam;
 mod ui;
 
 fn main() {
-    // Initialize logger
-    simple_logger::SimpleLogger::new().init().unwrap();
+    let mut cli = cli::Cli::parse();
+
+    // Keep headless output readable; the doctor report already covers warnings
+    let default_level = if cli.doctor {
+        log::LevelFilter::Error
+    } else if cli.is_headless() {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    };
+
+    // Initialize logger, keeping recent lines for the diagnostics report
+    if let Err(e) = core::logging::init(default_level, cli.log_level(), cli.log_file.as_deref()) {
+        eprintln!("Failed to initialize logging: {}", e);
+        std::process::exit(1);
+    }
+
+    // Testing aid: stands in for --user so fprintd is asked about that account, too
+    if cli.user.is_none() {
+        cli.user = core::util::username_override();
+    }
+
+    if let Some(user) = cli.user.as_deref() {
+        if !core::util::user_exists(user) {
+            eprintln!("Unknown user '{}'", user);
+            std::process::exit(2);
+        }
+    }
+
+    if cli.is_headless() {
+        std::process::exit(cli::run(&cli));
+    }
 
     info!(
         "Starting {} v{}",
@@ -19,11 +52,22 @@ fn main() {
     );
     info!("Application ID: {}", config::app_info::ID);
 
+    // Load the color palette up front so config problems are reported at startup
+    ui::app::register_resources();
+    config::load_colors();
+
     let app = Application::builder()
         .application_id(config::app_info::ID)
         .build();
 
-    app.connect_activate(ui::setup_application_ui);
+    let username = cli.user.clone().unwrap_or_default();
+    let force = cli.force;
+    let allow_unsupported = cli.allow_unsupported;
+    app.connect_activate(move |app| {
+        ui::setup_application_ui(app, &username, force, allow_unsupported)
+    });
 
-    app.run();
+    // Arguments were already handled by clap; don't let GTK reject them
+    let program: Vec<String> = std::env::args().take(1).collect();
+    app.run_with_args(&program);
 }
@@ -1,17 +1,197 @@
 use crate::config;
 use log::{debug, error, info, warn};
+use serde::Deserialize;
+use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Utility for managing PAM fingerprint configurations.
 pub struct PamHelper;
 
+/// Configuration status of one path, as printed by `xfprintd-gui-helper check --json`.
+#[derive(Debug, Deserialize)]
+struct CheckStatus {
+    path: String,
+    applied: bool,
+}
+
+/// Fence marker audit of one PAM file, as printed by `xfprintd-gui-helper audit --json`.
+#[derive(Debug, Deserialize)]
+struct BlockAudit {
+    path: String,
+    malformed: bool,
+}
+
+/// A PAM service the helper has a patch for, as printed by `xfprintd-gui-helper services --json`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManagedService {
+    /// Service name, the file name of the PAM file.
+    pub service: String,
+    pub path: String,
+}
+
 /// PAM file paths (using configuration).
 pub const SUDO_PATH: &str = "/etc/pam.d/sudo";
-pub const POLKIT_PATH: &str = "/etc/pam.d/polkit-1";
 pub const LOGIN_PATH: &str = "/etc/pam.d/login";
 pub const SDDM_PATH: &str = "/etc/pam.d/sddm";
 
+/// PAM files of the login service; which one is used depends on the display manager.
+pub const LOGIN_PATHS: &[&str] = &[LOGIN_PATH, SDDM_PATH];
+
+/// Vendor directory holding the distribution defaults the helper copies from when a
+/// target PAM file does not exist yet.
+/// The helper also probes the other vendor PAM directories for the same file name.
+const DEFAULT_SOURCE_DIR: &str = "/usr/lib/pam.d";
+
+/// Get the distribution default to start from when the PAM file path is missing.
+pub fn default_source(path: &str) -> Option<String> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    Some(format!("{}/{}", DEFAULT_SOURCE_DIR, name))
+}
+
+/// PAM module referenced by the fingerprint configuration blocks.
+pub const FPRINTD_MODULE: &str = "pam_fprintd.so";
+
+/// Message shown when the fingerprint PAM module is missing.
+pub const MODULE_MISSING_MESSAGE: &str =
+    "Fingerprint PAM module not installed — install fprintd-pam";
+
+/// Exit codes pkexec uses when the authentication dialog is dismissed or fails.
+const PKEXEC_AUTH_EXIT_CODES: &[i32] = &[126, 127];
+
+/// Exit codes of the helper's `apply` and the message shown for each.
+const APPLY_FAILURE_MESSAGES: &[(i32, &str)] = &[
+    (3, "This PAM file is not managed by xfprintd-gui"),
+    (
+        4,
+        "No fingerprint configuration is installed for this PAM file — reinstall xfprintd-gui",
+    ),
+    (
+        5,
+        "The fingerprint configuration patch is invalid or not trusted",
+    ),
+    (
+        6,
+        "A PAM module used by the fingerprint configuration is not installed",
+    ),
+    (7, "Could not write the PAM file"),
+    (
+        8,
+        "The change would have broken the PAM file, so it was rolled back",
+    ),
+    (
+        9,
+        "This PAM file already uses pam_fprintd.so outside xfprintd-gui; remove that line first to avoid being asked for a finger twice",
+    ),
+    (
+        10,
+        "Your system configuration is read-only; fingerprint auth can't be enabled here",
+    ),
+];
+
+/// User facing message for a failed helper `apply`, falling back to the helper's output.
+/// A hint from the helper, such as SELinux or AppArmor refusing the write or which
+/// directory is mounted read-only, is kept.
+fn apply_failure_message(code: Option<i32>, stderr: &str) -> String {
+    let Some((_, message)) = APPLY_FAILURE_MESSAGES
+        .iter()
+        .find(|(known, _)| Some(*known) == code)
+    else {
+        return command_failure_message(code, stderr);
+    };
+    match helper_hint(stderr) {
+        Some(hint) => format!("{}. {}", message, hint),
+        None => message.to_string(),
+    }
+}
+
+/// The "Hint: …" the helper appends to an error it can explain further.
+fn helper_hint(stderr: &str) -> Option<&str> {
+    stderr
+        .lines()
+        .find_map(|line| line.find("Hint: ").map(|start| line[start..].trim_end()))
+}
+
+/// Why a PAM configuration change through the helper was not made.
+#[derive(Debug)]
+pub enum PamOpError {
+    /// User dismissed or failed the authentication prompt.
+    UserCancelled,
+    /// The helper binary is not installed.
+    HelperMissing,
+    /// The helper ran and refused or failed; carries the message to show.
+    HelperFailed(String),
+    /// pkexec itself could not be started.
+    Spawn(io::Error),
+}
+
+impl fmt::Display for PamOpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserCancelled => write!(f, "Authentication was cancelled"),
+            Self::HelperMissing => write!(
+                f,
+                "Helper tool not found at {}; reinstall xfprintd-gui",
+                config::helper::BINARY_PATH
+            ),
+            Self::HelperFailed(msg) => write!(f, "{}", msg),
+            Self::Spawn(e) => write!(f, "Failed to execute pkexec: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PamOpError {}
+
+/// Result of running a privileged command through pkexec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PkexecOutcome {
+    /// Helper ran and reported success.
+    Success,
+    /// User dismissed or failed the authentication prompt.
+    Cancelled,
+    /// pkexec or the helper failed; carries the error message.
+    Failed(String),
+}
+
+/// Directories PAM loads modules from: `/usr/lib*/security`, `/lib*/security`
+/// and their Debian-style multiarch subdirectories.
+fn pam_module_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    for root in ["/usr", "/"] {
+        let Ok(entries) = std::fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with("lib") {
+                continue;
+            }
+            let lib = entry.path();
+            dirs.push(lib.join("security"));
+
+            if let Ok(arches) = std::fs::read_dir(&lib) {
+                dirs.extend(
+                    arches
+                        .flatten()
+                        .filter(|arch| arch.file_name().to_string_lossy().contains("-linux-"))
+                        .map(|arch| arch.path().join("security")),
+                );
+            }
+        }
+    }
+
+    dirs
+}
+
+/// Locate the fingerprint PAM module, if installed.
+pub fn find_fprintd_module() -> Option<PathBuf> {
+    pam_module_dirs()
+        .into_iter()
+        .map(|dir| dir.join(FPRINTD_MODULE))
+        .find(|path| path.is_file())
+}
+
 /// Returns the appropriate login PAM path based on active display manager.
 /// Uses SDDM path if sddm.service is enabled, otherwise uses generic login path.
 pub fn get_login_path() -> &'static str {
@@ -48,94 +228,115 @@ pub fn is_sddm_enabled() -> bool {
 }
 
 impl PamHelper {
-    /// Check configuration status for all services (batch operation).
-    /// Returns (login_configured, sudo_configured, polkit_configured).
-    pub fn check_all_configurations() -> (bool, bool, bool) {
-        info!("Checking fingerprint authentication status for all PAM services");
-        info!("Performing batch check of all PAM configurations");
-
-        let login_path = get_login_path();
-        info!("Using login path: {}", login_path);
-
-        match Command::new(config::helper::BINARY_PATH)
-            .arg("check")
-            .arg(login_path)
-            .arg(SUDO_PATH)
-            .arg(POLKIT_PATH)
-            .output()
-        {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let mut login = false;
-                let mut sudo = false;
-                let mut polkit = false;
-
-                debug!("PAM helper output:\n{}", stdout);
-
-                for line in stdout.lines() {
-                    if let Some(path) = line.strip_prefix("applied: ") {
-                        // Check both possible login paths
-                        if path == LOGIN_PATH || path == SDDM_PATH {
-                            login = true;
-                            info!("Login PAM configuration: ENABLED ({})", path);
-                        } else {
-                            match path {
-                                SUDO_PATH => {
-                                    sudo = true;
-                                    info!("Sudo PAM configuration: ENABLED");
-                                }
-                                POLKIT_PATH => {
-                                    polkit = true;
-                                    info!("Polkit PAM configuration: ENABLED");
-                                }
-                                _ => {
-                                    debug!("Unknown PAM configuration found: {}", path);
-                                }
-                            }
-                        }
-                    } else if let Some(path) = line.strip_prefix("not-applied: ") {
-                        // Check both possible login paths
-                        if path == LOGIN_PATH || path == SDDM_PATH {
-                            info!("Login PAM configuration: DISABLED ({})", path);
-                        } else {
-                            match path {
-                                SUDO_PATH => info!("Sudo PAM configuration: DISABLED"),
-                                POLKIT_PATH => info!("Polkit PAM configuration: DISABLED"),
-                                _ => debug!("Unknown PAM path not configured: {}", path),
-                            }
-                        }
-                    }
-                }
+    /// Check configuration status for each of the PAM file paths (batch operation).
+    /// Returns one status per path, in the same order.
+    pub fn check_configurations(paths: &[&str]) -> Vec<bool> {
+        info!(
+            "Checking fingerprint authentication status for {} PAM files",
+            paths.len()
+        );
+        if paths.is_empty() {
+            return Vec::new();
+        }
 
-                let exit_code = output.status.code().unwrap_or(-1);
-                info!("PAM batch check completed (exit code: {})", exit_code);
-                info!(
-                    "Final PAM status: login={}, sudo={}, polkit={}",
-                    login, sudo, polkit
-                );
-                (login, sudo, polkit)
-            }
+        match Self::check_paths(paths) {
+            Ok(statuses) => paths
+                .iter()
+                .map(|path| {
+                    let applied = statuses
+                        .iter()
+                        .any(|status| status.path == *path && status.applied);
+                    info!(
+                        "PAM configuration of {}: {}",
+                        path,
+                        if applied { "ENABLED" } else { "DISABLED" }
+                    );
+                    applied
+                })
+                .collect(),
             Err(e) => {
                 warn!("Batch PAM check failed: {}", e);
-                warn!("Fallback to individual service checks");
                 warn!("Using fallback method: checking PAM configurations individually");
+                paths.iter().map(|path| Self::is_configured(path)).collect()
+            }
+        }
+    }
 
-                let login_path = get_login_path();
-                let login = Self::is_configured(login_path);
-                let sudo = Self::is_configured(SUDO_PATH);
-                let polkit = Self::is_configured(POLKIT_PATH);
+    /// Ask the helper whether the fingerprint configuration is applied to each path.
+    /// The helper exits with 1 when some path is not configured, which is not an error here.
+    fn check_paths(paths: &[&str]) -> io::Result<Vec<CheckStatus>> {
+        let output = Command::new(config::helper::BINARY_PATH)
+            .arg("check")
+            .arg("--json")
+            .args(paths)
+            .output()?;
 
-                info!(
-                    "Individual PAM check results: login={}, sudo={}, polkit={}",
-                    login, sudo, polkit
-                );
-                info!(
-                    "Final PAM status: login={}, sudo={}, polkit={}",
-                    login, sudo, polkit
-                );
-                (login, sudo, polkit)
-            }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        debug!("PAM helper output:\n{}", stdout);
+        info!(
+            "PAM batch check completed (exit code: {})",
+            output.status.code().unwrap_or(-1)
+        );
+
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "helper check failed: {}",
+                stderr.trim()
+            )));
         }
+
+        serde_json::from_str(&stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// List the PAM services the helper has patch files for.
+    pub fn managed_services() -> io::Result<Vec<ManagedService>> {
+        let output = Command::new(config::helper::BINARY_PATH)
+            .arg("services")
+            .arg("--json")
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "helper services failed: {}",
+                stderr.trim()
+            )));
+        }
+
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Find PAM files whose xfprintd-gui blocks are duplicated or unbalanced, as an
+    /// interrupted change can leave them. The helper exits with 1 when it finds one.
+    pub fn find_malformed_blocks() -> io::Result<Vec<String>> {
+        let output = Command::new(config::helper::BINARY_PATH)
+            .arg("audit")
+            .arg("--json")
+            .output()?;
+
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "helper audit failed: {}",
+                stderr.trim()
+            )));
+        }
+
+        let audits: Vec<BlockAudit> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(audits
+            .into_iter()
+            .filter(|audit| audit.malformed)
+            .map(|audit| audit.path)
+            .collect())
+    }
+
+    /// Rewrite every PAM file with malformed blocks to hold at most one well-formed block.
+    pub fn repair_blocks() -> Result<(), PamOpError> {
+        info!("Repairing malformed fingerprint PAM blocks");
+        Self::run_privileged("audit", "--fix", command_failure_message)
     }
 
     /// Check if fingerprint configuration is applied for path.
@@ -170,96 +371,221 @@ impl PamHelper {
         }
     }
 
-    /// Apply fingerprint configuration for PAM file path using pkexec.
-    pub fn apply_configuration(path: &str) -> io::Result<()> {
-        info!(
-            "Applying fingerprint PAM configuration for path: '{}'",
-            path
-        );
-        info!("Requesting root privileges via pkexec");
+    /// List services included into the auth stack of the PAM file path.
+    pub fn auth_includes(path: &str) -> Vec<String> {
+        match Command::new(config::helper::BINARY_PATH)
+            .arg("includes")
+            .arg(path)
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                let includes: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                debug!("PAM path '{}' includes: {:?}", path, includes);
+                includes
+            }
+            Ok(output) => {
+                warn!(
+                    "Failed to detect includes for '{}': {}",
+                    path,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                Vec::new()
+            }
+            Err(e) => {
+                warn!("Failed to execute helper for include detection: {}", e);
+                Vec::new()
+            }
+        }
+    }
 
-        // Build JSON object with optional default file
-        let json_arg = if path == POLKIT_PATH {
-            format!(
-                r#"{{"file":"{}","default":"/usr/lib/pam.d/polkit-1"}}"#,
-                path
-            )
-        } else {
-            format!(r#"{{"file":"{}"}}"#, path)
-        };
+    /// Get the fenced block the helper would insert into the PAM file path.
+    pub fn show_configuration(path: &str) -> io::Result<String> {
+        debug!("Requesting configuration block preview for '{}'", path);
 
-        let output = Command::new("pkexec")
-            .arg(config::helper::BINARY_PATH)
-            .arg("apply")
-            .arg(&json_arg)
+        let output = Command::new(config::helper::BINARY_PATH)
+            .arg("show")
+            .arg(path)
             .output()
             .map_err(|e| {
-                error!("Failed to execute pkexec for PAM configuration: {}", e);
-                error!("Make sure polkit is installed and configured properly");
-                io::Error::other(format!("Failed to execute pkexec: {}", e))
+                error!("Failed to execute helper for configuration preview: {}", e);
+                io::Error::other(format!("Failed to execute helper: {}", e))
             })?;
 
         if !output.status.success() {
             let err = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            error!("PAM configuration failed for path '{}': {}", path, err);
-            if !stdout.is_empty() {
-                debug!("Helper stdout: {}", stdout);
+            warn!("Configuration preview failed for path '{}': {}", path, err);
+            return Err(io::Error::other(format!("Helper failed: {}", err.trim())));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Probe the PAM service for path with pamtester and report whether pam_fprintd.so is reached.
+    pub fn selftest(path: &str) -> io::Result<bool> {
+        let service = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::other(format!("Invalid PAM path: {}", path)))?;
+
+        info!("Running PAM self-test for service '{}'", service);
+
+        let output = Command::new(config::helper::BINARY_PATH)
+            .arg("selftest")
+            .arg(service)
+            .output()
+            .map_err(|e| {
+                error!("Failed to execute helper for PAM self-test: {}", e);
+                io::Error::other(format!("Failed to execute helper: {}", e))
+            })?;
+
+        match output.status.code() {
+            Some(0) => {
+                info!("PAM self-test: '{}' reaches pam_fprintd.so", service);
+                Ok(true)
+            }
+            Some(1) => {
+                info!("PAM self-test: '{}' does not reach pam_fprintd.so", service);
+                Ok(false)
+            }
+            _ => {
+                let err = String::from_utf8_lossy(&output.stderr);
+                warn!("PAM self-test failed for '{}': {}", service, err);
+                Err(io::Error::other(format!("Helper failed: {}", err.trim())))
             }
-            return Err(io::Error::other(format!("Helper failed: {}", err)));
         }
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+    /// Apply fingerprint configuration for PAM file path using pkexec.
+    pub fn apply_configuration(path: &str) -> Result<(), PamOpError> {
         info!(
-            "Successfully applied fingerprint PAM configuration for '{}'",
+            "Applying fingerprint PAM configuration for path: '{}'",
             path
         );
-        if !stdout.is_empty() {
-            info!("Helper response: {}", stdout.trim());
+
+        // Enabling a block that references a missing module breaks the service
+        match find_fprintd_module() {
+            Some(module) => info!("Found fingerprint PAM module at {}", module.display()),
+            None => {
+                warn!(
+                    "{} not found, refusing to apply configuration",
+                    FPRINTD_MODULE
+                );
+                return Err(PamOpError::HelperFailed(MODULE_MISSING_MESSAGE.to_string()));
+            }
+        }
+
+        // Build JSON object with optional default file
+        let json_arg = match default_source(path) {
+            Some(default) => {
+                debug!("Using '{}' as default source for '{}'", default, path);
+                serde_json::json!({ "file": path, "default": default })
+            }
+            None => serde_json::json!({ "file": path }),
         }
+        .to_string();
+
+        Self::run_privileged("apply", &json_arg, apply_failure_message)?;
+        info!(
+            "Successfully applied fingerprint PAM configuration for '{}'",
+            path
+        );
         Ok(())
     }
 
     /// Remove fingerprint configuration for PAM file path using pkexec.
-    pub fn remove_configuration(path: &str) -> io::Result<()> {
+    pub fn remove_configuration(path: &str) -> Result<(), PamOpError> {
         info!(
             "Removing fingerprint PAM configuration for path: '{}'",
             path
         );
-        info!("Requesting root privileges via pkexec");
 
-        let output = Command::new("pkexec")
-            .arg(config::helper::BINARY_PATH)
-            .arg("remove")
-            .arg(path)
-            .output()
-            .map_err(|e| {
-                error!("Failed to execute pkexec for PAM removal: {}", e);
-                error!("Make sure polkit is installed and configured properly");
-                io::Error::other(format!("Failed to execute pkexec: {}", e))
-            })?;
-
-        if !output.status.success() {
-            let err = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            error!(
-                "PAM configuration removal failed for path '{}': {}",
-                path, err
-            );
-            if !stdout.is_empty() {
-                debug!("Helper stdout: {}", stdout);
-            }
-            return Err(io::Error::other(format!("Helper failed: {}", err)));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        Self::run_privileged("remove", path, command_failure_message)?;
         info!(
             "Successfully removed fingerprint PAM configuration for '{}'",
             path
         );
+        Ok(())
+    }
+
+    /// Run a helper subcommand with a single argument through pkexec.
+    /// pkexec reports a missing program like a dismissed prompt, so check for it first.
+    fn run_privileged(
+        subcommand: &str,
+        arg: &str,
+        failure_message: impl Fn(Option<i32>, &str) -> String,
+    ) -> Result<(), PamOpError> {
+        if !Path::new(config::helper::BINARY_PATH).is_file() {
+            error!("Helper tool not found at {}", config::helper::BINARY_PATH);
+            return Err(PamOpError::HelperMissing);
+        }
+        pkexec(
+            config::helper::BINARY_PATH,
+            &[subcommand, arg],
+            failure_message,
+        )
+    }
+}
+
+/// Run a program with arguments through pkexec.
+pub fn run_pkexec(program: &str, args: &[&str]) -> PkexecOutcome {
+    match pkexec(program, args, command_failure_message) {
+        Ok(()) => PkexecOutcome::Success,
+        Err(PamOpError::UserCancelled) => PkexecOutcome::Cancelled,
+        Err(e) => PkexecOutcome::Failed(e.to_string()),
+    }
+}
+
+/// Describe a failed privileged command by its stderr.
+fn command_failure_message(_code: Option<i32>, stderr: &str) -> String {
+    format!("Command failed: {}", stderr.trim())
+}
+
+/// Run a program with arguments through pkexec, describing a failure from the
+/// program's exit code and stderr with `failure_message`.
+fn pkexec(
+    program: &str,
+    args: &[&str],
+    failure_message: impl Fn(Option<i32>, &str) -> String,
+) -> Result<(), PamOpError> {
+    info!("Requesting root privileges via pkexec");
+
+    let output = match Command::new("pkexec").arg(program).args(args).output() {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Failed to execute pkexec for '{}': {}", program, e);
+            error!("Make sure polkit is installed and configured properly");
+            return Err(PamOpError::Spawn(e));
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
         if !stdout.is_empty() {
-            info!("Helper response: {}", stdout.trim());
+            info!("Privileged command response: {}", stdout.trim());
+        }
+        return Ok(());
+    }
+
+    let err = String::from_utf8_lossy(&output.stderr);
+    if !stdout.is_empty() {
+        debug!("Privileged command stdout: {}", stdout);
+    }
+
+    match output.status.code() {
+        Some(code) if PKEXEC_AUTH_EXIT_CODES.contains(&code) => {
+            info!(
+                "Privilege escalation for '{}' was cancelled (exit code {})",
+                program, code
+            );
+            Err(PamOpError::UserCancelled)
+        }
+        code => {
+            error!("'{} {}' failed: {}", program, args.join(" "), err);
+            Err(PamOpError::HelperFailed(failure_message(code, &err)))
         }
-        Ok(())
     }
 }
@@ -1,94 +1,317 @@
 //! Generic PAM switch handler functionality.
 
-use crate::pam::helper::PamHelper;
-#[allow(unused_imports)]
+use crate::config;
+use crate::pam::helper::{PamHelper, PamOpError};
+use crate::ui::dialogs;
 use gtk4::prelude::*;
-use gtk4::{glib, Switch};
-use log::{error, info};
+use gtk4::{glib, ApplicationWindow, Builder, Button, Label, Spinner, Switch, Window};
+use log::{error, info, warn};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Whether the include hint has already been shown during this session.
+static INCLUDE_HINT_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Whether the login fallback notice has already been shown during this session.
+static LOGIN_NOTICE_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Explains that password login keeps working after enabling fingerprint login.
+const LOGIN_NOTICE_MARKUP: &str = "Your password still works at the login screen. The fingerprint rule is <b>sufficient</b>, not <b>required</b>: if the reader is missing, busy or does not recognize you, login falls back to asking for your password.
+
+To avoid depending on a single print, enroll at least <b>two fingers</b>, ideally one on each hand.";
 
 /// PAM service configuration for switch handlers.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PamService {
-    pub name: &'static str,
-    pub path: &'static str,
+    pub name: String,
+    pub path: String,
 }
 
-/// Available PAM services that can be configured.
+/// Available PAM services that can be configured, as listed by the helper.
+/// The display manager PAM files are offered as a single login service; every other
+/// patch the helper has is a service of its own, named after its PAM file.
 pub mod services {
     use super::PamService;
-    use crate::pam::helper::{get_login_path, POLKIT_PATH, SUDO_PATH};
+    use crate::pam::helper::{get_login_path, ManagedService, PamHelper, LOGIN_PATHS};
+    use std::io;
 
-    pub fn login() -> PamService {
-        PamService {
-            name: "login",
-            path: get_login_path(),
+    /// Name of the login service, whose PAM file depends on the display manager.
+    pub const LOGIN_NAME: &str = "login";
+
+    /// Services for the patches listed by the helper, login first.
+    /// Login is only offered when the helper has a patch for the active display manager.
+    pub fn from_managed(managed: &[ManagedService]) -> Vec<PamService> {
+        let login_path = get_login_path();
+        let mut services = Vec::new();
+
+        if managed.iter().any(|service| service.path == login_path) {
+            services.push(PamService {
+                name: LOGIN_NAME.to_string(),
+                path: login_path.to_string(),
+            });
         }
+        services.extend(
+            managed
+                .iter()
+                .filter(|service| !LOGIN_PATHS.contains(&service.path.as_str()))
+                .map(|service| PamService {
+                    name: service.service.clone(),
+                    path: service.path.clone(),
+                }),
+        );
+
+        services
     }
 
-    pub const SUDO: PamService = PamService {
-        name: "sudo",
-        path: SUDO_PATH,
-    };
+    /// List the services the helper has patches for.
+    pub fn list() -> io::Result<Vec<PamService>> {
+        Ok(from_managed(&PamHelper::managed_services()?))
+    }
 
-    pub const POLKIT: PamService = PamService {
-        name: "polkit",
-        path: POLKIT_PATH,
-    };
+    /// Find a service by its name or the path of its PAM file.
+    pub fn find<'a>(services: &'a [PamService], name: &str) -> Option<&'a PamService> {
+        services
+            .iter()
+            .find(|service| service.name == name || service.path == name)
+    }
 }
 
 /// Set up a generic PAM switch handler for any service.
-pub fn setup_pam_switch(switch: &Switch, service: PamService) {
-    let service_name = service.name.to_string();
-    let service_path = service.path;
+/// The helper runs on the Tokio runtime; the switch state is committed once it returns.
+/// State changes are not applied while `initializing` is set.
+pub fn setup_pam_switch(
+    switch: &Switch,
+    spinner: &Spinner,
+    service: PamService,
+    rt: Arc<Runtime>,
+    initializing: Rc<Cell<bool>>,
+) {
+    // Set while the result is written back so it isn't treated as user input
+    let syncing = Rc::new(Cell::new(false));
+    let spinner = spinner.clone();
+
+    switch.connect_state_set(move |switch, state| {
+        if syncing.get() {
+            return glib::Propagation::Proceed;
+        }
+        if initializing.get() {
+            info!(
+                "Ignoring {} switch change during initialization",
+                service.name
+            );
+            return glib::Propagation::Proceed;
+        }
 
-    switch.connect_state_set(move |_switch, state| {
-        handle_pam_toggle(state, &service_name, service_path)
+        handle_pam_toggle(switch, &spinner, state, &service, &rt, syncing.clone());
+        glib::Propagation::Stop
     });
 }
 
 /// Handle PAM toggle for any service (generic implementation).
-fn handle_pam_toggle(state: bool, service_name: &str, service_path: &str) -> glib::Propagation {
+fn handle_pam_toggle(
+    switch: &Switch,
+    spinner: &Spinner,
+    state: bool,
+    service: &PamService,
+    rt: &Runtime,
+    syncing: Rc<Cell<bool>>,
+) {
     if state {
         info!(
             "User enabled {} fingerprint authentication switch",
-            service_name
+            service.name
         );
     } else {
         info!(
             "User disabled {} fingerprint authentication switch",
-            service_name
+            service.name
         );
     }
 
-    let result = if state {
-        PamHelper::apply_configuration(service_path)
-    } else {
-        PamHelper::remove_configuration(service_path)
-    };
+    switch.set_sensitive(false);
+    spinner.set_visible(true);
+    spinner.start();
+
+    let (tx, rx) = mpsc::channel::<Result<(), PamOpError>>();
+    let service_path = service.path.clone();
+
+    rt.spawn_blocking(move || {
+        let result = if state {
+            PamHelper::apply_configuration(&service_path)
+        } else {
+            PamHelper::remove_configuration(&service_path)
+        };
+        let _ = tx.send(result);
+    });
+
+    let switch = switch.clone();
+    let spinner = spinner.clone();
+    let service = service.clone();
+
+    glib::idle_add_local(move || {
+        let outcome = match rx.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(TryRecvError::Disconnected) => {
+                warn!(
+                    "PAM helper task for {} ended without a result",
+                    service.name
+                );
+                Err(PamOpError::HelperFailed(
+                    "Helper task ended unexpectedly".to_string(),
+                ))
+            }
+        };
+
+        spinner.stop();
+        spinner.set_visible(false);
+        switch.set_sensitive(true);
+        finish_pam_toggle(&switch, state, &service, outcome, &syncing);
+        glib::ControlFlow::Break
+    });
+}
 
-    match result {
+/// Commit or revert the switch based on the helper outcome.
+fn finish_pam_toggle(
+    switch: &Switch,
+    state: bool,
+    service: &PamService,
+    outcome: Result<(), PamOpError>,
+    syncing: &Cell<bool>,
+) {
+    let applied = match outcome {
         Ok(()) => {
             if state {
                 info!(
                     "Successfully enabled fingerprint authentication for {}",
-                    service_name
+                    service.name
                 );
             } else {
                 info!(
                     "Successfully disabled fingerprint authentication for {}",
-                    service_name
+                    service.name
                 );
             }
-            glib::Propagation::Proceed
+            true
+        }
+        // Cancelling the prompt is a choice, not an error worth a dialog
+        Err(PamOpError::UserCancelled) => {
+            info!(
+                "Authentication cancelled, reverting {} switch without changes",
+                service.name
+            );
+            false
         }
         Err(e) => {
             error!(
                 "Failed to {} fingerprint authentication for {}: {}",
                 if state { "enable" } else { "disable" },
-                service_name,
+                service.name,
                 e
             );
-            glib::Propagation::Stop
+            show_pam_error_dialog(switch, state, &service.name, &e.to_string());
+            false
         }
+    };
+
+    let final_state = if applied { state } else { !state };
+    syncing.set(true);
+    switch.set_active(final_state);
+    switch.set_state(final_state);
+    syncing.set(false);
+
+    if applied && state {
+        if service.name == services::LOGIN_NAME {
+            maybe_show_login_notice(switch);
+        }
+        maybe_show_include_hint(switch, &service.path);
+    }
+}
+
+/// Explain once that password login keeps working, the first time the login switch is enabled.
+fn maybe_show_login_notice(switch: &Switch) {
+    if config::login_notice_shown() || LOGIN_NOTICE_SHOWN.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    info!("Login fingerprint enabled for the first time, showing fallback notice");
+    if let Err(e) = config::mark_login_notice_shown() {
+        warn!("Failed to remember that the login notice was shown: {}", e);
     }
+
+    dialogs::show_message(switch, "Fingerprint Login Enabled", LOGIN_NOTICE_MARKUP);
+}
+
+/// Show error dialog explaining why a PAM switch was reverted.
+fn show_pam_error_dialog(switch: &Switch, state: bool, service_name: &str, details: &str) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/pam_error_dialog.ui");
+
+    let dialog: Window = builder
+        .object("pam_error_window")
+        .expect("Failed to get pam_error_window");
+    let title_label: Label = builder
+        .object("pam_error_title")
+        .expect("Failed to get pam_error_title");
+    let message_label: Label = builder
+        .object("pam_error_message")
+        .expect("Failed to get pam_error_message");
+    let detail_label: Label = builder
+        .object("pam_error_detail")
+        .expect("Failed to get pam_error_detail");
+    let ok_button: Button = builder
+        .object("pam_error_ok_button")
+        .expect("Failed to get pam_error_ok_button");
+
+    title_label.set_label("Configuration Failed");
+    message_label.set_label(&format!(
+        "Could not {} fingerprint authentication for {}.",
+        if state { "enable" } else { "disable" },
+        service_name
+    ));
+    detail_label.set_label(details);
+
+    if let Some(toplevel) = switch.root() {
+        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
+            dialog.set_transient_for(Some(app_window));
+        }
+    }
+
+    let dialog_clone = dialog.clone();
+    ok_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
+
+/// Explain once per session that the enabled PAM file delegates to shared rules.
+fn maybe_show_include_hint(switch: &Switch, service_path: &str) {
+    let includes = PamHelper::auth_includes(service_path);
+    if includes.is_empty() || INCLUDE_HINT_SHOWN.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    info!(
+        "{} includes shared auth rules from {:?}, showing hint",
+        service_path, includes
+    );
+
+    let include_list = includes
+        .iter()
+        .map(|include| format!("<b>{}</b>", glib::markup_escape_text(include)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!(
+        "<b>{}</b> pulls its authentication rules from {}.\n\n\
+         The fingerprint rule was inserted before those rules, so it is tried first. \
+         Changes made to the shared files later apply here too, and other services \
+         using them are not affected by this switch.",
+        glib::markup_escape_text(service_path),
+        include_list
+    );
+    dialogs::show_message(switch, "Shared Authentication Rules", &message);
 }
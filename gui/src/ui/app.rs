@@ -1,15 +1,23 @@
 //! Application setup and initialization functionality.
 
-use crate::core::{system, FingerprintContext};
-use crate::ui::{button_handlers, fingerprint_ui, navigation, pam_ui};
+use crate::config;
+use crate::core::{device_manager, system, FingerprintContext};
+use crate::ui::{
+    button_handlers, device_info, fingerprint_ui, fprintd_banner, navigation, pam_ui, preferences,
+    shortcuts, theme,
+};
 use gtk4::glib;
 use gtk4::prelude::*;
-use gtk4::{gio, Application, ApplicationWindow, Builder, CssProvider};
+use gtk4::{gio, Application, ApplicationWindow, Builder};
 use log::{info, warn};
 
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
+/// How long closing the window waits for an interrupted operation to release the device.
+const CLOSE_CLEANUP_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Main application context with UI elements and runtime.
 #[derive(Clone)]
 pub struct AppContext {
@@ -17,7 +25,14 @@ pub struct AppContext {
 }
 
 /// Initialize and set up main application UI.
-pub fn setup_application_ui(app: &Application) {
+/// `username` selects whose fingerprints are managed; empty for the current user.
+/// `force` skips the supported distribution check; `allow_unsupported` turns it into a banner.
+pub fn setup_application_ui(
+    app: &Application,
+    username: &str,
+    force: bool,
+    allow_unsupported: bool,
+) {
     info!("Initializing application components");
 
     let rt = Arc::new(
@@ -28,7 +43,7 @@ pub fn setup_application_ui(app: &Application) {
     );
     info!("Tokio async runtime initialized");
 
-    setup_resources_and_theme();
+    setup_resources_and_theme(&rt);
 
     // Create single builder for all UI components
     let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/main.ui");
@@ -36,19 +51,38 @@ pub fn setup_application_ui(app: &Application) {
 
     window.show();
 
-    system::check_distribution_support(&window);
+    system::check_distribution_support(&window, &builder, force, allow_unsupported);
 
     info!("Performing system environment checks");
-    system::check_fprintd_service();
-    system::check_helper_tool();
+    let fprintd_active = system::check_fprintd_service();
+    let pam_module_installed = system::check_pam_fprintd_module();
+    if system::check_helper_tool() {
+        system::check_helper_version(&builder);
+    }
     system::check_pkexec_availability();
 
-    let ctx = setup_ui_components(&window, rt, &builder);
+    let ctx = setup_ui_components(&window, rt, &builder, username);
+    setup_close_cleanup(&window, ctx.fingerprint_ctx.rt.clone());
+    setup_runtime_shutdown(app, ctx.fingerprint_ctx.rt.clone());
+
+    ctx.fingerprint_ctx
+        .pam_module_installed
+        .set(pam_module_installed);
 
     // Setup UI components by category
-    pam_ui::setup_pam_switches(&ctx);
+    pam_ui::setup_pam_switches(&ctx, &builder);
+    pam_ui::setup_selftest_button(&ctx, &builder);
     navigation::setup_navigation_and_dialogs(&ctx, &builder, &window);
+    preferences::setup_preferences_button(&ctx, &builder, &window);
+    fprintd_banner::setup_fprintd_banner(&ctx, &builder, fprintd_active);
     button_handlers::setup_button_handlers(&ctx);
+    button_handlers::setup_verify_all_button(&ctx, &builder);
+    button_handlers::setup_identify_button(&ctx, &builder);
+    shortcuts::setup_shortcuts(&ctx, &builder, app, &window);
+    device_info::setup_device_info_button(&ctx, &builder);
+    fingerprint_ui::setup_device_selector(&ctx);
+    fingerprint_ui::setup_user_selector(&ctx, &builder, &window);
+    fingerprint_ui::setup_scan_retry(&ctx);
     fingerprint_ui::perform_initial_fingerprint_scan(&ctx);
 
     info!("Setting initial view to main page");
@@ -56,23 +90,20 @@ pub fn setup_application_ui(app: &Application) {
     info!("XFPrintD GUI application startup complete");
 }
 
-/// Set up resources and theme.
-fn setup_resources_and_theme() {
+/// Register the compiled gresources; must run before any resource is loaded.
+pub fn register_resources() {
     gio::resources_register_include!("xyz.xerolinux.xfprintd_gui.gresource")
         .expect("Failed to register gresources");
+}
 
+/// Set up resources and theme.
+fn setup_resources_and_theme(rt: &Runtime) {
     if let Some(display) = gtk4::gdk::Display::default() {
         info!("Setting up UI theme and styling");
         let theme = gtk4::IconTheme::for_display(&display);
         theme.add_resource_path("/xyz/xerolinux/xfprintd_gui/icons");
 
-        let css_provider = CssProvider::new();
-        css_provider.load_from_resource("/xyz/xerolinux/xfprintd_gui/css/style.css");
-        gtk4::style_context_add_provider_for_display(
-            &display,
-            &css_provider,
-            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-        );
+        theme::setup_theme(&display, rt);
         info!("UI theme and styling loaded successfully");
     } else {
         warn!("No default display found - UI theming may not work properly");
@@ -89,9 +120,103 @@ fn create_main_window(app: &Application, builder: &Builder) -> ApplicationWindow
     info!("Setting window icon to fingerprint");
     window.set_icon_name(Some("xfprintd-gui"));
 
+    restore_window_geometry(&window);
+    window.connect_close_request(|window| {
+        save_window_geometry(window);
+        glib::Propagation::Proceed
+    });
+
     window
 }
 
+/// Apply the window size saved by the previous session, clamped to the monitor so a size
+/// saved on a larger display does not open partly off-screen.
+/// GTK 4 exposes no work area on Wayland, so the full monitor geometry is the bound.
+fn restore_window_geometry(window: &ApplicationWindow) {
+    let Some(saved) = config::load_window_geometry() else {
+        return;
+    };
+    if saved.width <= 0 || saved.height <= 0 {
+        warn!(
+            "Ignoring saved window size {}x{}",
+            saved.width, saved.height
+        );
+        return;
+    }
+
+    let (mut width, mut height) = (saved.width, saved.height);
+    let monitor = WidgetExt::display(window)
+        .monitors()
+        .item(0)
+        .and_downcast::<gtk4::gdk::Monitor>();
+    if let Some(area) = monitor.map(|monitor| monitor.geometry()) {
+        width = width.min(area.width());
+        height = height.min(area.height());
+    }
+
+    info!("Restoring window size {}x{}", width, height);
+    window.set_default_size(width, height);
+}
+
+/// Remember the current window size; a maximized window keeps the size it had before.
+fn save_window_geometry(window: &ApplicationWindow) {
+    if window.is_maximized() {
+        return;
+    }
+
+    let (width, height) = window.default_size();
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    if let Err(e) = config::save_window_geometry(config::WindowGeometry { width, height }) {
+        warn!("Failed to save window size: {}", e);
+    }
+}
+
+/// Release a device left claimed by an unfinished operation when the window is closed.
+fn setup_close_cleanup(window: &ApplicationWindow, rt: Arc<Runtime>) {
+    window.connect_close_request(move |_| {
+        info!("Main window closing - releasing any claimed fingerprint device");
+        let cleanup = tokio::time::timeout(
+            CLOSE_CLEANUP_TIMEOUT,
+            device_manager::release_active_device(),
+        );
+        if rt.block_on(cleanup).is_err() {
+            warn!("Timed out releasing the fingerprint device");
+        }
+        glib::Propagation::Proceed
+    });
+}
+
+/// Release the device and shut the runtime down once the application quits, so listener
+/// tasks and deferred releases don't outlive the window.
+fn setup_runtime_shutdown(app: &Application, rt: Arc<Runtime>) {
+    let rt = std::cell::Cell::new(Some(rt));
+    app.connect_shutdown(move |_| {
+        let Some(rt) = rt.take() else {
+            return;
+        };
+
+        // Covers quitting without a close request, e.g. from the application menu
+        let cleanup = tokio::time::timeout(
+            CLOSE_CLEANUP_TIMEOUT,
+            device_manager::release_active_device(),
+        );
+        if rt.block_on(cleanup).is_err() {
+            warn!("Timed out releasing the fingerprint device");
+        }
+
+        match Arc::try_unwrap(rt) {
+            Ok(rt) => {
+                info!("Shutting down the async runtime");
+                rt.shutdown_timeout(CLOSE_CLEANUP_TIMEOUT);
+            }
+            // Still held by a pending callback; its tasks end with the process
+            Err(_) => warn!("Async runtime still in use at shutdown, not waiting for its tasks"),
+        }
+    });
+}
+
 /// Helper to extract widgets from builder with consistent error handling.
 pub fn extract_widget<T: IsA<glib::Object>>(builder: &Builder, name: &str) -> T {
     builder
@@ -104,29 +229,72 @@ fn setup_ui_components(
     _window: &ApplicationWindow,
     rt: Arc<Runtime>,
     builder: &Builder,
+    username: &str,
 ) -> AppContext {
     // Extract all widgets using helper
     let stack = extract_widget(builder, "stack");
     let fingers_flow = extract_widget(builder, "fingers_flow");
+    let scan_status_box = extract_widget(builder, "scan_status_box");
+    let scan_spinner = extract_widget(builder, "scan_spinner");
+    let scan_status_label = extract_widget(builder, "scan_status_label");
+    let scan_retry_button = extract_widget(builder, "scan_retry_button");
+    let device_dropdown = extract_widget(builder, "device_dropdown");
     let finger_label = extract_widget(builder, "finger_label");
+    let enrolled_label = extract_widget(builder, "enrolled_label");
     let action_label = extract_widget(builder, "action_label");
+    let finger_icon = extract_widget(builder, "finger_icon");
+    let stage_dots = extract_widget(builder, "stage_dots");
     let button_add = extract_widget(builder, "button_add");
+    let button_reenroll = extract_widget(builder, "button_reenroll");
     let button_delete = extract_widget(builder, "button_delete");
+    let manage_btn = extract_widget(builder, "manage_btn");
+    let guided_setup_btn = extract_widget(builder, "guided_setup_btn");
+    let back_btn = extract_widget(builder, "back_btn");
+    let button_back = extract_widget(builder, "button_back");
     let sw_login = extract_widget(builder, "sw_login");
-    let sw_term = extract_widget(builder, "sw_term");
-    let sw_prompt = extract_widget(builder, "sw_prompt");
+    let sp_login = extract_widget(builder, "sp_login");
 
     info!("All UI components successfully initialized from Glade builder");
 
     // Assemble UI components using builder pattern
-    let switches = crate::core::context::PamSwitches::new(sw_login, sw_term, sw_prompt);
-    let labels = crate::core::context::FingerprintLabels::new(finger_label, action_label);
-    let buttons = crate::core::context::FingerprintButtons::new(button_add, button_delete);
-    let ui =
-        crate::core::context::UiComponents::new(fingers_flow, stack, switches, labels, buttons);
+    let switches = crate::core::context::PamSwitches::new(sw_login, sp_login);
+    let labels = crate::core::context::FingerprintLabels::new(
+        finger_label,
+        enrolled_label,
+        action_label,
+        finger_icon,
+        stage_dots,
+    );
+    let scan_status = crate::core::context::ScanStatus::new(
+        scan_status_box,
+        scan_spinner,
+        scan_status_label,
+        scan_retry_button,
+    );
+    let buttons =
+        crate::core::context::FingerprintButtons::new(button_add, button_reenroll, button_delete);
+    let navigation = crate::core::context::NavigationButtons::new(
+        manage_btn,
+        guided_setup_btn,
+        back_btn,
+        button_back,
+    );
+    let ui = crate::core::context::UiComponents::new(
+        fingers_flow,
+        scan_status,
+        device_dropdown,
+        stack,
+        switches,
+        labels,
+        buttons,
+        navigation,
+    );
 
     let selected_finger = std::rc::Rc::new(std::cell::RefCell::new(None));
-    let fingerprint_ctx = FingerprintContext::new(rt, ui, selected_finger);
+    let toast = crate::ui::toast::Toast::from_builder(builder);
+    let settings = config::Settings::load();
+    let fingerprint_ctx =
+        FingerprintContext::new(rt, ui, selected_finger, username, toast, settings);
 
     AppContext { fingerprint_ctx }
 }
@@ -1,15 +1,19 @@
 //! Button click handlers functionality.
 
 use crate::core::FingerprintContext;
-use crate::fingerprints::{enroll, remove};
-use crate::ui::app::AppContext;
+use crate::fingerprints::{enroll, remove, verify};
+use crate::ui::app::{extract_widget, AppContext};
 use gtk4::prelude::*;
-use gtk4::Button;
+use gtk4::{Builder, Button, Label, Spinner};
 use log::info;
 
 /// Set up all button handlers.
 pub fn setup_button_handlers(ctx: &AppContext) {
     setup_enroll_button(&ctx.fingerprint_ctx.ui.buttons.add, &ctx.fingerprint_ctx);
+    setup_reenroll_button(
+        &ctx.fingerprint_ctx.ui.buttons.reenroll,
+        &ctx.fingerprint_ctx,
+    );
     setup_delete_button(&ctx.fingerprint_ctx.ui.buttons.delete, &ctx.fingerprint_ctx);
 }
 
@@ -26,6 +30,17 @@ fn setup_enroll_button(button_add: &Button, ctx: &FingerprintContext) {
     });
 }
 
+/// Set up re-enroll button.
+fn setup_reenroll_button(button_reenroll: &Button, ctx: &FingerprintContext) {
+    let ctx_clone = ctx.clone();
+    button_reenroll.connect_clicked(move |_| {
+        if let Some(key) = ctx_clone.get_selected_finger() {
+            info!("User clicked 'Re-enroll' button for finger: '{}'", key);
+            enroll::confirm_reenrollment(key, ctx_clone.clone());
+        }
+    });
+}
+
 /// Set up delete button.
 fn setup_delete_button(button_delete: &Button, ctx: &FingerprintContext) {
     let ctx_clone = ctx.clone();
@@ -35,3 +50,39 @@ fn setup_delete_button(button_delete: &Button, ctx: &FingerprintContext) {
         }
     });
 }
+
+/// Set up the button verifying every enrolled finger.
+pub fn setup_verify_all_button(ctx: &AppContext, builder: &Builder) {
+    let button: Button = extract_widget(builder, "verify_all_btn");
+    let spinner: Spinner = extract_widget(builder, "sp_verify_all");
+    let hint: Label = extract_widget(builder, "lbl_verify_all_hint");
+    let ctx_clone = ctx.fingerprint_ctx.clone();
+
+    button.connect_clicked(move |button| {
+        info!("User clicked 'Verify' for all enrolled fingers");
+        verify::start_verify_all(
+            ctx_clone.clone(),
+            button.clone(),
+            spinner.clone(),
+            hint.clone(),
+        );
+    });
+}
+
+/// Set up the button verifying any enrolled finger.
+pub fn setup_identify_button(ctx: &AppContext, builder: &Builder) {
+    let button: Button = extract_widget(builder, "identify_btn");
+    let spinner: Spinner = extract_widget(builder, "sp_identify");
+    let hint: Label = extract_widget(builder, "lbl_identify_hint");
+    let ctx_clone = ctx.fingerprint_ctx.clone();
+
+    button.connect_clicked(move |button| {
+        info!("User clicked 'Scan' for any enrolled finger");
+        verify::start_identify(
+            ctx_clone.clone(),
+            button.clone(),
+            spinner.clone(),
+            hint.clone(),
+        );
+    });
+}
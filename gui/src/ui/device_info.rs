@@ -0,0 +1,117 @@
+//! Dialog listing the properties of the selected fingerprint reader.
+
+use crate::core::FingerprintContext;
+use crate::fingerprints::{self, DeviceInfo};
+use crate::ui::app::{extract_widget, AppContext};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Align, ApplicationWindow, Builder, Button, Grid, Label, Spinner, Window};
+use log::{info, warn};
+use std::sync::mpsc::{self, TryRecvError};
+
+/// Set up the info button on the manage page.
+pub fn setup_device_info_button(ctx: &AppContext, builder: &Builder) {
+    let device_info_btn: Button = extract_widget(builder, "device_info_btn");
+
+    let ctx = ctx.fingerprint_ctx.clone();
+    device_info_btn.connect_clicked(move |_| {
+        info!("User clicked device info button - showing reader details");
+        show_device_info_dialog(&ctx);
+    });
+}
+
+/// Show the reader details dialog and fill it in once the properties have been read.
+fn show_device_info_dialog(ctx: &FingerprintContext) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/device_info_dialog.ui");
+    let dialog: Window = extract_widget(&builder, "device_info_window");
+    let grid: Grid = extract_widget(&builder, "device_info_grid");
+    let spinner: Spinner = extract_widget(&builder, "device_info_spinner");
+    let close_button: Button = extract_widget(&builder, "device_info_close_button");
+
+    if let Some(toplevel) = ctx.ui.stack.root() {
+        if let Some(app_window) = toplevel.downcast_ref::<ApplicationWindow>() {
+            dialog.set_transient_for(Some(app_window));
+        }
+    }
+
+    let dialog_clone = dialog.clone();
+    close_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    let (tx, rx) = mpsc::channel::<DeviceInfo>();
+
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok(device_info) => {
+            spinner.set_visible(false);
+            fill_grid(&grid, &device_info);
+            glib::ControlFlow::Break
+        }
+        Err(TryRecvError::Empty) => glib::ControlFlow::Continue,
+        Err(TryRecvError::Disconnected) => {
+            spinner.set_visible(false);
+            fill_grid(&grid, &DeviceInfo::default());
+            glib::ControlFlow::Break
+        }
+    });
+
+    let device_path = ctx.get_selected_device();
+    let username = ctx.username();
+    let lock = ctx.operation_lock.clone();
+    ctx.rt.spawn(async move {
+        // The stage count needs a claim, so wait for any running operation
+        let _guard = lock.lock().await;
+        match fingerprints::read_device_info(device_path, &username).await {
+            Ok(device_info) => {
+                let _ = tx.send(device_info);
+            }
+            Err(e) => warn!("Failed to read device properties: {}", e),
+        }
+    });
+
+    dialog.present();
+}
+
+/// Add one key/value row per property, showing "unknown" for unreadable ones.
+fn fill_grid(grid: &Grid, device_info: &DeviceInfo) {
+    let rows = [
+        ("Name", device_info.name.clone()),
+        (
+            "Scan type",
+            device_info.scan_type.as_deref().map(scan_type_name),
+        ),
+        (
+            "Enroll stages",
+            device_info.enroll_stages.map(|stages| stages.to_string()),
+        ),
+        (
+            "Default reader",
+            device_info
+                .is_default
+                .map(|default| if default { "Yes" } else { "No" }.to_string()),
+        ),
+        ("D-Bus path", device_info.path.clone()),
+    ];
+
+    for (row, (key, value)) in rows.into_iter().enumerate() {
+        let key_label = Label::new(Some(key));
+        key_label.set_halign(Align::End);
+        key_label.add_css_class("dim-label");
+
+        let value_label = Label::new(Some(value.as_deref().unwrap_or("unknown")));
+        value_label.set_halign(Align::Start);
+        value_label.set_selectable(true);
+
+        grid.attach(&key_label, 0, row as i32, 1, 1);
+        grid.attach(&value_label, 1, row as i32, 1, 1);
+    }
+}
+
+/// Describe an fprintd scan type.
+fn scan_type_name(scan_type: &str) -> String {
+    match scan_type {
+        "press" => "Press (touch the sensor)".to_string(),
+        "swipe" => "Swipe (slide across the sensor)".to_string(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,103 @@
+//! Reusable message and confirmation dialogs.
+
+use crate::ui::app::extract_widget;
+use gtk4::prelude::*;
+use gtk4::{Builder, Button, CheckButton, Label, Widget, Window};
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+use tokio::sync::oneshot;
+
+/// Show an informational dialog over the window containing `parent`.
+/// `body` is Pango markup.
+pub fn show_message(parent: &impl IsA<Widget>, title: &str, body: &str) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/message_dialog.ui");
+    let dialog: Window = extract_widget(&builder, "message_window");
+    let title_label: Label = extract_widget(&builder, "message_title_label");
+    let body_label: Label = extract_widget(&builder, "message_body");
+    let close_button: Button = extract_widget(&builder, "message_close_button");
+
+    dialog.set_title(Some(title));
+    title_label.set_label(title);
+    body_label.set_markup(body);
+    set_parent_window(&dialog, parent);
+
+    let dialog_clone = dialog.clone();
+    close_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
+
+/// Ask the user to confirm an action over the window containing `parent`.
+/// `body` is Pango markup and `proceed_label` names the action. With `require_checkbox`
+/// the action stays disabled until that acknowledgement is ticked.
+/// Resolves to whether the user chose to proceed; closing the dialog counts as cancelling.
+pub fn confirm(
+    parent: &impl IsA<Widget>,
+    title: &str,
+    body: &str,
+    proceed_label: &str,
+    require_checkbox: Option<&str>,
+) -> impl Future<Output = bool> {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/confirm_dialog.ui");
+    let dialog: Window = extract_widget(&builder, "confirm_window");
+    let title_label: Label = extract_widget(&builder, "confirm_title_label");
+    let body_label: Label = extract_widget(&builder, "confirm_body");
+    let check: CheckButton = extract_widget(&builder, "confirm_check");
+    let cancel_button: Button = extract_widget(&builder, "confirm_cancel_button");
+    let proceed_button: Button = extract_widget(&builder, "confirm_proceed_button");
+
+    dialog.set_title(Some(title));
+    title_label.set_label(title);
+    body_label.set_markup(body);
+    proceed_button.set_label(proceed_label);
+    set_parent_window(&dialog, parent);
+
+    if let Some(acknowledgement) = require_checkbox {
+        check.set_label(Some(acknowledgement));
+        check.set_visible(true);
+        proceed_button.set_sensitive(false);
+
+        let proceed_button = proceed_button.clone();
+        check.connect_toggled(move |check| {
+            proceed_button.set_sensitive(check.is_active());
+        });
+    }
+
+    // Answered once: by the proceed button, or as a refusal when the dialog closes
+    let (tx, rx) = oneshot::channel::<bool>();
+    let answer = Rc::new(Cell::new(Some(tx)));
+
+    let dialog_clone = dialog.clone();
+    let answer_proceed = answer.clone();
+    proceed_button.connect_clicked(move |_| {
+        if let Some(tx) = answer_proceed.take() {
+            let _ = tx.send(true);
+        }
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    dialog.connect_close_request(move |_| {
+        if let Some(tx) = answer.take() {
+            let _ = tx.send(false);
+        }
+        gtk4::glib::Propagation::Proceed
+    });
+
+    dialog.present();
+    async move { rx.await.unwrap_or(false) }
+}
+
+/// Keep `dialog` above the window containing `parent`.
+fn set_parent_window(dialog: &Window, parent: &impl IsA<Widget>) {
+    if let Some(window) = parent.root().and_downcast::<Window>() {
+        dialog.set_transient_for(Some(&window));
+    }
+}
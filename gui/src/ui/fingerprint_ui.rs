@@ -1,92 +1,317 @@
 //! Fingerprint management UI functionality.
 
-use crate::core::{fprintd, util, FingerprintContext};
-use crate::ui::app::AppContext;
+use crate::core::device_manager::DeviceError;
+use crate::core::util::{self, Finger, Hand, FINGERS};
+use crate::core::FingerprintContext;
+use crate::fingerprints::DeviceEnrollment;
+use crate::ui::app::{extract_widget, AppContext};
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::{
-    pango, Align, Box as GtkBox, Button, Image, Justification, Label, Orientation, Overlay,
+    pango, Align, ApplicationWindow, Box as GtkBox, Builder, Button, Entry, FlowBox, Image,
+    Justification, Label, Orientation, Overlay, SelectionMode, StringList,
 };
-use log::info;
+use log::{info, warn};
 
-use std::collections::HashSet;
 use std::sync::mpsc::{self, TryRecvError};
+use std::time::Duration;
+
+/// How long the initial scan may take before the reader is reported as not found.
+const INITIAL_SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long after a refresh further requests are coalesced into one trailing refresh.
+const REFRESH_DEBOUNCE: Duration = Duration::from_millis(500);
 
 /// Perform initial fingerprint scan and enable switches if fingerprints found.
+/// Until a reader answers, the finger grid is replaced by a detection state that turns
+/// into a retry prompt when no reader is found or the scan times out.
 pub fn perform_initial_fingerprint_scan(ctx: &AppContext) {
     info!("Starting background fingerprint enrollment check");
 
-    let (tx, rx) = mpsc::channel::<bool>();
-    let ctx_clone = ctx.clone();
+    // The error is the message shown next to the retry button
+    let (tx, rx) = mpsc::channel::<Result<Vec<DeviceEnrollment>, String>>();
+    let ctx_clone = ctx.fingerprint_ctx.clone();
+    show_scan_detecting(&ctx.fingerprint_ctx);
+
+    glib::idle_add_local(move || {
+        let scanned = match rx.try_recv() {
+            Ok(scanned) => scanned,
+            Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(TryRecvError::Disconnected) => {
+                Err("The fingerprint scan ended unexpectedly.".to_string())
+            }
+        };
+
+        match scanned {
+            Ok(devices) if !devices.is_empty() => {
+                ctx_clone.ui.scan_status.spinner.stop();
+                ctx_clone.ui.scan_status.container.set_visible(false);
+                ctx_clone.set_device_enrollments(devices);
+                update_device_selector(&ctx_clone);
+                update_fingerprint_ui(&ctx_clone);
 
-    glib::idle_add_local(move || match rx.try_recv() {
-        Ok(has_any) => {
-            if has_any {
-                info!("Enrollment check complete: fingerprints found, enabling switches");
-            } else {
-                info!("Enrollment check complete: no fingerprints found, switches remain disabled");
+                let has_any = ctx_clone.has_any_enrolled();
+                if has_any {
+                    info!("Enrollment check complete: fingerprints found, enabling switches");
+                } else {
+                    info!("Enrollment check complete: no fingerprints found, switches remain disabled");
+                }
+                ctx_clone.finish_initialization(has_any);
+            }
+            Ok(_) => {
+                show_scan_failed(
+                    &ctx_clone,
+                    "No fingerprint reader found. Check that it is connected and that fprintd is running.",
+                );
+                ctx_clone.finish_initialization(false);
+            }
+            Err(message) => {
+                show_scan_failed(&ctx_clone, &message);
+                ctx_clone.finish_initialization(false);
             }
-            ctx_clone
-                .fingerprint_ctx
-                .set_pam_switches_sensitive(has_any);
-            glib::ControlFlow::Break
         }
-        Err(TryRecvError::Empty) => glib::ControlFlow::Continue,
-        Err(TryRecvError::Disconnected) => glib::ControlFlow::Break,
+        glib::ControlFlow::Break
     });
 
     let rt = ctx.fingerprint_ctx.rt.clone();
+    let username = ctx.fingerprint_ctx.username();
+    let lock = ctx.fingerprint_ctx.operation_lock.clone();
     rt.spawn(async move {
+        let _guard = lock.lock().await;
         info!("Starting system fingerprint device detection and enrollment scan");
-        let enrolled = crate::fingerprints::scan_enrolled_fingerprints().await;
-        let has_any = !enrolled.is_empty();
-
-        if has_any {
-            info!(
-                "System ready: {} enrolled fingerprint(s) detected",
-                enrolled.len()
-            );
-            info!("PAM authentication switches will be enabled");
-        } else {
-            info!("No enrolled fingerprints found on initial scan");
-            info!("PAM authentication switches will remain disabled until enrollment");
-            info!("Click 'Enroll' to add your first fingerprint");
+        let scan = crate::fingerprints::scan_enrolled_fingerprints_by_device(&username);
+        let scanned = match tokio::time::timeout(INITIAL_SCAN_TIMEOUT, scan).await {
+            Ok(Ok(devices)) => Ok(devices),
+            Ok(Err(e)) => Err(scan_failed_message(&e)),
+            Err(_) => {
+                warn!(
+                    "Initial fingerprint scan timed out after {}s",
+                    INITIAL_SCAN_TIMEOUT.as_secs()
+                );
+                Err("The fingerprint reader did not respond in time.".to_string())
+            }
+        };
+
+        if let Ok(devices) = &scanned {
+            let enrolled: usize = devices.iter().map(|device| device.enrolled.len()).sum();
+            if enrolled > 0 {
+                info!(
+                    "System ready: {} enrolled fingerprint(s) detected",
+                    enrolled
+                );
+                info!("PAM authentication switches will be enabled");
+            } else if !devices.is_empty() {
+                info!("No enrolled fingerprints found on initial scan");
+                info!("PAM authentication switches will remain disabled until enrollment");
+                info!("Click 'Enroll' to add your first fingerprint");
+            }
         }
 
-        let _ = tx.send(has_any);
+        let _ = tx.send(scanned);
     });
+}
+
+/// Let the retry prompt of a failed initial scan look for the reader again.
+pub fn setup_scan_retry(ctx: &AppContext) {
+    let ctx_clone = ctx.clone();
+    ctx.fingerprint_ctx
+        .ui
+        .scan_status
+        .retry
+        .connect_clicked(move |_| {
+            info!("Retrying fingerprint reader detection");
+            perform_initial_fingerprint_scan(&ctx_clone);
+        });
+}
+
+/// Message shown next to the retry button when the enrolled fingerprints can't be listed.
+fn scan_failed_message(e: &DeviceError) -> String {
+    format!("Could not list the enrolled fingerprints: {}.", e)
+}
+
+/// Replace the finger grid with the spinner while looking for a reader.
+fn show_scan_detecting(ctx: &FingerprintContext) {
+    let status = &ctx.ui.scan_status;
+    clear_finger_grid(ctx);
+    status.label.set_label("Detecting fingerprint reader…");
+    status.retry.set_visible(false);
+    status.spinner.set_visible(true);
+    status.spinner.start();
+    status.container.set_visible(true);
+}
+
+/// Show why no reader was found, with a button to look again.
+fn show_scan_failed(ctx: &FingerprintContext, message: &str) {
+    let status = &ctx.ui.scan_status;
+    warn!("Fingerprint reader detection failed: {}", message);
+    clear_finger_grid(ctx);
+    status.spinner.stop();
+    status.spinner.set_visible(false);
+    status.label.set_label(message);
+    status.retry.set_visible(true);
+    status.container.set_visible(true);
+}
 
-    refresh_fingerprint_display(ctx.fingerprint_ctx.clone());
+/// Remove every hand section from the finger grid.
+fn clear_finger_grid(ctx: &FingerprintContext) {
+    while let Some(child) = ctx.ui.flow.first_child() {
+        ctx.ui.flow.remove(&child);
+    }
+}
+
+/// Set up the device selector shown when more than one reader is present.
+pub fn setup_device_selector(ctx: &AppContext) {
+    let ctx_clone = ctx.fingerprint_ctx.clone();
+    ctx.fingerprint_ctx
+        .ui
+        .device_dropdown
+        .connect_selected_notify(move |dropdown| {
+            let devices = ctx_clone.get_devices();
+            let Some((path, name)) = devices.get(dropdown.selected() as usize) else {
+                return;
+            };
+            if ctx_clone.get_selected_device().as_ref() == Some(path) {
+                return;
+            }
+
+            info!("User selected fingerprint device: '{}'", name);
+            ctx_clone.set_selected_device(Some(path.clone()));
+            update_fingerprint_ui(&ctx_clone);
+        });
+}
+
+/// Set up the account selector, shown when running as root or when started with `--user`.
+/// Switching accounts updates the window title and rescans the new account's fingerprints.
+pub fn setup_user_selector(ctx: &AppContext, builder: &Builder, window: &ApplicationWindow) {
+    let user_box: GtkBox = extract_widget(builder, "user_box");
+    let entry: Entry = extract_widget(builder, "user_entry");
+    let base_title = window.title().unwrap_or_default().to_string();
+
+    let username = ctx.fingerprint_ctx.username();
+    if !username.is_empty() {
+        info!("Managing fingerprints of user '{}'", username);
+        window.set_title(Some(&user_title(&base_title, &username)));
+    }
+    if !util::is_root() && username.is_empty() {
+        return;
+    }
+    user_box.set_visible(true);
+    entry.set_text(&username);
+    let default_tooltip = entry.tooltip_text();
+
+    let ctx_clone = ctx.fingerprint_ctx.clone();
+    let window = window.clone();
+    entry.connect_activate(move |entry| {
+        let requested = entry.text().trim().to_string();
+        if requested == ctx_clone.username() {
+            return;
+        }
+        if !requested.is_empty() && !util::user_exists(&requested) {
+            warn!("Refusing to manage unknown user '{}'", requested);
+            entry.add_css_class("error");
+            entry.set_tooltip_text(Some(&format!("No user named '{}'", requested)));
+            return;
+        }
+        // Switching while an operation runs would mix two accounts' fingerprints
+        if ctx_clone.try_begin_operation().is_none() {
+            entry.set_text(&ctx_clone.username());
+            return;
+        }
+        entry.remove_css_class("error");
+        entry.set_tooltip_text(default_tooltip.as_deref());
+
+        if requested.is_empty() {
+            info!("Managing fingerprints of the current user");
+        } else {
+            info!("Managing fingerprints of user '{}'", requested);
+        }
+        window.set_title(Some(&user_title(&base_title, &requested)));
+        ctx_clone.set_username(&requested);
+        ctx_clone.set_selected_finger(None);
+        refresh_fingerprint_display(ctx_clone.clone());
+    });
+}
+
+/// Window title naming the managed account; the plain title for the current user.
+fn user_title(base: &str, username: &str) -> String {
+    if username.is_empty() {
+        base.to_string()
+    } else {
+        format!("{} — {}", base, username)
+    }
 }
 
 /// Refresh fingerprint display with current enrollment status.
+/// Requests made while a refresh runs, or shortly after, coalesce into a single trailing
+/// refresh so back-to-back completions don't stack up scans of the device.
 pub fn refresh_fingerprint_display(ctx: FingerprintContext) {
-    let (tx, rx) = mpsc::channel::<HashSet<String>>();
+    if ctx.refresh_busy.replace(true) {
+        ctx.refresh_pending.set(true);
+        return;
+    }
+
+    let (tx, rx) = mpsc::channel::<Result<Vec<DeviceEnrollment>, DeviceError>>();
 
     {
         let ctx_clone = ctx.clone();
 
-        glib::idle_add_local(move || match rx.try_recv() {
-            Ok(enrolled) => {
-                ctx_clone.set_enrolled(enrolled);
-                update_fingerprint_ui(&ctx_clone);
-                glib::ControlFlow::Break
+        glib::idle_add_local(move || {
+            match rx.try_recv() {
+                Ok(Ok(devices)) => {
+                    ctx_clone.set_device_enrollments(devices);
+                    update_device_selector(&ctx_clone);
+                    update_fingerprint_ui(&ctx_clone);
+                }
+                // Leave the stored enrollments alone and offer to look again
+                Ok(Err(e)) => show_scan_failed(&ctx_clone, &scan_failed_message(&e)),
+                Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(TryRecvError::Disconnected) => {}
             }
-            Err(TryRecvError::Empty) => glib::ControlFlow::Continue,
-            Err(TryRecvError::Disconnected) => glib::ControlFlow::Break,
+
+            let ctx_clone = ctx_clone.clone();
+            glib::timeout_add_local_once(REFRESH_DEBOUNCE, move || {
+                ctx_clone.refresh_busy.set(false);
+                if ctx_clone.refresh_pending.replace(false) {
+                    refresh_fingerprint_display(ctx_clone);
+                }
+            });
+            glib::ControlFlow::Break
         });
     }
 
+    let username = ctx.username();
+    let lock = ctx.operation_lock.clone();
     ctx.rt.spawn(async move {
-        let enrolled = crate::fingerprints::scan_enrolled_fingerprints().await;
-        let _ = tx.send(enrolled);
+        // Wait for a running enroll or delete instead of competing for the claim
+        let _guard = lock.lock().await;
+        let devices = crate::fingerprints::scan_enrolled_fingerprints_by_device(&username).await;
+        let _ = tx.send(devices);
     });
 }
 
+/// Populate the device selector and only show it when there is a choice.
+fn update_device_selector(ctx: &FingerprintContext) {
+    let devices = ctx.get_devices();
+    let selected = ctx.get_selected_device();
+    let dropdown = &ctx.ui.device_dropdown;
+
+    let names: Vec<&str> = devices.iter().map(|(_, name)| name.as_str()).collect();
+    dropdown.set_model(Some(&StringList::new(&names)));
+
+    if let Some(index) = devices
+        .iter()
+        .position(|(path, _)| Some(path) == selected.as_ref())
+    {
+        dropdown.set_selected(index as u32);
+    }
+
+    dropdown.set_visible(devices.len() > 1);
+}
+
 /// Update fingerprint UI elements with enrollment data.
-fn update_fingerprint_ui(ctx: &FingerprintContext) {
+pub fn update_fingerprint_ui(ctx: &FingerprintContext) {
     let enrolled = ctx.get_enrolled();
-    let has_any = !enrolled.is_empty();
+    let has_any = ctx.has_any_enrolled();
     info!(
         "Updating finger selection UI with {} enrolled fingerprints",
         enrolled.len()
@@ -107,51 +332,100 @@ fn update_fingerprint_ui(ctx: &FingerprintContext) {
     // Update button states based on selected finger and enrollment status
     update_button_states(ctx);
 
-    while let Some(child) = ctx.ui.flow.first_child() {
-        ctx.ui.flow.remove(&child);
-    }
-
+    clear_finger_grid(ctx);
     create_finger_sections(ctx);
 
     info!("Finger selection UI updated successfully with hand separation");
 }
 
 /// Create finger button sections for left and right hands.
+/// The left-handed layout mirrors the grid: left hand first, little finger first.
 fn create_finger_sections(ctx: &FingerprintContext) {
-    let left_fingers = &fprintd::FINGERS[0..5];
-    let right_fingers = &fprintd::FINGERS[5..10];
-
-    let right_hand_container = create_hand_section("Right Hand", right_fingers, ctx);
-    ctx.ui.flow.append(&right_hand_container);
+    let hands = if ctx.settings().left_handed {
+        [Hand::Left, Hand::Right]
+    } else {
+        [Hand::Right, Hand::Left]
+    };
 
-    let left_hand_container = create_hand_section("Left Hand", left_fingers, ctx);
-    ctx.ui.flow.append(&left_hand_container);
+    for hand in hands {
+        let hand_container = create_hand_section(hand, ctx);
+        ctx.ui.flow.append(&hand_container);
+    }
 }
 
 /// Create hand section (left or right) with finger buttons.
-fn create_hand_section(title: &str, fingers: &[&str], ctx: &FingerprintContext) -> GtkBox {
+fn create_hand_section(hand: Hand, ctx: &FingerprintContext) -> GtkBox {
     let hand_container = GtkBox::new(Orientation::Vertical, 10);
     hand_container.set_halign(Align::Center);
 
-    let title_label = Label::new(Some(title));
+    let title_label = Label::new(Some(hand.display_name()));
     title_label.set_css_classes(&["hand-title"]);
     hand_container.append(&title_label);
 
-    let finger_grid = GtkBox::new(Orientation::Horizontal, 8);
+    // Wraps onto more rows when the window is narrow; not homogeneous, so the thumb gap
+    // and finger heights below keep their own sizes
+    let finger_grid = FlowBox::new();
     finger_grid.set_halign(Align::Center);
-    finger_grid.set_homogeneous(true);
+    finger_grid.set_selection_mode(SelectionMode::None);
+    finger_grid.set_homogeneous(false);
+    finger_grid.set_min_children_per_line(1);
+    finger_grid.set_max_children_per_line(FINGERS_PER_HAND);
+    finger_grid.set_column_spacing(8);
+    finger_grid.set_row_spacing(8);
+
+    let mut fingers: Vec<Finger> = FINGERS
+        .iter()
+        .copied()
+        .filter(|finger| finger.hand() == hand)
+        .collect();
+    if ctx.settings().left_handed {
+        fingers.reverse();
+    }
 
-    for finger in fingers {
+    let last = fingers.len().saturating_sub(1);
+    for (position, finger) in fingers.into_iter().enumerate() {
         let finger_box = create_finger_button(finger, ctx);
+
+        // Stagger the buttons like fingertips and set the thumb apart from the fingers
+        finger_box.set_margin_top(finger_drop(finger));
+        if finger.is_thumb() {
+            if position == 0 {
+                finger_box.set_margin_end(THUMB_GAP);
+            } else if position == last {
+                finger_box.set_margin_start(THUMB_GAP);
+            }
+        }
+        // Stagger from the top of the row rather than stretching to its height
+        finger_box.set_valign(Align::Start);
         finger_grid.append(&finger_box);
+        // The finger button takes keyboard focus, not the flow box cell around it
+        if let Some(cell) = finger_box.parent() {
+            cell.set_focusable(false);
+        }
     }
 
     hand_container.append(&finger_grid);
     hand_container
 }
 
+/// Fingers shown on one row of a hand when there is room for all of them.
+const FINGERS_PER_HAND: u32 = 5;
+
+/// Extra space between the thumb and the other fingers of a hand.
+const THUMB_GAP: i32 = 16;
+
+/// How far a finger's button sits below the middle finger's, following fingertip height.
+fn finger_drop(finger: Finger) -> i32 {
+    match finger {
+        Finger::LeftMiddle | Finger::RightMiddle => 0,
+        Finger::LeftIndex | Finger::RightIndex | Finger::LeftRing | Finger::RightRing => 12,
+        Finger::LeftLittle | Finger::RightLittle => 28,
+        Finger::LeftThumb | Finger::RightThumb => 48,
+    }
+}
+
 /// Create finger button widget.
-fn create_finger_button(finger: &str, ctx: &FingerprintContext) -> GtkBox {
+fn create_finger_button(finger: Finger, ctx: &FingerprintContext) -> GtkBox {
     let container = GtkBox::new(Orientation::Vertical, 5);
     container.set_halign(Align::Center);
     container.set_size_request(120, 120);
@@ -165,6 +439,13 @@ fn create_finger_button(finger: &str, ctx: &FingerprintContext) -> GtkBox {
     let overlay = Overlay::new();
     let base_image = Image::from_icon_name("fingerprint-symbolic");
     base_image.set_pixel_size(64);
+    if finger.is_thumb() {
+        // Tilt the print outwards like a thumb resting beside the hand
+        base_image.add_css_class(match finger.hand() {
+            Hand::Left => "thumb-left",
+            Hand::Right => "thumb-right",
+        });
+    }
     overlay.set_child(Some(&base_image));
 
     if is_enrolled {
@@ -182,36 +463,37 @@ fn create_finger_button(finger: &str, ctx: &FingerprintContext) -> GtkBox {
     } else {
         button.add_css_class("finger-unenrolled");
     }
+    if finger.is_thumb() {
+        button.add_css_class("finger-thumb");
+    }
+    button.set_tooltip_text(Some(finger.display_name()));
 
-    let finger_key = finger.to_string();
     let ctx_clone = ctx.clone();
 
     button.connect_clicked(move |_| {
-        ctx_clone.set_selected_finger(Some(finger_key.clone()));
-        ctx_clone
-            .ui
-            .labels
-            .finger
-            .set_label(&util::display_finger_name(&finger_key));
+        ctx_clone.set_selected_finger(Some(finger));
+        ctx_clone.ui.labels.finger.set_label(finger.display_name());
         ctx_clone.ui.labels.action.set_use_markup(false);
         ctx_clone
             .ui
             .labels
             .action
             .set_label("Select an action below.");
+        ctx_clone.ui.labels.stage_dots.set_visible(false);
         ctx_clone.ui.stack.set_visible_child_name("finger");
-        info!("User selected finger: '{}'", finger_key);
+        info!("User selected finger: '{}'", finger);
 
         // Update button states when finger is selected
-        let is_enrolled = ctx_clone.is_finger_enrolled(&finger_key);
+        let is_enrolled = ctx_clone.is_finger_enrolled(finger);
         ctx_clone.update_button_states(is_enrolled);
+        update_enrolled_label(&ctx_clone);
     });
 
-    let display_name = util::display_finger_name(finger);
-    let short_name = util::create_short_finger_name(&display_name);
-
-    let label = Label::new(Some(&short_name));
+    let label = Label::new(Some(finger.short_name()));
     label.set_css_classes(&["finger-label"]);
+    if finger.is_thumb() {
+        label.add_css_class("thumb-label");
+    }
     label.set_wrap(true);
     label.set_wrap_mode(pango::WrapMode::Word);
     label.set_justify(Justification::Center);
@@ -224,19 +506,37 @@ fn create_finger_button(finger: &str, ctx: &FingerprintContext) -> GtkBox {
 
 /// Update button states based on selected finger and enrollment status
 fn update_button_states(ctx: &FingerprintContext) {
-    if let Some(ref finger_key) = ctx.get_selected_finger() {
-        let is_enrolled = ctx.is_finger_enrolled(finger_key);
+    if let Some(finger) = ctx.get_selected_finger() {
+        let is_enrolled = ctx.is_finger_enrolled(finger);
 
         ctx.update_button_states(is_enrolled);
 
         info!(
             "Updated button states for finger '{}': Add={}, Remove={}",
-            finger_key, !is_enrolled, is_enrolled
+            finger, !is_enrolled, is_enrolled
         );
     } else {
         // No finger selected, disable both buttons
         ctx.ui.buttons.add.set_sensitive(false);
+        ctx.ui.buttons.reenroll.set_sensitive(false);
         ctx.ui.buttons.delete.set_sensitive(false);
         info!("No finger selected, both buttons disabled");
     }
+
+    update_enrolled_label(ctx);
+}
+
+/// Show when the selected finger was enrolled, or hide the label if it is not enrolled.
+fn update_enrolled_label(ctx: &FingerprintContext) {
+    let label = &ctx.ui.labels.enrolled;
+    match ctx.get_selected_finger() {
+        Some(finger) if ctx.is_finger_enrolled(finger) => {
+            label.set_label(&crate::fingerprints::metadata::describe_enrolled(
+                &ctx.username(),
+                finger,
+            ));
+            label.set_visible(true);
+        }
+        _ => label.set_visible(false),
+    }
 }
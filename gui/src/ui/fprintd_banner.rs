@@ -0,0 +1,100 @@
+//! Banner offering to start the fprintd service when it is not running.
+
+use crate::core::system;
+use crate::pam::helper::PkexecOutcome;
+use crate::ui::app::{extract_widget, AppContext};
+use crate::ui::fingerprint_ui;
+use gtk4::prelude::*;
+use gtk4::{glib, Box as GtkBox, Builder, Button, Label};
+use log::{info, warn};
+use std::sync::mpsc::{self, TryRecvError};
+
+/// Widgets making up the fprintd service banner.
+#[derive(Clone)]
+struct FprintdBanner {
+    container: GtkBox,
+    label: Label,
+    start_btn: Button,
+    enable_btn: Button,
+}
+
+/// Set up the fprintd banner, showing it when the service is not running.
+pub fn setup_fprintd_banner(ctx: &AppContext, builder: &Builder, fprintd_active: bool) {
+    let banner = FprintdBanner {
+        container: extract_widget(builder, "fprintd_banner"),
+        label: extract_widget(builder, "fprintd_banner_label"),
+        start_btn: extract_widget(builder, "fprintd_start_btn"),
+        enable_btn: extract_widget(builder, "fprintd_enable_btn"),
+    };
+
+    banner.container.set_visible(!fprintd_active);
+
+    {
+        let ctx = ctx.clone();
+        let banner_clone = banner.clone();
+        banner.start_btn.connect_clicked(move |_| {
+            info!("User clicked 'Start fprintd' in service banner");
+            start_service(&ctx, &banner_clone, false);
+        });
+    }
+
+    {
+        let ctx = ctx.clone();
+        let banner_clone = banner.clone();
+        banner.enable_btn.connect_clicked(move |_| {
+            info!("User clicked 'Enable on Boot' in service banner");
+            start_service(&ctx, &banner_clone, true);
+        });
+    }
+}
+
+/// Start fprintd in the background and rescan once it is running.
+fn start_service(ctx: &AppContext, banner: &FprintdBanner, enable_on_boot: bool) {
+    banner.start_btn.set_sensitive(false);
+    banner.enable_btn.set_sensitive(false);
+
+    let (tx, rx) = mpsc::channel::<PkexecOutcome>();
+    ctx.fingerprint_ctx.rt.spawn_blocking(move || {
+        let _ = tx.send(system::start_fprintd_service(enable_on_boot));
+    });
+
+    let ctx = ctx.clone();
+    let banner = banner.clone();
+    glib::idle_add_local(move || {
+        let outcome = match rx.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(TryRecvError::Disconnected) => {
+                PkexecOutcome::Failed("Service task ended unexpectedly".to_string())
+            }
+        };
+
+        banner.start_btn.set_sensitive(true);
+        banner.enable_btn.set_sensitive(true);
+
+        match outcome {
+            PkexecOutcome::Success if system::check_fprintd_service() => {
+                info!("fprintd service started, rescanning fingerprints");
+                banner.container.set_visible(false);
+                fingerprint_ui::perform_initial_fingerprint_scan(&ctx);
+            }
+            PkexecOutcome::Success => {
+                warn!("fprintd start reported success but service is not active");
+                banner
+                    .label
+                    .set_label("fprintd was started but is not reporting as active. Check 'systemctl status fprintd'.");
+            }
+            PkexecOutcome::Cancelled => {
+                info!("User cancelled starting fprintd");
+            }
+            PkexecOutcome::Failed(msg) => {
+                warn!("Failed to start fprintd: {}", msg);
+                banner
+                    .label
+                    .set_label(&format!("Could not start fprintd: {}", msg));
+            }
+        }
+
+        glib::ControlFlow::Break
+    });
+}
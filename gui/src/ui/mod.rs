@@ -3,15 +3,29 @@
 //! This module contains all UI-related components organized by functionality:
 //! - `app`: Application setup and initialization
 //! - `pam_ui`: PAM authentication switches UI
+//! - `preferences`: Preferences window for enrollment limits and the finger layout
 //! - `navigation`: Navigation buttons and dialogs
 //! - `button_handlers`: Button click handlers
+//! - `device_info`: Reader details dialog
+//! - `dialogs`: Reusable message and confirmation dialogs
 //! - `fingerprint_ui`: Fingerprint management UI
+//! - `fprintd_banner`: Banner for starting a stopped fprintd service
+//! - `shortcuts`: Keyboard shortcuts for the common actions
+//! - `theme`: Light and dark stylesheets following the desktop
+//! - `toast`: Transient notifications with an undo action
 
 pub mod app;
 pub mod button_handlers;
+pub mod device_info;
+pub mod dialogs;
 pub mod fingerprint_ui;
+pub mod fprintd_banner;
 pub mod navigation;
 pub mod pam_ui;
+pub mod preferences;
+pub mod shortcuts;
+pub mod theme;
+pub mod toast;
 
 // Re-export commonly used items
 pub use app::setup_application_ui;
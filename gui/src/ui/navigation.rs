@@ -1,11 +1,32 @@
 //! Navigation buttons and dialogs functionality.
 
+use crate::config;
+use crate::core::{logging, util, FingerprintContext};
+use crate::fingerprints::batch;
 use crate::pam::helper::is_sddm_enabled;
 use crate::ui::app::{extract_widget, AppContext};
+use crate::ui::dialogs;
 use gtk4::prelude::*;
-use gtk4::{ApplicationWindow, Builder, Button, Window};
+use gtk4::{ApplicationWindow, Builder, Button};
 use log::info;
 
+/// How to log in with a fingerprint through the SDDM greeter.
+const SDDM_HINT_MARKUP: &str = "<b>SDDM</b> greeter currently doesn’t <i>natively</i> support fingerprint login, so it will not display a “scan your finger” prompt.
+
+To unlock using your fingerprint:
+1. Select your user (if needed)
+2. Leave the password field blank
+3. Press <b>Enter</b> once
+4. Scan your registered finger
+
+This is a temporary hack until native support is added (if ever, lol).";
+
+/// Screen locking in a Wayland session, which SDDM plays no part in.
+const WAYLAND_LOCK_HINT_MARKUP: &str = "You are in a <b>Wayland</b> session, where the lock screen is drawn by your compositor rather than SDDM. If it shows no fingerprint prompt either, unlock it the same way: leave the password blank, press <b>Enter</b> and scan.";
+
+/// The same for an X11 session.
+const X11_LOCK_HINT_MARKUP: &str = "You are in an <b>X11</b> session, where the lock screen comes from a separate screen locker rather than SDDM. If it shows no fingerprint prompt either, unlock it the same way: leave the password blank, press <b>Enter</b> and scan.";
+
 /// Set up navigation buttons and dialogs.
 pub fn setup_navigation_and_dialogs(
     ctx: &AppContext,
@@ -13,13 +34,14 @@ pub fn setup_navigation_and_dialogs(
     window: &ApplicationWindow,
 ) {
     setup_navigation_buttons(ctx, builder);
-    setup_info_button(window, builder);
+    setup_info_button(ctx, window, builder);
     setup_sddm_login_hint(window, builder);
 }
 
 /// Set up navigation buttons.
 fn setup_navigation_buttons(ctx: &AppContext, builder: &Builder) {
     let manage_btn: Button = extract_widget(builder, "manage_btn");
+    let guided_setup_btn: Button = extract_widget(builder, "guided_setup_btn");
     let back_btn: Button = extract_widget(builder, "back_btn");
     let button_back: Button = extract_widget(builder, "button_back");
 
@@ -31,6 +53,14 @@ fn setup_navigation_buttons(ctx: &AppContext, builder: &Builder) {
         });
     }
 
+    {
+        let ctx = ctx.fingerprint_ctx.clone();
+        guided_setup_btn.connect_clicked(move |_| {
+            info!("User clicked 'Guided Setup' button - showing finger picker");
+            batch::show_guided_setup(ctx.clone());
+        });
+    }
+
     {
         let stack = ctx.fingerprint_ctx.ui.stack.clone();
         back_btn.connect_clicked(move |_| {
@@ -49,13 +79,14 @@ fn setup_navigation_buttons(ctx: &AppContext, builder: &Builder) {
 }
 
 /// Set up info button to show about dialog.
-fn setup_info_button(window: &ApplicationWindow, builder: &Builder) {
+fn setup_info_button(ctx: &AppContext, window: &ApplicationWindow, builder: &Builder) {
     let info_btn: Button = extract_widget(builder, "info_btn");
 
     let window_clone = window.clone();
+    let ctx = ctx.fingerprint_ctx.clone();
     info_btn.connect_clicked(move |_| {
         info!("User clicked 'About' button - showing info dialog");
-        show_info_dialog(&window_clone);
+        show_info_dialog(&window_clone, &ctx);
     });
 }
 
@@ -77,8 +108,8 @@ fn setup_sddm_login_hint(window: &ApplicationWindow, builder: &Builder) {
     }
 }
 
-/// Show the info dialog with credits and donation links.
-fn show_info_dialog(main_window: &ApplicationWindow) {
+/// Show the info dialog with credits, donation links and the diagnostics button.
+fn show_info_dialog(main_window: &ApplicationWindow, ctx: &FingerprintContext) {
     let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/info_dialog.ui");
 
     let info_window: gtk4::Window = builder
@@ -89,8 +120,19 @@ fn show_info_dialog(main_window: &ApplicationWindow) {
         .object("close_button")
         .expect("Failed to get close_button");
 
+    let copy_button: Button = builder
+        .object("copy_diagnostics_button")
+        .expect("Failed to get copy_diagnostics_button");
+
     info_window.set_transient_for(Some(main_window));
 
+    let ctx = ctx.clone();
+    copy_button.connect_clicked(move |button| {
+        info!("User clicked 'Copy diagnostics' - copying report to clipboard");
+        button.clipboard().set_text(&build_diagnostics(&ctx));
+        button.set_label("Copied");
+    });
+
     let info_window_clone = info_window.clone();
     close_button.connect_clicked(move |_| {
         info_window_clone.close();
@@ -99,20 +141,75 @@ fn show_info_dialog(main_window: &ApplicationWindow) {
     info_window.show();
 }
 
-/// Show SDDM-specific fingerprint hint dialog.
-fn show_sddm_hint(parent: &ApplicationWindow) {
-    info!("Displaying SDDM fingerprint hint dialog");
-    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/sddm_hint_dialog.ui");
+/// Build a plain text report with version, system, device and recent log information.
+fn build_diagnostics(ctx: &FingerprintContext) -> String {
+    let mut report = format!(
+        "{} {} ({})\nGTK: {}.{}.{}\nDistribution: {}\nfprintd: {}\n",
+        config::app_info::NAME,
+        config::app_info::VERSION,
+        config::app_info::ID,
+        gtk4::major_version(),
+        gtk4::minor_version(),
+        gtk4::micro_version(),
+        util::get_distribution_name().unwrap_or_else(|| "Unknown".to_string()),
+        util::get_fprintd_version().unwrap_or_else(|| "Unknown".to_string())
+    );
+
+    let username = ctx.username();
+    if !username.is_empty() {
+        report.push_str(&format!("Managed user: {}\n", username));
+    }
 
-    let window: Window = extract_widget(&builder, "sddm_hint_window");
-    let close_button: Button = extract_widget(&builder, "sddm_hint_close_button");
+    let devices = ctx.get_devices();
+    if devices.is_empty() {
+        report.push_str("Devices: none detected\n");
+    }
+    for (path, name) in &devices {
+        let enrolled = ctx
+            .enrolled
+            .borrow()
+            .get(path)
+            .map(|fingers| fingers.len())
+            .unwrap_or(0);
+        report.push_str(&format!(
+            "Device: {} ({}), {} enrolled\n",
+            name,
+            path.as_str(),
+            enrolled
+        ));
+    }
 
-    window.set_transient_for(Some(parent));
+    let lines = logging::recent_lines();
+    report.push_str(&format!("\nRecent log ({} lines):\n", lines.len()));
+    for line in lines {
+        report.push_str(&line);
+        report.push('\n');
+    }
 
-    let window_clone = window.clone();
-    close_button.connect_clicked(move |_| {
-        window_clone.close();
-    });
+    report
+}
+
+/// Show SDDM-specific fingerprint hint dialog.
+fn show_sddm_hint(parent: &ApplicationWindow) {
+    let session_type = std::env::var("XDG_SESSION_TYPE").ok();
+    info!(
+        "Displaying SDDM fingerprint hint dialog (session type: {})",
+        session_type.as_deref().unwrap_or("unknown")
+    );
+    dialogs::show_message(
+        parent,
+        "Fingerprint Login with SDDM",
+        &sddm_hint_markup(session_type.as_deref()),
+    );
+}
 
-    window.show();
+/// SDDM hint followed by how the lock screen of the current `session_type` behaves;
+/// other session types, such as a TTY, only get the SDDM steps.
+fn sddm_hint_markup(session_type: Option<&str>) -> String {
+    let lock_hint = match session_type {
+        Some("wayland") => WAYLAND_LOCK_HINT_MARKUP,
+        Some("x11") => X11_LOCK_HINT_MARKUP,
+        _ => return SDDM_HINT_MARKUP.to_string(),
+    };
+    format!("{}\n\n{}", SDDM_HINT_MARKUP, lock_hint)
 }
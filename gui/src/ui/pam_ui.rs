@@ -1,77 +1,346 @@
 //! PAM authentication switches UI functionality.
 
-use crate::pam::{helper::PamHelper, switch as pam_switch};
-use crate::ui::app::AppContext;
-use log::info;
+use crate::core::FingerprintContext;
+use crate::pam::{
+    helper::{self, PamHelper, PamOpError},
+    switch::{self as pam_switch, services, PamService},
+};
+use crate::ui::app::{extract_widget, AppContext};
+use crate::ui::dialogs;
+use gtk4::prelude::*;
+use gtk4::{
+    glib, Align, Box as GtkBox, Builder, Button, Label, ListBox, ListBoxRow, Orientation,
+    Separator, Spinner, Switch,
+};
+use log::{info, warn};
+use std::io;
+use std::sync::mpsc::{self, TryRecvError};
+
+/// Title and hint of the switch rows of well-known services; other services get generic ones.
+const SERVICE_LABELS: &[(&str, &str, &str)] = &[
+    (
+        "sudo",
+        "Enable Authentication in Terminal",
+        "Use fingerprint for sudo and terminal prompts.",
+    ),
+    (
+        "polkit-1",
+        "Enable Authentication in System Prompt",
+        "Use fingerprint for polkit and graphical prompts.",
+    ),
+];
 
 /// Set up PAM authentication switches.
-pub fn setup_pam_switches(ctx: &AppContext) {
+/// The services the helper has patches for and their configuration are read on a
+/// background thread; a switch is shown for each, login using its row in main.ui.
+/// Toggles are ignored until this and the initial enrollment scan have both finished.
+/// Malformed blocks are offered for repair before the configuration is read.
+pub fn setup_pam_switches(ctx: &AppContext, builder: &Builder) {
     info!("Checking current PAM configurations for switches initialization");
 
-    let (login_configured, sudo_configured, polkit_configured) =
-        PamHelper::check_all_configurations();
+    info!("Temporarily disabling PAM switches until fingerprint enrollment check");
+    ctx.fingerprint_ctx.set_pam_switches_sensitive(false);
+
+    let fingerprint_ctx = ctx.fingerprint_ctx.clone();
+    let builder = builder.clone();
+    glib::spawn_future_local(async move {
+        // Repair first so the switches show the state the files end up in
+        offer_block_repair(&fingerprint_ctx).await;
+
+        let check = fingerprint_ctx.rt.spawn_blocking(check_services);
+        let checked = match check.await {
+            Ok(Ok(checked)) => checked,
+            Ok(Err(e)) => {
+                warn!(
+                    "Could not list the services the helper manages ({}), showing no switches",
+                    e
+                );
+                Vec::new()
+            }
+            Err(e) => {
+                warn!("PAM check task ended without a result: {}", e);
+                Vec::new()
+            }
+        };
+
+        show_service_switches(&fingerprint_ctx, &builder, checked);
+        fingerprint_ctx.finish_pam_check();
+    });
+}
 
-    info!(
-        "PAM Login Authentication: {}",
-        if login_configured {
-            "ENABLED"
+/// Services the helper has patches for, each with whether it is configured.
+fn check_services() -> io::Result<Vec<(PamService, bool)>> {
+    let listed = services::list()?;
+    let paths: Vec<&str> = listed.iter().map(|service| service.path.as_str()).collect();
+    let configured = PamHelper::check_configurations(&paths);
+    Ok(listed.into_iter().zip(configured).collect())
+}
+
+/// Show a switch for every service: login in its row in main.ui, which stays hidden
+/// without a patch for the display manager, the others in rows added below it.
+fn show_service_switches(
+    ctx: &FingerprintContext,
+    builder: &Builder,
+    checked: Vec<(PamService, bool)>,
+) {
+    let login_row: ListBoxRow = extract_widget(builder, "row_login");
+    let has_login = checked
+        .iter()
+        .any(|(service, _)| service.name == services::LOGIN_NAME);
+    if !has_login {
+        info!("No patch for the login service, hiding its switch");
+        login_row.set_visible(false);
+        extract_widget::<ListBoxRow>(builder, "row_login_separator").set_visible(false);
+    }
+
+    let list: ListBox = extract_widget(builder, "switches_list");
+    let mut position = login_row.index() + 1;
+    for (service, configured) in checked {
+        info!(
+            "PAM {} authentication ({}): {}",
+            service.name,
+            service.path,
+            status_name(configured)
+        );
+
+        let (switch, spinner) = if service.name == services::LOGIN_NAME {
+            (
+                ctx.ui.switches.login.clone(),
+                ctx.ui.switches.login_spinner.clone(),
+            )
         } else {
-            "DISABLED"
-        }
-    );
-    info!(
-        "PAM Sudo Authentication: {}",
-        if sudo_configured {
-            "ENABLED"
+            let (row, switch, spinner) = build_service_row(&service);
+            list.insert(&separator_row(), position);
+            list.insert(&row, position + 1);
+            position += 2;
+            ctx.ui.switches.services.borrow_mut().push(switch.clone());
+            (switch, spinner)
+        };
+
+        // Set before the handler exists and kept insensitive until startup finishes
+        switch.set_active(configured);
+        switch.set_sensitive(false);
+        if ctx.pam_module_installed.get() {
+            set_pam_switch_tooltip(&switch, &service);
         } else {
-            "DISABLED"
+            switch.set_tooltip_text(Some(&module_missing_tooltip()));
         }
-    );
-    info!(
-        "PAM Polkit Authentication: {}",
-        if polkit_configured {
-            "ENABLED"
-        } else {
-            "DISABLED"
+        pam_switch::setup_pam_switch(
+            &switch,
+            &spinner,
+            service,
+            ctx.rt.clone(),
+            ctx.initializing.clone(),
+        );
+    }
+}
+
+/// Build a switch row laid out like the login row in main.ui.
+fn build_service_row(service: &PamService) -> (ListBoxRow, Switch, Spinner) {
+    let (title, hint) = match SERVICE_LABELS
+        .iter()
+        .find(|(name, ..)| *name == service.name)
+    {
+        Some((_, title, hint)) => (title.to_string(), hint.to_string()),
+        None => (
+            format!("Enable Authentication for {}", service.name),
+            format!("Use fingerprint for the {} PAM service.", service.name),
+        ),
+    };
+
+    let title = Label::new(Some(&title));
+    title.set_halign(Align::Start);
+    title.set_margin_top(6);
+    title.add_css_class("toggle-title");
+
+    let hint = Label::new(Some(&hint));
+    hint.set_wrap(true);
+    hint.set_halign(Align::Start);
+    hint.set_margin_bottom(6);
+    hint.add_css_class("toggle-hint");
+
+    let text_box = GtkBox::new(Orientation::Vertical, 0);
+    text_box.set_hexpand(true);
+    text_box.append(&title);
+    text_box.append(&hint);
+
+    let spinner = Spinner::new();
+    spinner.set_valign(Align::Center);
+    spinner.set_visible(false);
+
+    let switch = Switch::new();
+    switch.set_halign(Align::End);
+    switch.set_valign(Align::Center);
+
+    let content = GtkBox::new(Orientation::Horizontal, 12);
+    content.set_hexpand(true);
+    content.append(&text_box);
+    content.append(&spinner);
+    content.append(&switch);
+
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.set_child(Some(&content));
+
+    (row, switch, spinner)
+}
+
+/// Build a separator row like the ones between the rows in main.ui.
+fn separator_row() -> ListBoxRow {
+    let separator = Separator::new(Orientation::Horizontal);
+    separator.set_margin_top(6);
+    separator.set_margin_bottom(6);
+
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.set_child(Some(&separator));
+    row
+}
+
+/// Offer to repair PAM files left with duplicated or unbalanced xfprintd-gui blocks.
+async fn offer_block_repair(ctx: &FingerprintContext) {
+    let audit = ctx.rt.spawn_blocking(PamHelper::find_malformed_blocks);
+    let paths = match audit.await {
+        Ok(Ok(paths)) => paths,
+        Ok(Err(e)) => {
+            warn!("Could not audit PAM files for malformed blocks: {}", e);
+            return;
+        }
+        Err(e) => {
+            warn!("PAM audit task ended without a result: {}", e);
+            return;
         }
+    };
+    if paths.is_empty() {
+        return;
+    }
+
+    warn!("Malformed xfprintd-gui blocks in: {}", paths.join(", "));
+    let listed: Vec<String> = paths
+        .iter()
+        .map(|path| format!("<tt>{}</tt>", glib::markup_escape_text(path)))
+        .collect();
+    let body = format!(
+        "These PAM files have duplicated or unfinished xfprintd-gui blocks, probably left by an interrupted change:\n\n{}\n\nRepair them so each keeps at most one block?",
+        listed.join("\n")
     );
+    let confirmed = dialogs::confirm(
+        &ctx.ui.stack,
+        "Repair PAM Configuration",
+        &body,
+        "Repair",
+        None,
+    );
+    if !confirmed.await {
+        info!("User declined repairing malformed PAM blocks");
+        return;
+    }
 
-    ctx.fingerprint_ctx
-        .ui
-        .switches
-        .login
-        .set_active(login_configured);
-    ctx.fingerprint_ctx
-        .ui
-        .switches
-        .term
-        .set_active(sudo_configured);
-    ctx.fingerprint_ctx
-        .ui
-        .switches
-        .prompt
-        .set_active(polkit_configured);
+    match ctx.rt.spawn_blocking(PamHelper::repair_blocks).await {
+        Ok(Ok(())) => info!("Repaired malformed PAM blocks"),
+        Ok(Err(PamOpError::UserCancelled)) => info!("User cancelled repairing PAM blocks"),
+        Ok(Err(e)) => {
+            warn!("Failed to repair PAM blocks: {}", e);
+            dialogs::show_message(
+                &ctx.ui.stack,
+                "Repair Failed",
+                &glib::markup_escape_text(&e.to_string()),
+            );
+        }
+        Err(e) => warn!("PAM repair task ended without a result: {}", e),
+    }
+}
 
-    info!("Temporarily disabling PAM switches until fingerprint enrollment check");
-    ctx.fingerprint_ctx.set_pam_switches_sensitive(false);
+/// Log label for a PAM configuration state.
+fn status_name(configured: bool) -> &'static str {
+    if configured {
+        "ENABLED"
+    } else {
+        "DISABLED"
+    }
+}
 
-    setup_pam_switch_handlers(ctx);
+/// Tooltip of the switches while pam_fprintd.so is missing.
+fn module_missing_tooltip() -> String {
+    format!(
+        "{}.\nEnabling fingerprint authentication without it would break logins; configurations already enabled can still be turned off.",
+        helper::MODULE_MISSING_MESSAGE
+    )
 }
 
-/// Set up PAM switch event handlers using generic implementation.
-fn setup_pam_switch_handlers(ctx: &AppContext) {
-    pam_switch::setup_pam_switch(
-        &ctx.fingerprint_ctx.ui.switches.login,
-        pam_switch::services::login(),
+/// Show which file the switch modifies and the block that will be inserted.
+fn set_pam_switch_tooltip(switch: &Switch, service: &PamService) {
+    let mut markup = format!(
+        "Modifies <b>{}</b>",
+        glib::markup_escape_text(&service.path)
     );
 
-    pam_switch::setup_pam_switch(
-        &ctx.fingerprint_ctx.ui.switches.term,
-        pam_switch::services::SUDO,
-    );
+    match PamHelper::show_configuration(&service.path) {
+        Ok(block) => {
+            markup.push_str("\n\nInserts:\n<tt>");
+            markup.push_str(&glib::markup_escape_text(block.trim_end()));
+            markup.push_str("</tt>");
+        }
+        Err(e) => {
+            info!(
+                "Configuration preview unavailable for {}: {}",
+                service.name, e
+            );
+        }
+    }
 
-    pam_switch::setup_pam_switch(
-        &ctx.fingerprint_ctx.ui.switches.prompt,
-        pam_switch::services::POLKIT,
-    );
+    switch.set_tooltip_markup(Some(&markup));
+}
+
+/// Set up the button that checks whether sudo actually reaches pam_fprintd.so.
+pub fn setup_selftest_button(ctx: &AppContext, builder: &Builder) {
+    let button: Button = extract_widget(builder, "selftest_btn");
+    let spinner: Spinner = extract_widget(builder, "sp_selftest");
+    let hint: Label = extract_widget(builder, "lbl_selftest_hint");
+    let rt = ctx.fingerprint_ctx.rt.clone();
+
+    button.connect_clicked(move |button| {
+        info!("User clicked 'Test Configuration'");
+        button.set_sensitive(false);
+        spinner.set_visible(true);
+        spinner.start();
+        hint.set_label("Testing sudo configuration…");
+
+        let (tx, rx) = mpsc::channel::<io::Result<bool>>();
+        rt.spawn_blocking(move || {
+            let _ = tx.send(PamHelper::selftest(helper::SUDO_PATH));
+        });
+
+        let button = button.clone();
+        let spinner = spinner.clone();
+        let hint = hint.clone();
+        glib::idle_add_local(move || {
+            let result = match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(TryRecvError::Disconnected) => {
+                    Err(io::Error::other("Self-test task ended unexpectedly"))
+                }
+            };
+
+            spinner.stop();
+            spinner.set_visible(false);
+            button.set_sensitive(true);
+
+            match result {
+                Ok(true) => hint.set_label("sudo reaches the fingerprint module."),
+                Ok(false) => hint.set_label(
+                    "sudo does not reach the fingerprint module. Check that terminal authentication is enabled and fprintd is running.",
+                ),
+                Err(e) => {
+                    warn!("PAM self-test failed: {}", e);
+                    hint.set_label(&format!("Could not run the test: {}", e));
+                }
+            }
+
+            glib::ControlFlow::Break
+        });
+    });
 }
@@ -0,0 +1,92 @@
+//! Preferences window for enrollment limits and the finger layout.
+
+use crate::config::{self, Settings};
+use crate::core::FingerprintContext;
+use crate::ui::app::{extract_widget, AppContext};
+use crate::ui::fingerprint_ui;
+use gtk4::prelude::*;
+use gtk4::{ApplicationWindow, Builder, Button, CheckButton, SpinButton, Window};
+use log::{info, warn};
+
+/// Set up the preferences button on the main page.
+pub fn setup_preferences_button(ctx: &AppContext, builder: &Builder, window: &ApplicationWindow) {
+    let preferences_btn: Button = extract_widget(builder, "preferences_btn");
+
+    let window = window.clone();
+    let ctx = ctx.fingerprint_ctx.clone();
+    preferences_btn.connect_clicked(move |_| {
+        info!("User clicked 'Preferences' button - showing preferences window");
+        show_preferences_dialog(&window, &ctx);
+    });
+}
+
+/// Show the preferences window; every change applies immediately and is saved.
+fn show_preferences_dialog(parent: &ApplicationWindow, ctx: &FingerprintContext) {
+    let builder = Builder::from_resource("/xyz/xerolinux/xfprintd_gui/ui/preferences_dialog.ui");
+    let dialog: Window = extract_widget(&builder, "preferences_window");
+    let timeout_spin: SpinButton = extract_widget(&builder, "enroll_timeout_spin");
+    let advice_spin: SpinButton = extract_widget(&builder, "retry_advice_spin");
+    let left_handed_check: CheckButton = extract_widget(&builder, "left_handed_check");
+    let close_button: Button = extract_widget(&builder, "preferences_close_button");
+
+    dialog.set_transient_for(Some(parent));
+
+    let settings = ctx.settings();
+    timeout_spin.set_range(0.0, config::enrollment::MAX_TIMEOUT_SECS as f64);
+    timeout_spin.set_increments(5.0, 30.0);
+    timeout_spin.set_value(settings.enroll_timeout_secs as f64);
+    // Advice at or past the retry limit would never be seen
+    advice_spin.set_range(
+        1.0,
+        (config::enrollment::MAX_CONSECUTIVE_RETRIES - 1) as f64,
+    );
+    advice_spin.set_increments(1.0, 1.0);
+    advice_spin.set_value(settings.retry_advice_after as f64);
+    left_handed_check.set_active(settings.left_handed);
+
+    let ctx_timeout = ctx.clone();
+    timeout_spin.connect_value_changed(move |spin| {
+        update_settings(&ctx_timeout, |settings| {
+            settings.enroll_timeout_secs = spin.value_as_int().max(0) as u64;
+        });
+    });
+
+    let ctx_advice = ctx.clone();
+    advice_spin.connect_value_changed(move |spin| {
+        update_settings(&ctx_advice, |settings| {
+            settings.retry_advice_after = spin.value_as_int().max(1) as usize;
+        });
+    });
+
+    let ctx_layout = ctx.clone();
+    left_handed_check.connect_toggled(move |check| {
+        let left_handed = check.is_active();
+        info!(
+            "User switched to the {}-handed finger layout",
+            if left_handed { "left" } else { "right" }
+        );
+        update_settings(&ctx_layout, |settings| settings.left_handed = left_handed);
+        fingerprint_ui::update_fingerprint_ui(&ctx_layout);
+    });
+
+    let dialog_clone = dialog.clone();
+    close_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
+
+/// Apply `change` to the current settings and persist the result.
+fn update_settings(ctx: &FingerprintContext, change: impl FnOnce(&mut Settings)) {
+    let mut settings = ctx.settings();
+    change(&mut settings);
+    if settings == ctx.settings() {
+        return;
+    }
+
+    ctx.set_settings(settings);
+    if let Err(e) = settings.save() {
+        warn!("Failed to save preferences: {}", e);
+    }
+}
@@ -0,0 +1,96 @@
+//! Keyboard shortcuts for the common actions.
+
+use crate::core::FingerprintContext;
+use crate::ui::app::{extract_widget, AppContext};
+use gtk4::prelude::*;
+use gtk4::{gio, Application, ApplicationWindow, Builder, Button, Stack};
+use log::info;
+
+/// Window actions with their accelerators and the button each one clicks.
+const SHORTCUTS: &[(&str, &str, &str)] = &[
+    ("enroll", "<Control>e", "button_add"),
+    ("delete", "Delete", "button_delete"),
+    ("about", "F1", "info_btn"),
+];
+
+/// Accelerator of the back action, which depends on the page.
+const BACK_ACCEL: &str = "Escape";
+
+/// Register the window actions and their accelerators.
+/// Each action clicks the matching button, so it runs the same handler. Actions are
+/// ignored while an enrollment or deletion runs and while the button is insensitive.
+pub fn setup_shortcuts(
+    ctx: &AppContext,
+    builder: &Builder,
+    app: &Application,
+    window: &ApplicationWindow,
+) {
+    let stack = ctx.fingerprint_ctx.ui.stack.clone();
+
+    for &(name, accel, button_id) in SHORTCUTS {
+        let button: Button = extract_widget(builder, button_id);
+        let action = gio::SimpleAction::new(name, None);
+        let ctx = ctx.fingerprint_ctx.clone();
+        action.connect_activate(move |_, _| {
+            info!("Keyboard shortcut '{}' pressed", name);
+            click_if_idle(&ctx, &button);
+        });
+        window.add_action(&action);
+        app.set_accels_for_action(&format!("win.{}", name), &[accel]);
+    }
+
+    let back_to_main: Button = extract_widget(builder, "back_btn");
+    let back_to_manage: Button = extract_widget(builder, "button_back");
+    let back = gio::SimpleAction::new("back", None);
+    {
+        let stack = stack.clone();
+        let ctx = ctx.fingerprint_ctx.clone();
+        back.connect_activate(move |_, _| match stack.visible_child_name().as_deref() {
+            Some("finger") => click_if_idle(&ctx, &back_to_manage),
+            Some("manage") => click_if_idle(&ctx, &back_to_main),
+            _ => {}
+        });
+    }
+    window.add_action(&back);
+    app.set_accels_for_action("win.back", &[BACK_ACCEL]);
+
+    // Page specific actions stay disabled elsewhere so their keys, Delete in particular,
+    // reach the focused widget instead
+    let window_clone = window.clone();
+    update_action_states(&window_clone, &stack);
+    stack.connect_visible_child_name_notify(move |stack| {
+        update_action_states(&window_clone, stack);
+    });
+}
+
+/// Enable the actions that apply to the visible page.
+fn update_action_states(window: &ApplicationWindow, stack: &Stack) {
+    let page = stack.visible_child_name();
+    let on_finger_page = page.as_deref() == Some("finger");
+    let on_main_page = page.as_deref() == Some("main");
+
+    for (name, enabled) in [
+        ("enroll", on_finger_page),
+        ("delete", on_finger_page),
+        ("back", !on_main_page),
+    ] {
+        if let Some(action) = window
+            .lookup_action(name)
+            .and_downcast::<gio::SimpleAction>()
+        {
+            action.set_enabled(enabled);
+        }
+    }
+}
+
+/// Click `button` like the user would; nothing happens while an operation runs or the
+/// button is insensitive.
+fn click_if_idle(ctx: &FingerprintContext, button: &Button) {
+    if ctx.is_busy() {
+        info!("Ignoring keyboard shortcut while an operation is running");
+        return;
+    }
+    if button.is_sensitive() {
+        button.emit_clicked();
+    }
+}
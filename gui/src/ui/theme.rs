@@ -0,0 +1,155 @@
+//! Light and dark stylesheet variants following the desktop color scheme.
+//!
+//! The scheme comes from the `org.freedesktop.appearance` portal when it is available,
+//! otherwise from the GTK settings. Both are watched so switching the desktop theme
+//! updates the app live.
+
+use crate::config;
+use futures_util::StreamExt;
+use gtk4::{gdk, glib, CssProvider, Settings};
+use log::{debug, info, warn};
+use std::cell::Cell;
+use std::rc::Rc;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{Connection, Proxy};
+
+/// Resource path of the rules shared by both variants.
+const BASE_STYLE_RESOURCE: &str = "/xyz/xerolinux/xfprintd_gui/css/style.css";
+
+const PORTAL_SERVICE: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_SETTINGS_IFACE: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// Load the stylesheets for `display` and keep the variant in sync with the desktop.
+pub fn setup_theme(display: &gdk::Display, rt: &Runtime) {
+    let base = CssProvider::new();
+    base.load_from_resource(BASE_STYLE_RESOURCE);
+    gtk4::style_context_add_provider_for_display(
+        display,
+        &base,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+
+    // Added after the base so its palette wins at the same priority
+    let variant = CssProvider::new();
+    gtk4::style_context_add_provider_for_display(
+        display,
+        &variant,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+
+    let settings = Settings::for_display(display);
+    // Portal preference once known: `Some(true)` dark, `Some(false)` light
+    let portal_dark = Rc::new(Cell::new(None::<bool>));
+    let applied = Cell::new(None::<bool>);
+
+    let apply = {
+        let settings = settings.clone();
+        let portal_dark = portal_dark.clone();
+        Rc::new(move || {
+            let dark = portal_dark
+                .get()
+                .unwrap_or_else(|| settings_prefer_dark(&settings));
+            if applied.replace(Some(dark)) == Some(dark) {
+                return;
+            }
+
+            info!(
+                "Using the {} stylesheet",
+                if dark { "dark" } else { "light" }
+            );
+            variant.load_from_resource(if dark {
+                config::DARK_STYLE_RESOURCE
+            } else {
+                config::LIGHT_STYLE_RESOURCE
+            });
+            config::set_dark_theme(dark);
+        })
+    };
+    apply();
+
+    let apply_prefer_dark = apply.clone();
+    settings.connect_gtk_application_prefer_dark_theme_notify(move |_| apply_prefer_dark());
+    let apply_theme_name = apply.clone();
+    settings.connect_gtk_theme_name_notify(move |_| apply_theme_name());
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Option<bool>>();
+    rt.spawn(async move {
+        if let Err(e) = watch_portal_color_scheme(tx).await {
+            debug!(
+                "Appearance portal unavailable, following GTK settings: {}",
+                e
+            );
+        }
+    });
+    glib::spawn_future_local(async move {
+        while let Some(dark) = rx.recv().await {
+            portal_dark.set(dark);
+            apply();
+        }
+    });
+}
+
+/// Whether the GTK settings ask for a dark theme, explicitly or through a "-dark" theme name.
+fn settings_prefer_dark(settings: &Settings) -> bool {
+    settings.is_gtk_application_prefer_dark_theme()
+        || settings
+            .gtk_theme_name()
+            .is_some_and(|name| name.to_lowercase().ends_with("-dark"))
+}
+
+/// Send the portal color scheme now and on every change: `Some(true)` for dark,
+/// `Some(false)` for light and `None` when the user has no preference.
+async fn watch_portal_color_scheme(tx: mpsc::UnboundedSender<Option<bool>>) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let proxy = Proxy::new(&conn, PORTAL_SERVICE, PORTAL_PATH, PORTAL_SETTINGS_IFACE).await?;
+    // Subscribe first so a change right after the initial read is not missed
+    let mut changes = proxy.receive_signal("SettingChanged").await?;
+
+    let initial: OwnedValue = match proxy
+        .call("ReadOne", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+        .await
+    {
+        Ok(value) => value,
+        // Portals older than version 2 only have the deprecated Read
+        Err(_) => {
+            proxy
+                .call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+                .await?
+        }
+    };
+    if tx.send(color_scheme_preference(&initial)).is_err() {
+        return Ok(());
+    }
+
+    while let Some(msg) = changes.next().await {
+        let (namespace, key, value): (String, String, OwnedValue) = msg.body().deserialize()?;
+        if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+            continue;
+        }
+        if tx.send(color_scheme_preference(&value)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Map the portal `color-scheme` value (0 no preference, 1 dark, 2 light) to a preference.
+fn color_scheme_preference(value: &Value<'_>) -> Option<bool> {
+    match value {
+        // Read wraps the value in another variant
+        Value::Value(inner) => color_scheme_preference(inner),
+        Value::U32(1) => Some(true),
+        Value::U32(2) => Some(false),
+        Value::U32(_) => None,
+        other => {
+            warn!("Unexpected color-scheme value from the portal: {:?}", other);
+            None
+        }
+    }
+}
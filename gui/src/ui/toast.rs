@@ -0,0 +1,77 @@
+//! Transient notification with an optional action, shown at the bottom of the window.
+
+use crate::ui::app::extract_widget;
+use gtk4::prelude::*;
+use gtk4::{glib, Builder, Button, Label, Revealer};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Action run when the toast button is clicked.
+type ToastAction = Box<dyn FnOnce()>;
+
+/// Toast widgets and the pending action of the toast currently shown.
+#[derive(Clone)]
+pub struct Toast {
+    revealer: Revealer,
+    label: Label,
+    button: Button,
+    action: Rc<RefCell<Option<ToastAction>>>,
+    // Incremented per toast so an older timeout doesn't hide a newer toast
+    generation: Rc<Cell<u64>>,
+}
+
+impl Toast {
+    /// Create toast from the `toast_*` widgets of the main builder.
+    pub fn from_builder(builder: &Builder) -> Self {
+        let toast = Self {
+            revealer: extract_widget(builder, "toast_revealer"),
+            label: extract_widget(builder, "toast_label"),
+            button: extract_widget(builder, "toast_button"),
+            action: Rc::new(RefCell::new(None)),
+            generation: Rc::new(Cell::new(0)),
+        };
+
+        let toast_clone = toast.clone();
+        toast.button.connect_clicked(move |_| {
+            let action = toast_clone.action.borrow_mut().take();
+            toast_clone.hide();
+            if let Some(action) = action {
+                action();
+            }
+        });
+
+        toast
+    }
+
+    /// Show `text` with a button labelled `button_label` for `timeout`.
+    /// `on_click` runs if the button is clicked before the toast disappears.
+    pub fn show(
+        &self,
+        text: &str,
+        button_label: &str,
+        timeout: Duration,
+        on_click: impl FnOnce() + 'static,
+    ) {
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+
+        self.label.set_label(text);
+        self.button.set_label(button_label);
+        *self.action.borrow_mut() = Some(Box::new(on_click));
+        self.revealer.set_reveal_child(true);
+
+        let toast = self.clone();
+        glib::timeout_add_local_once(timeout, move || {
+            if toast.generation.get() == generation {
+                toast.hide();
+            }
+        });
+    }
+
+    /// Hide the toast and drop its pending action.
+    pub fn hide(&self) {
+        self.action.borrow_mut().take();
+        self.revealer.set_reveal_child(false);
+    }
+}
@@ -1,19 +1,54 @@
 //! Privileged helper tool for managing PAM configurations using patch files.
 //!
-//! This tool safely applies, removes, or checks configuration blocks
+//! This tool safely applies, removes, checks, or shows configuration blocks
 //! in PAM configuration files using patch files stored alongside the binary.
+//! It can also probe a PAM service with pamtester to confirm that
+//! pam_fprintd.so is actually reached, and list the services it has patches
+//! for so the GUI does not need its own copy of that list.
 //!
 //! Patch files are stored in: /opt/xfprintd-gui/patches/<encoded-path>.patch
 //! For example: /opt/xfprintd-gui/patches/etc/pam.d/sudo.patch
+//! `--patches-dir` or `XFPRINTD_PATCHES_DIR` points at another directory, for
+//! packaging under a different prefix or testing against a temporary one.
+//!
+//! A patch in the invoking user's `$XDG_CONFIG_HOME/xfprintd-gui/patches/`
+//! (`~/.config/xfprintd-gui/patches/` when run through pkexec) takes
+//! precedence over the system patch of the same name.
+//!
+//! `apply` exit codes:
+//!
+//! | Code | Meaning                                                     |
+//! |------|-------------------------------------------------------------|
+//! | 0    | Every target configured (or already configured)             |
+//! | 3    | Target path is not allowlisted                              |
+//! | 4    | No patch file for the target                                |
+//! | 5    | Patch file rejected: invalid content or untrusted override  |
+//! | 6    | A PAM module referenced by the patch is not installed       |
+//! | 7    | Reading or writing the PAM file failed                      |
+//! | 8    | The written file had PAM syntax errors and was rolled back  |
+//! | 9    | The PAM file already loads pam_fprintd.so outside the block |
+//! | 10   | The PAM file is on a read-only filesystem                   |
+//! | 126  | Not running as root                                         |
+//!
+//! With several targets the code of the first failure is used. Code 2 is left to
+//! usage errors reported by the argument parser. `apply --atomic` changes either
+//! every target or none; on failure it exits with the same codes without having
+//! written anything.
 
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use std::{
+    env,
+    ffi::{CStr, OsStr},
     fs,
-    io::{self, Write},
-    os::unix::fs::PermissionsExt,
+    io::{self, Read, Write},
+    os::unix::ffi::OsStrExt,
+    os::unix::fs::{self as unix_fs, MetadataExt, PermissionsExt},
     path::{Path, PathBuf},
-    time::{SystemTime, UNIX_EPOCH},
+    process::{Child, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 /// Markers used to fence the configuration blocks
@@ -23,12 +58,45 @@ const END_MARK: &str = "# END xfprintd-gui";
 /// Standard PAM header
 const PAM_HEADER: &str = "#%PAM-1.0";
 
+/// Valid PAM management groups (the first field of a rule)
+const PAM_TYPES: &[&str] = &["auth", "account", "password", "session"];
+
+/// Valid simple PAM control values
+const PAM_CONTROLS: &[&str] = &[
+    "required",
+    "requisite",
+    "sufficient",
+    "optional",
+    "include",
+    "substack",
+];
+
 /// Base directory for patches (relative to binary location)
 const PATCHES_BASE_DIR: &str = "/opt/xfprintd-gui/patches";
 
+/// Per-user patch overrides, relative to the user's configuration directory
+const USER_PATCHES_SUBDIR: &str = "xfprintd-gui/patches";
+
+/// Controls a user override may give its pam_fprintd.so rules
+const OVERRIDE_CONTROLS: &[&str] = &["sufficient", "required", "requisite", "optional"];
+
 /// Allowlisted PAM configuration directories
 const ALLOWED_DIRS: &[&str] = &["/etc/pam.d"];
 
+/// PAM module the fingerprint blocks reference
+const FPRINTD_MODULE: &str = "pam_fprintd.so";
+
+/// Directories probed for a distribution default when the given default file is missing
+const VENDOR_PAM_DIRS: &[&str] = &[
+    "/usr/lib/pam.d",
+    "/usr/share/pam.d",
+    "/lib/pam.d",
+    "/etc/pam.d",
+];
+
+/// How long `selftest` waits for pam_fprintd.so to prompt before giving up
+const SELFTEST_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Target configuration with optional default file fallback
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct TargetConfig {
@@ -52,23 +120,167 @@ impl TargetConfig {
     }
 }
 
+/// Reason `apply` failed for a target, each with its own exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ApplyFailure {
+    NotAllowlisted,
+    PatchMissing,
+    PatchRejected,
+    ModuleMissing,
+    WriteFailed,
+    RolledBack,
+    Conflict,
+    ReadOnly,
+}
+
+impl ApplyFailure {
+    /// Process exit code reporting this failure, see the table in the module docs
+    fn exit_code(self) -> i32 {
+        match self {
+            Self::NotAllowlisted => 3,
+            Self::PatchMissing => 4,
+            Self::PatchRejected => 5,
+            Self::ModuleMissing => 6,
+            Self::WriteFailed => 7,
+            Self::RolledBack => 8,
+            Self::Conflict => 9,
+            Self::ReadOnly => 10,
+        }
+    }
+}
+
+/// Error returned by `apply_config`, classified for the exit code
+#[derive(Debug)]
+struct ApplyError {
+    failure: ApplyFailure,
+    source: io::Error,
+}
+
+impl ApplyError {
+    fn new(failure: ApplyFailure, source: io::Error) -> Self {
+        Self { failure, source }
+    }
+}
+
+impl std::fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+/// Error `apply_patch` returns after undoing a write that left the file with invalid
+/// rules, so a rollback can be told apart from other invalid data such as a non UTF-8 file
+#[derive(Debug)]
+struct RolledBack(String);
+
+impl std::fmt::Display for RolledBack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RolledBack {}
+
+/// Why `apply --atomic` left the targets alone
+#[derive(Debug)]
+struct AtomicAbort {
+    /// Each failed target with its error
+    failures: Vec<(String, ApplyError)>,
+    /// Targets already replaced when a later rename failed and that could not be restored
+    not_restored: Vec<String>,
+}
+
+/// New content of a target computed by `apply`, before anything is written
+#[derive(Debug)]
+struct PlannedWrite {
+    path: PathBuf,
+    /// File content before the change; None when the target did not exist
+    backup: Option<Vec<u8>>,
+    /// Content the block was inserted into, to tell new syntax errors from existing ones
+    base_content: String,
+    content: String,
+}
+
+impl PlannedWrite {
+    /// Puts the target back the way it was before the change
+    fn restore(&self) -> io::Result<()> {
+        match &self.backup {
+            Some(original) => atomic_write(&self.path, original),
+            None => fs::remove_file(&self.path),
+        }
+    }
+}
+
+/// Configuration status of one path reported by `check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CheckStatus {
+    path: String,
+    applied: bool,
+}
+
+/// Fence markers found in one PAM file by `audit`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BlockAudit {
+    path: String,
+    /// Complete BEGIN..END blocks
+    blocks: usize,
+    /// BEGIN markers never closed before the next BEGIN or the end of the file
+    unterminated: usize,
+    /// END markers without an open block
+    stray_ends: usize,
+    /// More than one block or any unbalanced marker
+    malformed: bool,
+    /// Whether `audit --fix` rewrote the file
+    fixed: bool,
+}
+
+/// PAM service with a patch file, reported by `services`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ManagedService {
+    /// Service name, the file name of the target (e.g., "sudo")
+    service: String,
+    /// Target PAM file (e.g., "/etc/pam.d/sudo")
+    path: String,
+}
+
 /// Command line interface definition
 #[derive(Debug, Parser)]
 #[command(
     name = "xfprintd-gui-helper",
-    version,
-    about = "Apply/remove/check PAM config blocks using patch files"
+    disable_version_flag = true,
+    arg_required_else_help = true,
+    about = "Apply/remove/check/show/test PAM config blocks using patch files and list the patched services"
 )]
 struct Cli {
+    /// Print version information (add --verbose for install paths)
+    #[arg(short = 'V', long)]
+    version: bool,
+
+    /// Include patch and allowlist locations in --version output
+    #[arg(short, long, requires = "version")]
+    verbose: bool,
+
+    /// Read patch files from this directory instead of /opt/xfprintd-gui/patches (refused under pkexec)
+    #[arg(long, global = true, value_name = "DIR", env = "XFPRINTD_PATCHES_DIR")]
+    patches_dir: Option<PathBuf>,
+
     #[command(subcommand)]
-    cmd: Command,
+    cmd: Option<Command>,
 }
 
 /// Available subcommands
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Insert fenced configuration block into specified PAM files
+    ///
+    /// Exits with 3 (not allowlisted), 4 (patch missing), 5 (patch rejected),
+    /// 6 (PAM module missing), 7 (write failed), 8 (rolled back), 9 (pam_fprintd.so
+    /// already configured outside the block) or 10 (read-only filesystem) on failure.
     Apply {
+        /// Change every target or none: all new contents are staged first and only
+        /// renamed into place once each target is ready
+        #[arg(long)]
+        atomic: bool,
         /// JSON objects with 'file' and optional 'default' fields
         /// Example: '{"file":"/etc/pam.d/sudo"}' or '{"file":"/etc/pam.d/polkit-1","default":"/usr/lib/pam.d/polkit-1"}'
         #[arg(required = true)]
@@ -85,34 +297,235 @@ enum Command {
         /// PAM configuration file paths (e.g., /etc/pam.d/sudo)
         #[arg(required = true)]
         paths: Vec<String>,
+        /// Print a JSON array of {"path": ..., "applied": ...} objects instead of text lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// List services pulled into the auth stack of PAM files via include/substack
+    Includes {
+        /// PAM configuration file path (e.g., /etc/pam.d/sudo)
+        path: String,
+    },
+    /// Report PAM files in the allowlisted directories that contain fence markers
+    ///
+    /// Duplicated, unterminated or stray markers are flagged as malformed; exits with 1
+    /// when a malformed file remains.
+    Audit {
+        /// Rewrite malformed files: a file with a complete block gets a single block from
+        /// its current patch, any other file only loses its markers
+        #[arg(long)]
+        fix: bool,
+        /// Print a JSON array of audit results instead of text lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the PAM services that have a patch file, one target path per line
+    ///
+    /// Covers the patches directory only, user overrides can't add services; patches
+    /// for targets outside the allowlisted directories are left out.
+    Services {
+        /// Print a JSON array of {"service": ..., "path": ...} objects instead of paths
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the fenced configuration block that would be inserted into a PAM file
+    Show {
+        /// PAM configuration file path (e.g., /etc/pam.d/sudo)
+        path: String,
+    },
+    /// Run pamtester against a PAM service and report whether pam_fprintd.so is reached
+    ///
+    /// The authentication attempt is aborted as soon as fprintd prompts for a
+    /// finger, so no credentials are ever checked.
+    #[command(alias = "verify")]
+    Selftest {
+        /// PAM service name (e.g., sudo)
+        service: String,
+        /// User to authenticate as (defaults to the invoking user, the pkexec caller if any)
+        #[arg(long)]
+        user: Option<String>,
     },
 }
 
-/// Converts a file path to its corresponding patch file path
+/// Converts a file path to its patch file path below `base_dir`
 /// Example: /etc/pam.d/sudo -> /opt/xfprintd-gui/patches/etc/pam.d/sudo.patch
-fn get_patch_path(target_path: &str) -> PathBuf {
+fn patch_path_in(base_dir: &Path, target_path: &str) -> PathBuf {
     let normalized = target_path.strip_prefix('/').unwrap_or(target_path);
-    PathBuf::from(PATCHES_BASE_DIR)
-        .join(normalized)
-        .with_extension("patch")
+    base_dir.join(normalized).with_extension("patch")
+}
+
+/// User who asked for the change: the pkexec caller, or the current user otherwise
+fn invoking_uid() -> libc::uid_t {
+    env::var("PKEXEC_UID")
+        .ok()
+        .and_then(|uid| uid.parse().ok())
+        .unwrap_or_else(|| unsafe { libc::getuid() })
+}
+
+/// Home directory of `uid` from the password database
+fn home_dir_of(uid: libc::uid_t) -> Option<PathBuf> {
+    // getpwuid returns static storage that is only read before the next call
+    unsafe {
+        let passwd = libc::getpwuid(uid);
+        if passwd.is_null() || (*passwd).pw_dir.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr((*passwd).pw_dir);
+        Some(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+    }
+}
+
+/// Account name of `uid` from the password database
+fn user_name_of(uid: libc::uid_t) -> Option<String> {
+    // getpwuid returns static storage that is only read before the next call
+    unsafe {
+        let passwd = libc::getpwuid(uid);
+        if passwd.is_null() || (*passwd).pw_name.is_null() {
+            return None;
+        }
+        CStr::from_ptr((*passwd).pw_name)
+            .to_str()
+            .ok()
+            .map(str::to_string)
+    }
+}
+
+/// Directory holding the invoking user's patch overrides.
+/// pkexec clears the user's environment, so `~/.config` of the pkexec caller is used there
+fn user_patches_dir() -> Option<PathBuf> {
+    if env::var_os("PKEXEC_UID").is_some() {
+        let home = home_dir_of(invoking_uid())?;
+        return Some(home.join(".config").join(USER_PATCHES_SUBDIR));
+    }
+
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join(USER_PATCHES_SUBDIR))
+}
+
+/// An override is only used when it is a regular file owned by root or `uid`
+/// that nobody else can write
+fn is_trusted_override(path: &Path, uid: libc::uid_t) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| {
+        metadata.file_type().is_file()
+            && (metadata.uid() == 0 || metadata.uid() == uid)
+            && metadata.mode() & 0o022 == 0
+    })
 }
 
 /// Checks if a path is in the allowlist of supported PAM configuration directories
 fn is_allowlisted_path(path: &Path) -> bool {
+    is_allowlisted_in(path, ALLOWED_DIRS)
+}
+
+/// Checks that a path names a regular file (or a file to be created) directly inside
+/// one of `allowed_dirs`, rejecting traversal, trailing slashes and symlinks
+fn is_allowlisted_in(path: &Path, allowed_dirs: &[&str]) -> bool {
     let path_str = match path.to_str() {
         Some(s) => s,
         None => return false,
     };
 
-    ALLOWED_DIRS
+    // Reject relative paths, "..", "." and empty segments (including a trailing slash);
+    // checked on the raw string because `Path::components` silently drops some of them
+    if !path_str.starts_with('/')
+        || path_str[1..]
+            .split('/')
+            .any(|segment| matches!(segment, "" | "." | ".."))
+    {
+        return false;
+    }
+
+    // Never follow a symlink out of the PAM directory
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return false;
+    }
+
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    let Ok(parent) = fs::canonicalize(parent) else {
+        return false;
+    };
+
+    allowed_dirs
         .iter()
-        .any(|allowed| path_str.starts_with(allowed))
+        .any(|allowed| fs::canonicalize(allowed).is_ok_and(|allowed| allowed == parent))
+}
+
+/// Reads patch file content for the given target path from `patches_dir`, preferring
+/// the invoking user's override over it
+fn read_patch_content(target_path: &str, patches_dir: &Path) -> io::Result<String> {
+    // Only the packaged directory is trusted as is; another one gets the override checks
+    if patches_dir != Path::new(PATCHES_BASE_DIR) {
+        let patch_path = patch_path_in(patches_dir, target_path);
+        if fs::symlink_metadata(&patch_path).is_ok()
+            && !is_trusted_override(&patch_path, invoking_uid())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "Refusing patch {}: it must be a regular file owned by you or root and not writable by others",
+                    patch_path.display()
+                ),
+            ));
+        }
+    }
+
+    read_patch_with_override(
+        user_patches_dir().as_deref(),
+        patches_dir,
+        target_path,
+        invoking_uid(),
+    )
 }
 
-/// Reads patch file content for the given target path
-fn read_patch_content(target_path: &str) -> io::Result<String> {
-    let patch_path = get_patch_path(target_path);
+/// Reads the patch for the target path from `override_dir` if present there,
+/// otherwise from `base_dir`.
+/// An override only replaces a patch `base_dir` already has, and may only hold
+/// pam_fprintd.so rules, since the user writing it is not trusted with root's PAM stack
+fn read_patch_with_override(
+    override_dir: Option<&Path>,
+    base_dir: &Path,
+    target_path: &str,
+    uid: libc::uid_t,
+) -> io::Result<String> {
+    let base_path = patch_path_in(base_dir, target_path);
+    if let Some(override_dir) = override_dir {
+        let override_path = patch_path_in(override_dir, target_path);
+        if fs::symlink_metadata(&override_path).is_ok() && base_path.exists() {
+            if !is_trusted_override(&override_path, uid) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "Refusing patch override {}: it must be a regular file owned by you or root and not writable by others",
+                        override_path.display()
+                    ),
+                ));
+            }
+            let content = read_patch_file(&override_path)?;
+            if let Err(reason) = validate_override_content(&content) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "Refusing patch override {}: {}",
+                        override_path.display(),
+                        reason
+                    ),
+                ));
+            }
+            eprintln!("Using patch override {}", override_path.display());
+            return Ok(content);
+        }
+    }
+
+    read_patch_file(&base_path)
+}
 
+/// Reads a patch file, trimming trailing whitespace
+fn read_patch_file(patch_path: &Path) -> io::Result<String> {
     if !patch_path.exists() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
@@ -120,12 +533,104 @@ fn read_patch_content(target_path: &str) -> io::Result<String> {
         ));
     }
 
-    let content = fs::read_to_string(&patch_path)?;
+    let content = fs::read_to_string(patch_path)?;
+
+    // A corrupted or tampered patch must never reach /etc/pam.d
+    if let Err(reason) = validate_patch_content(&content) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid patch {}: {}", patch_path.display(), reason),
+        ));
+    }
 
     // Remove trailing newlines/whitespace for consistent formatting
     Ok(content.trim_end().to_string())
 }
 
+/// Checks that patch content is a non-empty list of PAM rules without fence markers
+fn validate_patch_content(content: &str) -> Result<(), String> {
+    if let Some(marker) = content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with(BEGIN_MARK) || line.starts_with(END_MARK))
+    {
+        return Err(format!("it contains the fence marker '{}'", marker));
+    }
+
+    let rules = pam_rules(content);
+    if rules.is_empty() {
+        return Err("it contains no PAM rules".to_string());
+    }
+
+    // Includes are not expected in a patch, only plain rules
+    match rules
+        .iter()
+        .find(|rule| rule.starts_with('@') || !is_valid_pam_rule(rule))
+    {
+        Some(rule) => Err(format!("'{}' is not a PAM rule", rule)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a user override only holds `auth` rules loading pam_fprintd.so by name
+/// with one of the plain controls, so it can't weaken the rest of the PAM stack
+fn validate_override_content(content: &str) -> Result<(), String> {
+    match pam_rules(content).iter().find(|rule| {
+        let mut fields = rule.split_whitespace();
+        fields.next() != Some("auth")
+            || !fields
+                .next()
+                .is_some_and(|control| OVERRIDE_CONTROLS.contains(&control))
+            || fields.next() != Some(FPRINTD_MODULE)
+    }) {
+        Some(rule) => Err(format!(
+            "'{}' is not an auth rule for {}; overrides may only change how {} is used",
+            rule, FPRINTD_MODULE, FPRINTD_MODULE
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Returns the first module referenced by the rules in `content` that `is_installed`
+/// does not know, ignoring arguments that are not shared objects
+fn missing_pam_module(content: &str, is_installed: impl Fn(&str) -> bool) -> Option<String> {
+    pam_rules(content)
+        .iter()
+        .filter_map(|rule| rule_module(rule))
+        .filter(|module| module.ends_with(".so"))
+        .find(|module| !is_installed(module))
+        .map(str::to_string)
+}
+
+/// Returns the first rule in `content` that loads pam_fprintd.so, by name or path
+fn fprintd_rule(content: &str) -> Option<String> {
+    pam_rules(content).into_iter().find(|rule| {
+        rule_module(rule)
+            .and_then(|module| module.rsplit('/').next())
+            .is_some_and(|name| name == FPRINTD_MODULE)
+    })
+}
+
+/// Returns the module path field of a `type control module-path [args]` rule
+fn rule_module(rule: &str) -> Option<&str> {
+    let mut fields = rule.split_whitespace().skip(1);
+    let control = fields.next()?;
+    if control.starts_with('[') && !control.ends_with(']') {
+        fields.by_ref().find(|field| field.ends_with(']'))?;
+    }
+    fields.next()
+}
+
+/// Whether a module named in a rule exists, either as an absolute path or in a
+/// PAM module directory
+fn is_pam_module_installed(module: &str) -> bool {
+    if module.starts_with('/') {
+        Path::new(module).is_file()
+    } else {
+        find_pam_module(module).is_some()
+    }
+}
+
 /// Creates a fenced configuration block with begin/end markers
 fn create_fenced_block(content: &str) -> String {
     format!("{}\n{}\n{}\n", BEGIN_MARK, content, END_MARK)
@@ -140,28 +645,38 @@ fn read_file_or_default(path: &Path, default: &str) -> io::Result<String> {
     }
 }
 
-/// Removes any existing fenced blocks from the content
+/// Removes any existing fenced blocks from the content.
+/// Lines after a BEGIN marker that is never closed are kept, since they are the
+/// admin's own configuration rather than a block this tool wrote.
 fn remove_fenced_blocks(content: &str) -> String {
     let mut result = String::with_capacity(content.len());
-    let mut inside_block = false;
+    // Lines of the block currently open, dropped once its END marker is found
+    let mut pending: Option<String> = None;
 
     for line in content.lines() {
         let trimmed = line.trim();
 
         if trimmed == BEGIN_MARK {
-            inside_block = true;
+            pending.get_or_insert_with(String::new);
             continue;
         }
 
         if trimmed == END_MARK {
-            inside_block = false;
+            pending = None;
             continue;
         }
 
-        if !inside_block {
-            result.push_str(line);
-            result.push('\n');
-        }
+        let target = pending.as_mut().unwrap_or(&mut result);
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    if let Some(unterminated) = pending {
+        eprintln!(
+            "Warning: '{}' without a matching '{}'; keeping the lines after it",
+            BEGIN_MARK, END_MARK
+        );
+        result.push_str(&unterminated);
     }
 
     result
@@ -170,7 +685,7 @@ fn remove_fenced_blocks(content: &str) -> String {
 /// Inserts a configuration block after the first line (typically the PAM header)
 fn insert_block_after_header(mut base_content: String, block: &str) -> String {
     // Ensure content ends with newline for predictable processing
-    if !base_content.ends_with('\n') {
+    if !base_content.is_empty() && !base_content.ends_with('\n') {
         base_content.push('\n');
     }
 
@@ -200,8 +715,159 @@ fn insert_block_after_header(mut base_content: String, block: &str) -> String {
     result
 }
 
+/// Returns the services pulled into the auth stack via `include`/`substack`
+/// directives (including Debian-style `@include`)
+fn find_auth_includes(content: &str) -> Vec<String> {
+    let mut includes = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["@include", target, ..] => includes.push(target.to_string()),
+            [kind, control, target, ..]
+                if kind.trim_start_matches('-') == "auth"
+                    && (*control == "include" || *control == "substack") =>
+            {
+                includes.push(target.to_string())
+            }
+            _ => {}
+        }
+    }
+
+    includes
+}
+
+/// Resets the SELinux security context of a path to the policy default
+/// Does nothing on systems without SELinux
+fn restore_selinux_context(path: &Path) {
+    if !Path::new("/sys/fs/selinux").exists() {
+        return;
+    }
+
+    match std::process::Command::new("restorecon").arg(path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!(
+            "Warning: restorecon {} exited with {}",
+            path.display(),
+            status
+        ),
+        Err(e) => eprintln!("Warning: failed to run restorecon: {}", e),
+    }
+}
+
+/// SELinux enforcing switch, "1" when enforcing
+const SELINUX_ENFORCE_PATH: &str = "/sys/fs/selinux/enforce";
+
+/// AppArmor label of this process; older kernels only have the shared attr file
+const APPARMOR_LABEL_PATHS: &[&str] = &[
+    "/proc/self/attr/apparmor/current",
+    "/proc/self/attr/current",
+];
+
+/// Adds a hint about SELinux or AppArmor to a permission error seen while running as root,
+/// since root is only refused by a mandatory access control policy
+fn explain_permission_denied(e: io::Error) -> io::Error {
+    if e.kind() != io::ErrorKind::PermissionDenied || !is_root() {
+        return e;
+    }
+
+    let read_trimmed = |path: &str| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|s| s.trim_end_matches(['\n', '\0']).to_string())
+    };
+    let selinux_enforce = read_trimmed(SELINUX_ENFORCE_PATH);
+    let apparmor_label = APPARMOR_LABEL_PATHS
+        .iter()
+        .find_map(|path| read_trimmed(path));
+
+    match mac_hint(selinux_enforce.as_deref(), apparmor_label.as_deref()) {
+        Some(hint) => io::Error::new(e.kind(), format!("{}. Hint: {}", e, hint)),
+        None => e,
+    }
+}
+
+/// Hint naming the access control layer that may have refused a write, if one is enforcing
+fn mac_hint(selinux_enforce: Option<&str>, apparmor_label: Option<&str>) -> Option<String> {
+    if selinux_enforce == Some("1") {
+        return Some(
+            "SELinux is enforcing and may have blocked the write; check 'ausearch -m avc -ts recent'"
+                .to_string(),
+        );
+    }
+    // Confined processes are labelled "<profile> (enforce)"
+    let profile = apparmor_label?.strip_suffix(" (enforce)")?;
+    Some(format!(
+        "AppArmor profile '{}' may have blocked the write; check 'journalctl -k | grep apparmor'",
+        profile
+    ))
+}
+
+/// Names the file and its directory when a write failed because the filesystem is
+/// read-only, as on immutable or image-based systems
+fn explain_read_only(path: &Path, e: io::Error) -> io::Error {
+    if e.kind() != io::ErrorKind::ReadOnlyFilesystem {
+        return e;
+    }
+
+    let dir = path.parent().unwrap_or(path);
+    io::Error::new(
+        e.kind(),
+        format!(
+            "{} is on a read-only filesystem. Hint: {} is mounted read-only; on immutable systems change PAM through the system image or an /etc overlay",
+            path.display(),
+            dir.display()
+        ),
+    )
+}
+
+/// Refuses to write to a symlink: the rename would replace the link with a regular file
+/// and cut the service off from the file it points at, e.g. a shared system-auth
+fn refuse_symlink(path: &Path) -> io::Result<()> {
+    if !fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return Ok(());
+    }
+
+    let target = fs::read_link(path)
+        .map(|target| target.display().to_string())
+        .unwrap_or_else(|_| "an unreadable target".to_string());
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{} is a symlink to {}; refusing to replace it with a regular file. Hint: change the file it points to instead",
+            path.display(),
+            target
+        ),
+    ))
+}
+
+/// Adds the read-only filesystem and MAC hints to a failed write of `path`
+fn explain_write_error(path: &Path, e: io::Error) -> io::Error {
+    explain_permission_denied(explain_read_only(path, e))
+}
+
 /// Atomically writes data to a file using a temporary file and rename
 fn atomic_write(path: &Path, data: &[u8]) -> io::Result<()> {
+    refuse_symlink(path)?;
+    replace_via_temp_file(path, data).map_err(|e| explain_write_error(path, e))
+}
+
+/// Writes data to a temporary file next to `path` and renames it over `path`
+fn replace_via_temp_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp_path = stage_temp_file(path, data)?;
+    commit_temp_file(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// Writes data to a new temporary file next to `path`, with the permissions and
+/// ownership of `path`, and returns the temporary file
+fn stage_temp_file(path: &Path, data: &[u8]) -> io::Result<PathBuf> {
     let parent = path.parent().ok_or_else(|| {
         io::Error::new(io::ErrorKind::InvalidInput, "Path has no parent directory")
     })?;
@@ -219,15 +885,18 @@ fn atomic_write(path: &Path, data: &[u8]) -> io::Result<()> {
     let temp_name = format!(".{}.{}-{}.tmp", file_name, pid, timestamp);
     let temp_path = parent.join(temp_name);
 
-    // Preserve existing file permissions or use default 0644
-    let mode = if path.exists() {
-        fs::metadata(path)?.permissions().mode()
+    // Preserve existing file permissions and ownership, or use default 0644
+    let (mode, owner) = if path.exists() {
+        let metadata = fs::metadata(path)?;
+        (
+            metadata.permissions().mode(),
+            Some((metadata.uid(), metadata.gid())),
+        )
     } else {
-        0o644
+        (0o644, None)
     };
 
-    // Write to temporary file
-    {
+    let write = || -> io::Result<()> {
         let mut file = fs::OpenOptions::new()
             .create(true)
             .write(true)
@@ -235,132 +904,859 @@ fn atomic_write(path: &Path, data: &[u8]) -> io::Result<()> {
             .open(&temp_path)?;
         file.write_all(data)?;
         file.sync_all()?;
+
+        fs::set_permissions(&temp_path, fs::Permissions::from_mode(mode))?;
+        if let Some((uid, gid)) = owner {
+            unix_fs::chown(&temp_path, Some(uid), Some(gid))?;
+        }
+        Ok(())
+    };
+
+    match write() {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
     }
+}
 
-    // Set permissions and atomically replace
-    fs::set_permissions(&temp_path, fs::Permissions::from_mode(mode))?;
-    fs::rename(&temp_path, path)?;
+/// Atomically replaces `path` with a file staged by `stage_temp_file`
+fn commit_temp_file(temp_path: &Path, path: &Path) -> io::Result<()> {
+    fs::rename(temp_path, path)?;
+    restore_selinux_context(path);
 
     // Sync directory for durability
-    if let Ok(dir) = fs::File::open(parent) {
+    if let Some(dir) = path.parent().and_then(|parent| fs::File::open(parent).ok()) {
         let _ = dir.sync_all();
     }
 
     Ok(())
 }
 
-/// Applies configuration to the specified target
-fn apply_config(target: &TargetConfig) -> io::Result<()> {
-    let path = Path::new(&target.file);
+/// Writes every planned file or none of them: all contents are staged as temporary
+/// files before the first rename, and a failed rename restores the files already
+/// replaced. On failure returns the failed path, its error and the replaced files
+/// that could not be restored
+fn commit_all(plans: &[&PlannedWrite]) -> Result<(), (PathBuf, io::Error, Vec<String>)> {
+    let mut staged: Vec<(&PlannedWrite, PathBuf)> = Vec::new();
+    let discard = |staged: &[(&PlannedWrite, PathBuf)]| {
+        for (_, temp_path) in staged {
+            let _ = fs::remove_file(temp_path);
+        }
+    };
 
-    if !is_allowlisted_path(path) {
-        return Err(io::Error::new(
-            io::ErrorKind::PermissionDenied,
-            format!("Target path is not allowlisted: {}", target.file),
-        ));
+    for plan in plans {
+        let temp_path = refuse_symlink(&plan.path)
+            .and_then(|()| stage_temp_file(&plan.path, plan.content.as_bytes()));
+        match temp_path {
+            Ok(temp_path) => staged.push((plan, temp_path)),
+            Err(e) => {
+                discard(&staged);
+                let e = explain_write_error(&plan.path, e);
+                return Err((plan.path.clone(), e, Vec::new()));
+            }
+        }
     }
 
-    // Read the patch content
-    let patch_content = read_patch_content(&target.file)?;
-
-    // Use default file if specified and target doesn't exist
-    let base_content = if !path.exists() {
-        if let Some(default_path) = &target.default {
-            let default = Path::new(default_path);
-            if default.is_file() {
-                fs::read_to_string(default)?
-            } else {
-                read_file_or_default(path, PAM_HEADER)?
-            }
-        } else {
-            read_file_or_default(path, PAM_HEADER)?
+    for (index, (plan, temp_path)) in staged.iter().enumerate() {
+        if let Err(e) = commit_temp_file(temp_path, &plan.path) {
+            discard(&staged[index..]);
+            let not_restored = staged[..index]
+                .iter()
+                .filter_map(|(replaced, _)| match replaced.restore() {
+                    Ok(()) => None,
+                    Err(restore_error) => {
+                        eprintln!(
+                            "Error restoring {}: {}",
+                            replaced.path.display(),
+                            restore_error
+                        );
+                        Some(replaced.path.display().to_string())
+                    }
+                })
+                .collect();
+            let e = explain_write_error(&plan.path, e);
+            return Err((plan.path.clone(), e, not_restored));
         }
-    } else {
-        read_file_or_default(path, PAM_HEADER)?
-    };
+    }
 
-    // Remove any existing blocks and insert the new one
-    let cleaned_content = remove_fenced_blocks(&base_content);
-    let final_content = insert_block_after_header(cleaned_content, &patch_content);
+    Ok(())
+}
 
-    atomic_write(path, final_content.as_bytes())
+/// Applies configuration to the specified target with the patch from `patches_dir`,
+/// returning whether the file changed
+fn apply_config(target: &TargetConfig, patches_dir: &Path) -> Result<bool, ApplyError> {
+    let patch_content = checked_patch(target, patches_dir)?;
+
+    apply_patch(
+        Path::new(&target.file),
+        target.default.as_deref(),
+        &patch_content,
+    )
+    .map_err(|e| ApplyError::new(apply_failure(&e), e))
 }
 
-/// Removes configuration from the specified target path
-fn remove_config(target_path: &str) -> io::Result<()> {
-    let path = Path::new(target_path);
+/// Computes the new content of the target without writing it, refusing content that
+/// would add PAM syntax errors. Returns None when the block is already in place
+fn plan_config(
+    target: &TargetConfig,
+    patches_dir: &Path,
+) -> Result<Option<PlannedWrite>, ApplyError> {
+    let patch_content = checked_patch(target, patches_dir)?;
+    let path = Path::new(&target.file);
 
-    if !path.exists() || !is_allowlisted_path(path) {
-        return Ok(()); // Nothing to do
+    let plan = plan_patch(path, target.default.as_deref(), &patch_content)
+        .map_err(|e| ApplyError::new(apply_failure(&e), e))?;
+    if let Some(plan) = &plan {
+        // Caught before anything is written, so nothing is rolled back
+        if let Some(line) = introduced_errors(&plan.base_content, &plan.content).first() {
+            return Err(ApplyError::new(
+                ApplyFailure::PatchRejected,
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Not writing {}: invalid PAM line '{}'",
+                        path.display(),
+                        line
+                    ),
+                ),
+            ));
+        }
     }
+    Ok(plan)
+}
 
-    let original_content = fs::read_to_string(path)?;
-    let cleaned_content = remove_fenced_blocks(&original_content);
-
-    // Only write if content changed
-    if cleaned_content != original_content {
-        atomic_write(path, cleaned_content.as_bytes())?;
+/// Applies every target or none of them, returning whether each file changed.
+/// All targets are checked and their new content computed before anything is written
+fn apply_atomic(targets: &[TargetConfig], patches_dir: &Path) -> Result<Vec<bool>, AtomicAbort> {
+    let mut plans = Vec::new();
+    let mut failures = Vec::new();
+    for target in targets {
+        match plan_config(target, patches_dir) {
+            Ok(plan) => plans.push(plan),
+            Err(e) => failures.push((target.file.clone(), e)),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(AtomicAbort {
+            failures,
+            not_restored: Vec::new(),
+        });
     }
 
-    Ok(())
+    let changes: Vec<&PlannedWrite> = plans.iter().flatten().collect();
+    commit_all(&changes).map_err(|(path, e, not_restored)| AtomicAbort {
+        failures: vec![(
+            path.display().to_string(),
+            ApplyError::new(apply_failure(&e), e),
+        )],
+        not_restored,
+    })?;
+
+    Ok(plans.iter().map(Option::is_some).collect())
 }
 
-/// Checks if configuration is applied to the specified target path
-fn is_config_applied(target_path: &str) -> io::Result<bool> {
-    let path = Path::new(target_path);
+/// Checks that the target may be configured and returns its patch from `patches_dir`
+fn checked_patch(target: &TargetConfig, patches_dir: &Path) -> Result<String, ApplyError> {
+    let path = Path::new(&target.file);
 
-    if !path.exists() {
-        return Ok(false);
+    if !is_allowlisted_path(path) {
+        return Err(ApplyError::new(
+            ApplyFailure::NotAllowlisted,
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Target path is not allowlisted: {}", target.file),
+            ),
+        ));
     }
 
-    let content = fs::read_to_string(path)?;
-    Ok(content.contains(BEGIN_MARK))
-}
+    // Read the patch content
+    let patch_content = read_patch_content(&target.file, patches_dir).map_err(|e| {
+        let failure = match e.kind() {
+            io::ErrorKind::NotFound => ApplyFailure::PatchMissing,
+            _ => ApplyFailure::PatchRejected,
+        };
+        ApplyError::new(failure, e)
+    })?;
 
-/// Checks if the current process is running as root
-fn is_root() -> bool {
-    unsafe { libc::geteuid() == 0 }
+    // A block referencing a missing module would break the service
+    if let Some(module) = missing_pam_module(&patch_content, is_pam_module_installed) {
+        let hint = if module == FPRINTD_MODULE {
+            "; install fprintd-pam first"
+        } else {
+            ""
+        };
+        return Err(ApplyError::new(
+            ApplyFailure::ModuleMissing,
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not installed{}", module, hint),
+            ),
+        ));
+    }
+
+    Ok(patch_content)
 }
 
-/// Requires root privileges for the operation, exits with error code 126 if not root
-fn require_root() {
-    if !is_root() {
-        eprintln!("Permission denied: must be run as root (via pkexec)");
-        std::process::exit(126);
+/// Classifies a failed `apply_patch` for the exit code
+fn apply_failure(e: &io::Error) -> ApplyFailure {
+    // apply_patch reports a rollback as RolledBack and a duplicate module as
+    // AlreadyExists; anything else, unreadable content included, is an I/O failure
+    if e.get_ref().is_some_and(|inner| inner.is::<RolledBack>()) {
+        return ApplyFailure::RolledBack;
+    }
+    match e.kind() {
+        io::ErrorKind::AlreadyExists => ApplyFailure::Conflict,
+        io::ErrorKind::ReadOnlyFilesystem => ApplyFailure::ReadOnly,
+        _ => ApplyFailure::WriteFailed,
     }
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// Inserts the patch block into the target, rolling back if the written file gains
+/// PAM syntax errors. Returns false without writing when the block is already in place.
+fn apply_patch(path: &Path, default: Option<&str>, patch_content: &str) -> io::Result<bool> {
+    let Some(plan) = plan_patch(path, default, patch_content)? else {
+        return Ok(false);
+    };
 
-    match cli.cmd {
-        Command::Apply { targets } => {
-            require_root();
-            let mut errors = Vec::new();
+    atomic_write(path, plan.content.as_bytes())?;
 
-            for target_str in &targets {
-                // Try to parse as JSON first
-                let target = match TargetConfig::from_json(target_str) {
-                    Ok(t) => t,
-                    Err(_) => {
-                        // If JSON parsing fails, treat as simple file path for backwards compatibility
-                        TargetConfig::new(target_str.clone())
-                    }
-                };
+    // Only errors introduced by this apply count; pre-existing oddities are left alone
+    let written = fs::read_to_string(path)?;
+    let new_errors = introduced_errors(&plan.base_content, &written);
+    if new_errors.is_empty() {
+        return Ok(true);
+    }
 
-                match apply_config(&target) {
-                    Ok(()) => println!("Success: applied configuration to {}", target.file),
-                    Err(e) => {
-                        let error =
-                            format!("Error applying configuration to {}: {}", target.file, e);
-                        eprintln!("{}", error);
-                        errors.push(error);
-                    }
-                }
-            }
+    plan.restore()?;
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        RolledBack(format!(
+            "Rolled back {}: invalid PAM line '{}'",
+            path.display(),
+            new_errors[0]
+        )),
+    ))
+}
 
-            if !errors.is_empty() {
-                std::process::exit(1);
+/// Computes the content of the target with the patch block inserted, without writing.
+/// Returns None when the block is already in place
+fn plan_patch(
+    path: &Path,
+    default: Option<&str>,
+    patch_content: &str,
+) -> io::Result<Option<PlannedWrite>> {
+    // Backup for this transaction; None means the target did not exist
+    let backup = if path.exists() {
+        Some(fs::read(path)?)
+    } else {
+        None
+    };
+
+    // Use default file if specified and target doesn't exist
+    let base_content = match (default, backup.is_some()) {
+        (Some(default_path), false) => read_default_content(path, default_path)?,
+        _ => read_file_or_default(path, PAM_HEADER)?,
+    };
+
+    // Remove any existing blocks and insert the new one
+    let cleaned_content = remove_fenced_blocks(&base_content);
+
+    // A second pam_fprintd.so rule outside our block would prompt for a finger twice
+    let existing_rule = fprintd_rule(patch_content).and(fprintd_rule(&cleaned_content));
+    if let Some(rule) = existing_rule {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already has '{}' outside the xfprintd-gui block; remove it first",
+                path.display(),
+                rule
+            ),
+        ));
+    }
+
+    let final_content = insert_block_after_header(cleaned_content, patch_content);
+
+    // Leave an already configured file untouched so its mtime stays the same
+    if backup.as_deref() == Some(final_content.as_bytes()) {
+        return Ok(None);
+    }
+
+    Ok(Some(PlannedWrite {
+        path: path.to_path_buf(),
+        backup,
+        base_content,
+        content: final_content,
+    }))
+}
+
+/// Rule lines that are invalid in `content` but not in `base_content`
+fn introduced_errors(base_content: &str, content: &str) -> Vec<String> {
+    let base_errors = pam_syntax_errors(base_content);
+    pam_syntax_errors(content)
+        .into_iter()
+        .filter(|line| !base_errors.contains(line))
+        .collect()
+}
+
+/// Returns the rule lines that are not valid PAM syntax
+fn pam_syntax_errors(content: &str) -> Vec<String> {
+    pam_rules(content)
+        .into_iter()
+        .filter(|rule| !is_valid_pam_rule(rule))
+        .collect()
+}
+
+/// Returns the rules in PAM content, joining backslash continued lines and
+/// skipping blank lines and comments
+fn pam_rules(content: &str) -> Vec<String> {
+    let mut rules = Vec::new();
+    let mut logical = String::new();
+
+    for line in content.lines() {
+        // Join backslash continued lines into one rule
+        if let Some(continued) = line.strip_suffix('\\') {
+            logical.push_str(continued);
+            logical.push(' ');
+            continue;
+        }
+        logical.push_str(line);
+
+        let rule = logical.trim();
+        if !rule.is_empty() && !rule.starts_with('#') {
+            rules.push(rule.to_string());
+        }
+        logical.clear();
+    }
+
+    rules
+}
+
+/// Checks a single rule for `type control module-path [args]` (or Debian `@include`)
+fn is_valid_pam_rule(rule: &str) -> bool {
+    if rule.starts_with("@include ") {
+        return true;
+    }
+
+    let mut fields = rule.split_whitespace();
+    let Some(pam_type) = fields.next() else {
+        return false;
+    };
+    if !PAM_TYPES.contains(&pam_type.trim_start_matches('-')) {
+        return false;
+    }
+
+    let Some(control) = fields.next() else {
+        return false;
+    };
+    if control.starts_with('[') {
+        // Bracketed controls may span several whitespace separated fields
+        if !control.ends_with(']') && !fields.by_ref().any(|field| field.ends_with(']')) {
+            return false;
+        }
+    } else if !PAM_CONTROLS.contains(&control) {
+        return false;
+    }
+
+    fields.next().is_some()
+}
+
+/// Finds a PAM module in /usr/lib*/security, /lib*/security or a multiarch subdirectory
+fn find_pam_module(name: &str) -> Option<PathBuf> {
+    for root in ["/usr", "/"] {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with("lib") {
+                continue;
+            }
+            let lib = entry.path();
+            let mut dirs = vec![lib.join("security")];
+            if let Ok(arches) = fs::read_dir(&lib) {
+                dirs.extend(
+                    arches
+                        .flatten()
+                        .filter(|arch| arch.file_name().to_string_lossy().contains("-linux-"))
+                        .map(|arch| arch.path().join("security")),
+                );
+            }
+
+            if let Some(module) = dirs
+                .into_iter()
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+            {
+                return Some(module);
+            }
+        }
+    }
+
+    None
+}
+
+/// Candidate locations of a default file: the given path, then the same file name
+/// in each vendor PAM directory
+fn default_candidates(default_path: &str) -> Vec<PathBuf> {
+    let default = PathBuf::from(default_path);
+    let mut candidates = vec![default.clone()];
+
+    if let Some(name) = default.file_name() {
+        for dir in VENDOR_PAM_DIRS {
+            let candidate = Path::new(dir).join(name);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Reads the first existing default file for a missing target, falling back to the PAM header
+fn read_default_content(target: &Path, default_path: &str) -> io::Result<String> {
+    for candidate in default_candidates(default_path) {
+        if candidate != target && candidate.is_file() {
+            eprintln!(
+                "Using {} as base for {}",
+                candidate.display(),
+                target.display()
+            );
+            return fs::read_to_string(candidate);
+        }
+    }
+
+    eprintln!(
+        "Warning: no default found for {} (tried {} and vendor directories), using bare PAM header",
+        target.display(),
+        default_path
+    );
+    Ok(format!("{}\n", PAM_HEADER))
+}
+
+/// Removes configuration from the specified target path
+fn remove_config(target_path: &str) -> io::Result<()> {
+    let path = Path::new(target_path);
+
+    if !path.exists() || !is_allowlisted_path(path) {
+        return Ok(()); // Nothing to do
+    }
+
+    let original_content = fs::read_to_string(path)?;
+    let cleaned_content = remove_fenced_blocks(&original_content);
+
+    // Only write if content changed
+    if cleaned_content != original_content {
+        atomic_write(path, cleaned_content.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Checks if configuration is applied to the specified target path
+fn is_config_applied(target_path: &str) -> io::Result<bool> {
+    let path = Path::new(target_path);
+
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(content.contains(BEGIN_MARK))
+}
+
+/// Counts the fence markers in content, pairing each END with the BEGIN before it
+fn audit_blocks(path: &str, content: &str) -> BlockAudit {
+    let mut audit = BlockAudit {
+        path: path.to_string(),
+        ..BlockAudit::default()
+    };
+    let mut open = false;
+
+    for line in content.lines().map(str::trim) {
+        if line == BEGIN_MARK {
+            if open {
+                audit.unterminated += 1;
+            }
+            open = true;
+        } else if line == END_MARK {
+            if open {
+                audit.blocks += 1;
+            } else {
+                audit.stray_ends += 1;
+            }
+            open = false;
+        }
+    }
+    if open {
+        audit.unterminated += 1;
+    }
+
+    audit.malformed = audit.blocks > 1 || audit.unterminated > 0 || audit.stray_ends > 0;
+    audit
+}
+
+/// Audits every file in the allowlisted directories that contains fence markers
+fn audit_files() -> io::Result<Vec<BlockAudit>> {
+    let mut audits = Vec::new();
+
+    for dir in ALLOWED_DIRS {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_allowlisted_path(path) && path.is_file())
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let audit = audit_blocks(&path.to_string_lossy(), &content);
+            if audit.blocks + audit.unterminated + audit.stray_ends > 0 {
+                audits.push(audit);
+            }
+        }
+    }
+
+    Ok(audits)
+}
+
+/// Lists the services with a patch in any of `patches_dirs` whose target lies directly
+/// in one of `allowed_dirs`, sorted by target path without duplicates
+fn managed_services_in(
+    patches_dirs: &[&Path],
+    allowed_dirs: &[&str],
+) -> io::Result<Vec<ManagedService>> {
+    let mut services: Vec<ManagedService> = Vec::new();
+
+    for patches_dir in patches_dirs {
+        for dir in allowed_dirs {
+            let entries = match fs::read_dir(patches_dir.join(dir.trim_start_matches('/'))) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            for patch in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                if patch.extension() != Some(OsStr::new("patch")) || !patch.is_file() {
+                    continue;
+                }
+                let Some(service) = patch.file_stem().and_then(OsStr::to_str) else {
+                    continue;
+                };
+                let path = format!("{}/{}", dir.trim_end_matches('/'), service);
+                if !is_allowlisted_in(Path::new(&path), allowed_dirs)
+                    || services.iter().any(|known| known.path == path)
+                {
+                    continue;
+                }
+                services.push(ManagedService {
+                    service: service.to_string(),
+                    path,
+                });
+            }
+        }
+    }
+
+    services.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(services)
+}
+
+/// Rewrites a malformed file with a single block from its patch when it had a complete
+/// block, otherwise only strips the markers
+fn fix_blocks(audit: &BlockAudit, patches_dir: &Path) -> io::Result<()> {
+    if audit.blocks == 0 {
+        return remove_config(&audit.path);
+    }
+
+    apply_config(&TargetConfig::new(audit.path.clone()), patches_dir)
+        .map(|_| ())
+        .map_err(|e| e.source)
+}
+
+/// Lists services included into the auth stack of the target path
+fn auth_includes(target_path: &str) -> io::Result<Vec<String>> {
+    let path = Path::new(target_path);
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(find_auth_includes(&fs::read_to_string(path)?))
+}
+
+/// Returns the fenced block that `apply_config` would insert for the target path
+fn show_config(target_path: &str, patches_dir: &Path) -> io::Result<String> {
+    if !is_allowlisted_path(Path::new(target_path)) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Target path is not allowlisted: {}", target_path),
+        ));
+    }
+
+    let patch_content = read_patch_content(target_path, patches_dir)?;
+    Ok(create_fenced_block(&patch_content))
+}
+
+/// Returns whether PAM conversation output contains a pam_fprintd.so finger prompt,
+/// "Place your <finger> on <reader>" or "Swipe your <finger> across <reader>".
+/// Only whole prompt lines count, so a service name such as gdm-fingerprint echoed
+/// by pamtester or an fprintd error about fingers does not
+fn is_fprintd_prompt(output: &str) -> bool {
+    output.lines().any(|line| {
+        let line = line.trim();
+        line.strip_prefix("Place your ")
+            .is_some_and(|rest| rest.contains(" on "))
+            || line
+                .strip_prefix("Swipe your ")
+                .is_some_and(|rest| rest.contains(" across "))
+    })
+}
+
+/// Forwards everything read from a child pipe to the channel
+fn forward_output<R: Read + Send + 'static>(mut pipe: R, tx: mpsc::Sender<Vec<u8>>) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 256];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Kills and reaps a child process, ignoring errors if it already exited
+fn stop_child(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Runs `pamtester <service> <user> authenticate` and reports whether
+/// pam_fprintd.so prompted for a finger before the stack gave up
+fn selftest(service: &str, user: &str) -> io::Result<bool> {
+    if service.is_empty() || service.contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid PAM service name: {}", service),
+        ));
+    }
+
+    // Closed stdin makes any password prompt fail immediately instead of blocking;
+    // the C locale keeps the pam_fprintd.so prompts untranslated for is_fprintd_prompt
+    let mut child = std::process::Command::new("pamtester")
+        .args([service, user, "authenticate"])
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(e.kind(), "pamtester is not installed")
+            } else {
+                e
+            }
+        })?;
+
+    let (tx, rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward_output(stdout, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_output(stderr, tx);
+    }
+
+    let deadline = Instant::now() + SELFTEST_TIMEOUT;
+    let mut output = String::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok(chunk) => {
+                output.push_str(&String::from_utf8_lossy(&chunk));
+                if is_fprintd_prompt(&output) {
+                    stop_child(&mut child);
+                    return Ok(true);
+                }
+            }
+            // Both pipes closed: pamtester finished without fprintd prompting
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let _ = child.wait();
+                return Ok(false);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                stop_child(&mut child);
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// First line of `--version`; the GUI compares the version in it with its own
+fn version_line() -> String {
+    format!("xfprintd-gui-helper {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Prints the helper version, and with `verbose` the paths it operates on
+fn print_version(verbose: bool, patches_dir: &Path) {
+    println!("{}", version_line());
+
+    if verbose {
+        let state = |path: &str| {
+            if Path::new(path).is_dir() {
+                "present"
+            } else {
+                "missing"
+            }
+        };
+
+        let patches_dir = patches_dir.to_string_lossy();
+        println!(
+            "Patches directory: {} ({})",
+            patches_dir,
+            state(&patches_dir)
+        );
+        if let Some(dir) = user_patches_dir() {
+            println!(
+                "User patch overrides: {} ({})",
+                dir.display(),
+                state(&dir.to_string_lossy())
+            );
+        }
+        for dir in ALLOWED_DIRS {
+            println!("Allowed directory: {} ({})", dir, state(dir));
+        }
+    }
+}
+
+/// Prints the outcome of applying the configuration to a target
+fn report_applied(file: &str, changed: bool) {
+    if !changed {
+        println!("Unchanged: {} is already configured", file);
+        return;
+    }
+
+    println!("Success: applied configuration to {}", file);
+    for include in auth_includes(file).unwrap_or_default() {
+        println!(
+            "Note: {} also includes auth rules from {}; the fingerprint block runs before them",
+            file, include
+        );
+    }
+}
+
+/// Checks if the current process is running as root
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Requires root privileges for the operation, exits with error code 126 if not root
+fn require_root() {
+    if !is_root() {
+        eprintln!("Permission denied: must be run as root (via pkexec)");
+        std::process::exit(126);
+    }
+}
+
+/// Patches directory to read from, the packaged one unless `requested`.
+/// Under pkexec the arguments reach the root helper unchanged, so the caller must not
+/// be able to pick the patches written into the system PAM files
+fn resolve_patches_dir(requested: Option<PathBuf>, pkexec: bool) -> Result<PathBuf, String> {
+    match requested {
+        Some(dir) if pkexec => Err(format!(
+            "refusing --patches-dir {} when run through pkexec; only {} is used there",
+            dir.display(),
+            PATCHES_BASE_DIR
+        )),
+        Some(dir) => Ok(dir),
+        None => Ok(PathBuf::from(PATCHES_BASE_DIR)),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let patches_dir =
+        match resolve_patches_dir(cli.patches_dir, env::var_os("PKEXEC_UID").is_some()) {
+            Ok(dir) => dir,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2);
+            }
+        };
+
+    if cli.version {
+        print_version(cli.verbose, &patches_dir);
+        return;
+    }
+
+    let Some(cmd) = cli.cmd else {
+        eprintln!("Error: a subcommand is required (see --help)");
+        std::process::exit(2);
+    };
+
+    match cmd {
+        Command::Apply { atomic, targets } => {
+            require_root();
+            let targets: Vec<TargetConfig> = targets
+                .iter()
+                .map(|target_str| {
+                    // Not JSON: a plain file path, kept for backwards compatibility
+                    TargetConfig::from_json(target_str)
+                        .unwrap_or_else(|_| TargetConfig::new(target_str.clone()))
+                })
+                .collect();
+
+            if atomic {
+                match apply_atomic(&targets, &patches_dir) {
+                    Ok(changed) => {
+                        for (target, changed) in targets.iter().zip(changed) {
+                            report_applied(&target.file, changed);
+                        }
+                    }
+                    Err(abort) => {
+                        for (file, e) in &abort.failures {
+                            eprintln!("Error applying configuration to {}: {}", file, e);
+                        }
+                        if abort.not_restored.is_empty() {
+                            eprintln!("Aborted: no PAM file was changed");
+                        } else {
+                            eprintln!(
+                                "Aborted, but these files keep the new configuration: {}",
+                                abort.not_restored.join(", ")
+                            );
+                        }
+                        std::process::exit(abort.failures[0].1.failure.exit_code());
+                    }
+                }
+                return;
+            }
+
+            let mut first_failure = None;
+            let mut applied = 0;
+
+            for target in &targets {
+                match apply_config(target, &patches_dir) {
+                    Ok(changed) => {
+                        report_applied(&target.file, changed);
+                        applied += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("Error applying configuration to {}: {}", target.file, e);
+                        first_failure.get_or_insert(e.failure);
+                    }
+                }
+            }
+
+            if let Some(failure) = first_failure {
+                if applied > 0 {
+                    eprintln!(
+                        "Partial: {} of {} targets configured; use --atomic to change all or none",
+                        applied,
+                        targets.len()
+                    );
+                }
+                std::process::exit(failure.exit_code());
             }
         }
 
@@ -384,18 +1780,12 @@ fn main() {
             }
         }
 
-        Command::Check { paths } => {
-            let mut all_applied = true;
+        Command::Check { paths, json } => {
+            let mut statuses = Vec::new();
 
-            for path in &paths {
-                match is_config_applied(path) {
-                    Ok(true) => {
-                        println!("applied: {}", path);
-                    }
-                    Ok(false) => {
-                        println!("not-applied: {}", path);
-                        all_applied = false;
-                    }
+            for path in paths {
+                match is_config_applied(&path) {
+                    Ok(applied) => statuses.push(CheckStatus { path, applied }),
                     Err(e) => {
                         eprintln!("Error checking {}: {}", path, e);
                         std::process::exit(2);
@@ -403,7 +1793,921 @@ fn main() {
                 }
             }
 
+            if json {
+                match serde_json::to_string(&statuses) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => {
+                        eprintln!("Error encoding check results: {}", e);
+                        std::process::exit(2);
+                    }
+                }
+            } else {
+                for status in &statuses {
+                    let state = if status.applied {
+                        "applied"
+                    } else {
+                        "not-applied"
+                    };
+                    println!("{}: {}", state, status.path);
+                }
+            }
+
+            let all_applied = statuses.iter().all(|status| status.applied);
             std::process::exit(if all_applied { 0 } else { 1 });
         }
+
+        Command::Includes { path } => match auth_includes(&path) {
+            Ok(includes) => {
+                for include in includes {
+                    println!("{}", include);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+
+        Command::Audit { fix, json } => {
+            if fix {
+                require_root();
+            }
+
+            let mut audits = match audit_files() {
+                Ok(audits) => audits,
+                Err(e) => {
+                    eprintln!("Error auditing PAM files: {}", e);
+                    std::process::exit(2);
+                }
+            };
+
+            if fix {
+                for audit in audits.iter_mut().filter(|audit| audit.malformed) {
+                    match fix_blocks(audit, &patches_dir) {
+                        Ok(()) => audit.fixed = true,
+                        Err(e) => eprintln!("Error repairing {}: {}", audit.path, e),
+                    }
+                }
+            }
+
+            if json {
+                match serde_json::to_string(&audits) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => {
+                        eprintln!("Error encoding audit results: {}", e);
+                        std::process::exit(2);
+                    }
+                }
+            } else {
+                for audit in &audits {
+                    let state = match (audit.malformed, audit.fixed) {
+                        (false, _) => "ok",
+                        (true, false) => "malformed",
+                        (true, true) => "fixed",
+                    };
+                    println!(
+                        "{}: {} ({} block(s), {} unterminated, {} stray end marker(s))",
+                        state, audit.path, audit.blocks, audit.unterminated, audit.stray_ends
+                    );
+                }
+            }
+
+            let remaining = audits.iter().any(|audit| audit.malformed && !audit.fixed);
+            std::process::exit(if remaining { 1 } else { 0 });
+        }
+
+        Command::Services { json } => {
+            let services = match managed_services_in(&[patches_dir.as_path()], ALLOWED_DIRS) {
+                Ok(services) => services,
+                Err(e) => {
+                    eprintln!("Error listing patch files: {}", e);
+                    std::process::exit(2);
+                }
+            };
+
+            if json {
+                match serde_json::to_string(&services) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => {
+                        eprintln!("Error encoding services: {}", e);
+                        std::process::exit(2);
+                    }
+                }
+            } else {
+                for service in &services {
+                    println!("{}", service.path);
+                }
+            }
+        }
+
+        Command::Show { path } => match show_config(&path, &patches_dir) {
+            Ok(block) => print!("{}", block),
+            Err(e) => {
+                eprintln!("Error showing configuration for {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+
+        Command::Selftest { service, user } => {
+            let uid = invoking_uid();
+            let user = match user.or_else(|| user_name_of(uid)) {
+                Some(user) => user,
+                None => {
+                    eprintln!("Error: no user given and uid {} has no account name", uid);
+                    std::process::exit(2);
+                }
+            };
+
+            match selftest(&service, &user) {
+                Ok(true) => println!("reached: {}", service),
+                Ok(false) => {
+                    println!("not-reached: {}", service);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Error testing {}: {}", service, e);
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "auth    sufficient  pam_fprintd.so";
+
+    /// Mirrors what `apply_config` does to the file content.
+    fn apply(content: &str) -> String {
+        insert_block_after_header(remove_fenced_blocks(content), BLOCK)
+    }
+
+    #[test]
+    fn create_fenced_block_wraps_content_in_markers() {
+        assert_eq!(
+            create_fenced_block(BLOCK),
+            format!("{}\n{}\n{}\n", BEGIN_MARK, BLOCK, END_MARK)
+        );
+    }
+
+    #[test]
+    fn insert_into_empty_file_adds_pam_header() {
+        let result = insert_block_after_header(String::new(), BLOCK);
+        assert_eq!(
+            result,
+            format!("{}\n{}\n{}\n{}\n", PAM_HEADER, BEGIN_MARK, BLOCK, END_MARK)
+        );
+    }
+
+    #[test]
+    fn insert_places_block_after_header() {
+        let base = format!("{}\nauth include system-auth\n", PAM_HEADER);
+        let result = insert_block_after_header(base, BLOCK);
+        assert_eq!(
+            result,
+            format!(
+                "{}\n{}\n{}\n{}\nauth include system-auth\n",
+                PAM_HEADER, BEGIN_MARK, BLOCK, END_MARK
+            )
+        );
+    }
+
+    #[test]
+    fn insert_without_header_still_uses_first_line() {
+        let base = "auth include system-auth\naccount include system-auth".to_string();
+        let result = insert_block_after_header(base, BLOCK);
+        assert_eq!(
+            result,
+            format!(
+                "auth include system-auth\n{}\n{}\n{}\naccount include system-auth\n",
+                BEGIN_MARK, BLOCK, END_MARK
+            )
+        );
+    }
+
+    #[test]
+    fn apply_replaces_existing_block_instead_of_duplicating() {
+        let base = format!("{}\nauth include system-auth\n", PAM_HEADER);
+        let once = apply(&base);
+        let twice = apply(&once);
+
+        assert_eq!(once, twice);
+        assert_eq!(twice.matches(BEGIN_MARK).count(), 1);
+        assert_eq!(twice.matches(END_MARK).count(), 1);
+    }
+
+    #[test]
+    fn remove_strips_duplicate_blocks() {
+        let content = format!(
+            "{h}\n{b}\nold\n{e}\nauth include system-auth\n{b}\nolder\n{e}\n",
+            h = PAM_HEADER,
+            b = BEGIN_MARK,
+            e = END_MARK
+        );
+        assert_eq!(
+            remove_fenced_blocks(&content),
+            format!("{}\nauth include system-auth\n", PAM_HEADER)
+        );
+    }
+
+    #[test]
+    fn remove_handles_nested_begin_markers() {
+        let content = format!(
+            "{h}\n{b}\nouter\n{b}\ninner\n{e}\nauth include system-auth\n",
+            h = PAM_HEADER,
+            b = BEGIN_MARK,
+            e = END_MARK
+        );
+        assert_eq!(
+            remove_fenced_blocks(&content),
+            format!("{}\nauth include system-auth\n", PAM_HEADER)
+        );
+    }
+
+    #[test]
+    fn remove_keeps_content_after_unterminated_begin() {
+        let content = format!(
+            "{h}\n{b}\nold\n{e}\n{b}\nauth include system-auth\naccount include system-auth\n",
+            h = PAM_HEADER,
+            b = BEGIN_MARK,
+            e = END_MARK
+        );
+        assert_eq!(
+            remove_fenced_blocks(&content),
+            format!(
+                "{}\nauth include system-auth\naccount include system-auth\n",
+                PAM_HEADER
+            )
+        );
+    }
+
+    #[test]
+    fn remove_matches_indented_markers() {
+        let content = format!(
+            "{}\n  {}\n{}\n\t{}\nauth include system-auth\n",
+            PAM_HEADER, BEGIN_MARK, BLOCK, END_MARK
+        );
+        assert_eq!(
+            remove_fenced_blocks(&content),
+            format!("{}\nauth include system-auth\n", PAM_HEADER)
+        );
+    }
+
+    #[test]
+    fn audit_flags_duplicate_and_unbalanced_markers() {
+        let audit = |content: String| {
+            let audit = audit_blocks("/etc/pam.d/sudo", &content);
+            (
+                audit.blocks,
+                audit.unterminated,
+                audit.stray_ends,
+                audit.malformed,
+            )
+        };
+        let (b, e) = (BEGIN_MARK, END_MARK);
+
+        assert_eq!(
+            audit(insert_block_after_header(String::new(), BLOCK)),
+            (1, 0, 0, false)
+        );
+        assert_eq!(
+            audit(format!("{b}\nold\n{e}\n{b}\nolder\n{e}\n")),
+            (2, 0, 0, true)
+        );
+        assert_eq!(
+            audit(format!("{b}\nouter\n{b}\ninner\n{e}\n")),
+            (1, 1, 0, true)
+        );
+        assert_eq!(audit(format!("{b}\nold\n")), (0, 1, 0, true));
+        assert_eq!(audit(format!("{}\n{e}\n", PAM_HEADER)), (0, 0, 1, true));
+        assert_eq!(
+            audit(format!("{}\nauth include system-auth\n", PAM_HEADER)),
+            (0, 0, 0, false)
+        );
+    }
+
+    #[test]
+    fn find_auth_includes_detects_include_and_substack() {
+        let content = format!(
+            "{}\nauth include system-auth\n-auth substack system-login\naccount include system-auth\n# auth include commented\n",
+            PAM_HEADER
+        );
+        assert_eq!(
+            find_auth_includes(&content),
+            vec!["system-auth".to_string(), "system-login".to_string()]
+        );
+    }
+
+    #[test]
+    fn find_auth_includes_detects_debian_style_include() {
+        let content = "@include common-auth\nauth required pam_unix.so\n";
+        assert_eq!(find_auth_includes(content), vec!["common-auth".to_string()]);
+    }
+
+    #[test]
+    fn apply_remove_apply_is_idempotent() {
+        let base = format!("{}\nauth include system-auth\n", PAM_HEADER);
+
+        let applied = apply(&base);
+        let removed = remove_fenced_blocks(&applied);
+        let reapplied = apply(&removed);
+
+        assert_eq!(removed, base);
+        assert_eq!(reapplied, applied);
+    }
+
+    #[test]
+    fn default_candidates_probe_vendor_dirs_after_given_path() {
+        let candidates = default_candidates("/usr/lib/pam.d/polkit-1");
+        assert_eq!(candidates[0], PathBuf::from("/usr/lib/pam.d/polkit-1"));
+        assert!(candidates.contains(&PathBuf::from("/usr/share/pam.d/polkit-1")));
+        assert!(candidates.contains(&PathBuf::from("/etc/pam.d/polkit-1")));
+        assert_eq!(
+            candidates
+                .iter()
+                .filter(|c| c.as_path() == Path::new("/usr/lib/pam.d/polkit-1"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn mac_hint_names_the_enforcing_layer() {
+        let selinux = mac_hint(Some("1"), None).unwrap();
+        assert!(selinux.contains("SELinux"), "{}", selinux);
+
+        let apparmor = mac_hint(Some("0"), Some("xfprintd-gui-helper (enforce)")).unwrap();
+        assert!(apparmor.contains("'xfprintd-gui-helper'"), "{}", apparmor);
+
+        assert_eq!(mac_hint(Some("0"), Some("unconfined")), None);
+        assert_eq!(mac_hint(None, Some("xfprintd-gui-helper (complain)")), None);
+        assert_eq!(mac_hint(None, None), None);
+    }
+
+    #[test]
+    fn read_only_errors_name_the_path() {
+        let path = Path::new("/etc/pam.d/sudo");
+        let err = explain_read_only(path, io::Error::from_raw_os_error(libc::EROFS));
+        assert_eq!(err.kind(), io::ErrorKind::ReadOnlyFilesystem);
+        let message = err.to_string();
+        assert!(message.starts_with("/etc/pam.d/sudo is on a read-only filesystem"));
+        assert!(message.contains("Hint: /etc/pam.d is mounted read-only"));
+
+        let other = explain_read_only(path, io::Error::from_raw_os_error(libc::ENOSPC));
+        assert_eq!(other.kind(), io::ErrorKind::StorageFull);
+    }
+
+    #[test]
+    fn atomic_write_refuses_symlinked_targets() {
+        let dir = std::env::temp_dir().join(format!("xfprintd-gui-symlink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("system-auth");
+        let link = dir.join("sudo");
+        fs::write(&real, "#%PAM-1.0\n").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let err = atomic_write(&link, b"new\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("is a symlink to"));
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "#%PAM-1.0\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_write_preserves_mode_and_ownership() {
+        let dir = std::env::temp_dir().join(format!("xfprintd-gui-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sudo");
+
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let before = fs::metadata(&path).unwrap();
+
+        atomic_write(&path, b"new\n").unwrap();
+        let after = fs::metadata(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(after.permissions().mode() & 0o7777, 0o600);
+        assert_eq!(after.uid(), before.uid());
+        assert_eq!(after.gid(), before.gid());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pam_syntax_errors_reports_only_invalid_rules() {
+        let content = "#%PAM-1.0\n\
+            # comment\n\
+            auth sufficient pam_fprintd.so\n\
+            -auth optional pam_gnome_keyring.so\n\
+            auth [success=1 default=ignore] pam_succeed_if.so uid > 0\n\
+            auth include system-auth\n\
+            @include common-auth\n\
+            auth sufficient \\\n  pam_unix.so\n\
+            this is not pam\n\
+            auth maybe pam_unix.so\n";
+        assert_eq!(
+            pam_syntax_errors(content),
+            vec!["this is not pam", "auth maybe pam_unix.so"]
+        );
+    }
+
+    #[test]
+    fn apply_patch_skips_write_when_already_configured() {
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-unchanged-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sudo");
+        fs::write(&path, "#%PAM-1.0\nauth include system-auth\n").unwrap();
+
+        assert!(apply_patch(&path, None, BLOCK).unwrap());
+        let applied = fs::read_to_string(&path).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        assert!(!apply_patch(&path, None, BLOCK).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), applied);
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_patch_rolls_back_invalid_patch() {
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-rollback-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sudo");
+        let original = "#%PAM-1.0\nauth include system-auth\n";
+        fs::write(&path, original).unwrap();
+
+        let err = apply_patch(&path, None, "this is not pam").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(apply_failure(&err), ApplyFailure::RolledBack);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        // Content that can't be read is not a rollback, nothing was written
+        let binary = dir.join("binary");
+        fs::write(&binary, b"#%PAM-1.0\n\xff\xfe\n").unwrap();
+        let err = apply_patch(&binary, None, "auth sufficient pam_fprintd.so").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(apply_failure(&err), ApplyFailure::WriteFailed);
+
+        assert!(apply_patch(&path, None, "auth sufficient pam_fprintd.so").unwrap());
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("auth sufficient pam_fprintd.so"));
+
+        let missing = dir.join("missing");
+        assert!(apply_patch(&missing, None, "this is not pam").is_err());
+        assert!(!missing.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commit_all_writes_every_file_or_none() {
+        let dir = std::env::temp_dir().join(format!("xfprintd-gui-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sudo = dir.join("sudo");
+        let polkit = dir.join("polkit-1");
+        let login = dir.join("login");
+        let original = "#%PAM-1.0\nauth include system-auth\n";
+        fs::write(&sudo, original).unwrap();
+        fs::write(dir.join("system-login"), original).unwrap();
+        std::os::unix::fs::symlink(dir.join("system-login"), &login).unwrap();
+
+        let plan = |path: &Path| plan_patch(path, None, BLOCK).unwrap().unwrap();
+        let (sudo_plan, polkit_plan, login_plan) = (plan(&sudo), plan(&polkit), plan(&login));
+
+        // The symlink is refused while staging, before anything is renamed
+        let (failed, err, not_restored) =
+            commit_all(&[&sudo_plan, &polkit_plan, &login_plan]).unwrap_err();
+        assert_eq!(failed, login);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(not_restored.is_empty());
+        assert_eq!(fs::read_to_string(&sudo).unwrap(), original);
+        assert!(!polkit.exists());
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        commit_all(&[&sudo_plan, &polkit_plan]).unwrap();
+        assert!(fs::read_to_string(&sudo).unwrap().contains(BLOCK));
+        assert!(fs::read_to_string(&polkit).unwrap().contains(BLOCK));
+
+        polkit_plan.restore().unwrap();
+        assert!(!polkit.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plan_patch_leaves_configured_files_alone() {
+        let dir = std::env::temp_dir().join(format!("xfprintd-gui-plan-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sudo");
+        fs::write(&path, "#%PAM-1.0\n").unwrap();
+
+        let plan = plan_patch(&path, None, BLOCK).unwrap().unwrap();
+        assert_eq!(plan.backup.as_deref(), Some(&b"#%PAM-1.0\n"[..]));
+        assert!(introduced_errors(&plan.base_content, &plan.content).is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#%PAM-1.0\n");
+
+        fs::write(&path, &plan.content).unwrap();
+        assert!(plan_patch(&path, None, BLOCK).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_patch_refuses_existing_fprintd_rule() {
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-conflict-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sudo");
+
+        let original = "#%PAM-1.0\nauth sufficient /usr/lib/security/pam_fprintd.so\nauth include system-auth\n";
+        fs::write(&path, original).unwrap();
+        let err = apply_patch(&path, None, BLOCK).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        // Our own block and commented out rules are not conflicts
+        fs::write(
+            &path,
+            "#%PAM-1.0\n# auth sufficient pam_fprintd.so\nauth include system-auth\n",
+        )
+        .unwrap();
+        assert!(apply_patch(&path, None, BLOCK).unwrap());
+        assert!(!apply_patch(&path, None, BLOCK).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn allowlist_rejects_traversal_symlinks_and_trailing_slashes() {
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-allowlist-{}", std::process::id()));
+        let pam_dir = dir.join("pam.d");
+        fs::create_dir_all(pam_dir.join("nested")).unwrap();
+        fs::write(pam_dir.join("sudo"), "#%PAM-1.0\n").unwrap();
+        fs::write(dir.join("shadow"), "secret\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("shadow"), pam_dir.join("link")).unwrap();
+
+        let allowed = [pam_dir.to_str().unwrap()];
+        let check = |path: &str| is_allowlisted_in(Path::new(path), &allowed);
+        let pam = pam_dir.to_str().unwrap();
+
+        assert!(check(&format!("{}/sudo", pam)));
+        assert!(check(&format!("{}/polkit-1", pam)));
+        assert!(!check(&format!("{}/../shadow", pam)));
+        assert!(!check(&format!("{}/../pam.d/sudo", pam)));
+        assert!(!check(&format!("{}/./sudo", pam)));
+        assert!(!check(&format!("{}/nested/sudo", pam)));
+        assert!(!check(&format!("{}/link", pam)));
+        assert!(!check(&format!("{}/sudo/", pam)));
+        assert!(!check(&format!("{}/", pam)));
+        assert!(!check(pam));
+        assert!(!check(&format!("{}-evil/sudo", pam)));
+        assert!(!check("pam.d/sudo"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn is_fprintd_prompt_matches_finger_prompts_only() {
+        assert!(is_fprintd_prompt(
+            "Place your right index finger on the fingerprint reader\n"
+        ));
+        assert!(is_fprintd_prompt(
+            "Swipe your left thumb across the fingerprint reader"
+        ));
+        assert!(is_fprintd_prompt(
+            "pamtester: invoking pam_start(sudo, alice, ...)\nPlace your finger on Goodix MOC"
+        ));
+        assert!(!is_fprintd_prompt("Password: "));
+        assert!(!is_fprintd_prompt(
+            "pamtester: invoking pam_start(gdm-fingerprint, alice, ...)\n\
+             pamtester: Authentication failure"
+        ));
+        assert!(!is_fprintd_prompt("No fingers enrolled for this device"));
+    }
+
+    #[test]
+    fn user_name_of_reads_the_password_database() {
+        assert_eq!(user_name_of(0).as_deref(), Some("root"));
+        assert_eq!(user_name_of(libc::uid_t::MAX - 1), None);
+    }
+
+    #[test]
+    fn patches_dir_override_is_read_and_checked() {
+        let cli = Cli::try_parse_from([
+            "xfprintd-gui-helper",
+            "show",
+            "/etc/pam.d/sudo",
+            "--patches-dir",
+            "/tmp/patches",
+        ])
+        .unwrap();
+        assert_eq!(cli.patches_dir, Some(PathBuf::from("/tmp/patches")));
+        assert_eq!(
+            resolve_patches_dir(cli.patches_dir.clone(), false),
+            Ok(PathBuf::from("/tmp/patches"))
+        );
+        assert!(resolve_patches_dir(cli.patches_dir, true).is_err());
+        assert_eq!(
+            resolve_patches_dir(None, true),
+            Ok(PathBuf::from(PATCHES_BASE_DIR))
+        );
+
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-patches-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("etc/pam.d")).unwrap();
+        let patch_path = dir.join("etc/pam.d/sudo.patch");
+        fs::write(&patch_path, "auth sufficient pam_fprintd.so\n").unwrap();
+        fs::set_permissions(&patch_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert_eq!(
+            read_patch_content("/etc/pam.d/sudo", &dir).unwrap(),
+            "auth sufficient pam_fprintd.so"
+        );
+
+        fs::set_permissions(&patch_path, fs::Permissions::from_mode(0o666)).unwrap();
+        assert_eq!(
+            read_patch_content("/etc/pam.d/sudo", &dir)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn user_patch_override_takes_precedence() {
+        let dir =
+            std::env::temp_dir().join(format!("xfprintd-gui-override-{}", std::process::id()));
+        let base = dir.join("system");
+        let user = dir.join("user");
+        fs::create_dir_all(base.join("etc/pam.d")).unwrap();
+        fs::create_dir_all(user.join("etc/pam.d")).unwrap();
+        let uid = unsafe { libc::getuid() };
+
+        fs::write(
+            base.join("etc/pam.d/sudo.patch"),
+            "auth sufficient pam_fprintd.so\n",
+        )
+        .unwrap();
+        let read = |override_dir: Option<&Path>| {
+            read_patch_with_override(override_dir, &base, "/etc/pam.d/sudo", uid)
+        };
+        assert_eq!(read(Some(&user)).unwrap(), "auth sufficient pam_fprintd.so");
+
+        let override_path = user.join("etc/pam.d/sudo.patch");
+        fs::write(
+            &override_path,
+            "auth sufficient pam_fprintd.so timeout=10\n",
+        )
+        .unwrap();
+        fs::set_permissions(&override_path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            read(Some(&user)).unwrap(),
+            "auth sufficient pam_fprintd.so timeout=10"
+        );
+        assert_eq!(read(None).unwrap(), "auth sufficient pam_fprintd.so");
+
+        fs::set_permissions(&override_path, fs::Permissions::from_mode(0o666)).unwrap();
+        assert_eq!(
+            read(Some(&user)).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn user_patch_override_is_limited_to_fprintd_rules() {
+        let dir = std::env::temp_dir().join(format!(
+            "xfprintd-gui-override-content-{}",
+            std::process::id()
+        ));
+        let base = dir.join("system");
+        let user = dir.join("user");
+        fs::create_dir_all(base.join("etc/pam.d")).unwrap();
+        fs::create_dir_all(user.join("etc/pam.d")).unwrap();
+        let uid = unsafe { libc::getuid() };
+        let read = |target: &str| read_patch_with_override(Some(&user), &base, target, uid);
+
+        fs::write(base.join("etc/pam.d/sudo.patch"), format!("{}\n", BLOCK)).unwrap();
+        for (content, allowed) in [
+            ("auth required pam_fprintd.so max-tries=2\n", true),
+            ("auth sufficient pam_permit.so\n", false),
+            (
+                "auth sufficient pam_fprintd.so\nauth sufficient pam_permit.so\n",
+                false,
+            ),
+            ("auth sufficient /home/user/pam_fprintd.so\n", false),
+            ("auth [success=done default=ignore] pam_fprintd.so\n", false),
+            ("account sufficient pam_fprintd.so\n", false),
+        ] {
+            fs::write(user.join("etc/pam.d/sudo.patch"), content).unwrap();
+            match read("/etc/pam.d/sudo") {
+                Ok(patch) => {
+                    assert!(allowed, "accepted {:?}", content);
+                    assert_eq!(patch, content.trim_end());
+                }
+                Err(e) => {
+                    assert!(!allowed, "refused {:?}: {}", content, e);
+                    assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                }
+            }
+        }
+
+        // An override for a service without a packaged patch does not add it
+        fs::write(user.join("etc/pam.d/su.patch"), format!("{}\n", BLOCK)).unwrap();
+        assert_eq!(
+            read("/etc/pam.d/su").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validate_patch_rejects_empty_fenced_and_non_rule_content() {
+        assert!(validate_patch_content(&format!("# fprintd first\n{}\n", BLOCK)).is_ok());
+        assert!(validate_patch_content("").is_err());
+        assert!(validate_patch_content("\n# only a comment\n").is_err());
+        assert!(validate_patch_content(&create_fenced_block(BLOCK)).is_err());
+        assert!(validate_patch_content(&format!("{}\n  {}\n", BLOCK, END_MARK)).is_err());
+        assert!(validate_patch_content(&format!("{}\n@include common-auth\n", BLOCK)).is_err());
+        assert!(validate_patch_content(&format!("{}\nrm -rf /\n", BLOCK)).is_err());
+    }
+
+    #[test]
+    fn bundled_patches_are_valid() {
+        for patch in [
+            include_str!("../patches/etc/pam.d/login.patch"),
+            include_str!("../patches/etc/pam.d/polkit-1.patch"),
+            include_str!("../patches/etc/pam.d/sddm.patch"),
+            include_str!("../patches/etc/pam.d/sudo.patch"),
+        ] {
+            assert_eq!(validate_patch_content(patch), Ok(()));
+        }
+    }
+
+    #[test]
+    fn missing_pam_module_reports_first_unknown_module() {
+        let patch = "auth [success=1 default=ignore] pam_succeed_if.so service in sudo\n\
+                     auth sufficient pam_fprintd.so\n\
+                     auth optional /opt/pam_extra.so arg.so\n";
+        let installed =
+            |known: &'static [&'static str]| move |module: &str| known.contains(&module);
+
+        assert_eq!(
+            missing_pam_module(patch, installed(&["pam_succeed_if.so"])),
+            Some("pam_fprintd.so".to_string())
+        );
+        assert_eq!(
+            missing_pam_module(patch, installed(&["pam_succeed_if.so", "pam_fprintd.so"])),
+            Some("/opt/pam_extra.so".to_string())
+        );
+        assert_eq!(
+            missing_pam_module(
+                patch,
+                installed(&["pam_succeed_if.so", "pam_fprintd.so", "/opt/pam_extra.so"])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn apply_failures_have_distinct_reserved_free_exit_codes() {
+        let codes: Vec<i32> = [
+            ApplyFailure::NotAllowlisted,
+            ApplyFailure::PatchMissing,
+            ApplyFailure::PatchRejected,
+            ApplyFailure::ModuleMissing,
+            ApplyFailure::WriteFailed,
+            ApplyFailure::RolledBack,
+            ApplyFailure::Conflict,
+            ApplyFailure::ReadOnly,
+        ]
+        .into_iter()
+        .map(ApplyFailure::exit_code)
+        .collect();
+
+        for (i, code) in codes.iter().enumerate() {
+            assert!(![0, 1, 2, 126, 127].contains(code));
+            assert!(!codes[i + 1..].contains(code));
+        }
+    }
+
+    #[test]
+    fn apply_config_reports_non_allowlisted_target() {
+        let target = TargetConfig::new("/tmp/sudo".to_string());
+        let err = apply_config(&target, Path::new(PATCHES_BASE_DIR)).unwrap_err();
+        assert_eq!(err.failure, ApplyFailure::NotAllowlisted);
+        assert_eq!(err.failure.exit_code(), 3);
+    }
+
+    #[test]
+    fn check_status_serializes_as_path_and_applied() {
+        let statuses = vec![
+            CheckStatus {
+                path: "/etc/pam.d/sudo".to_string(),
+                applied: true,
+            },
+            CheckStatus {
+                path: "/etc/pam.d/polkit-1".to_string(),
+                applied: false,
+            },
+        ];
+        assert_eq!(
+            serde_json::to_string(&statuses).unwrap(),
+            r#"[{"path":"/etc/pam.d/sudo","applied":true},{"path":"/etc/pam.d/polkit-1","applied":false}]"#
+        );
+    }
+
+    #[test]
+    fn services_lists_allowlisted_patches_once() {
+        let root =
+            std::env::temp_dir().join(format!("xfprintd-gui-services-{}", std::process::id()));
+        let pam_dir = root.join("pam.d");
+        let system = root.join("system");
+        let user = root.join("user");
+        fs::create_dir_all(&pam_dir).unwrap();
+        let pam_dir_str = pam_dir.to_str().unwrap();
+        let relative = pam_dir_str.trim_start_matches('/');
+
+        for (dir, name) in [
+            (&system, "sudo.patch"),
+            (&system, "polkit-1.patch"),
+            (&system, "README"),
+            (&user, "sudo.patch"),
+            (&user, "kde.patch"),
+        ] {
+            fs::create_dir_all(dir.join(relative)).unwrap();
+            fs::write(dir.join(relative).join(name), format!("{}\n", BLOCK)).unwrap();
+        }
+        // Patches outside the allowlisted directories are not services
+        fs::create_dir_all(system.join("etc/security")).unwrap();
+        fs::write(system.join("etc/security/limits.patch"), BLOCK).unwrap();
+
+        let services =
+            managed_services_in(&[system.as_path(), user.as_path()], &[pam_dir_str]).unwrap();
+        let listed: Vec<(&str, String)> = services
+            .iter()
+            .map(|service| (service.service.as_str(), service.path.clone()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("kde", format!("{}/kde", pam_dir_str)),
+                ("polkit-1", format!("{}/polkit-1", pam_dir_str)),
+                ("sudo", format!("{}/sudo", pam_dir_str)),
+            ]
+        );
+
+        let missing = root.join("missing");
+        assert!(managed_services_in(&[missing.as_path()], &[pam_dir_str])
+            .unwrap()
+            .is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn managed_service_serializes_as_service_and_path() {
+        let service = ManagedService {
+            service: "sudo".to_string(),
+            path: "/etc/pam.d/sudo".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&[service]).unwrap(),
+            r#"[{"service":"sudo","path":"/etc/pam.d/sudo"}]"#
+        );
+    }
+
+    #[test]
+    fn version_line_is_name_and_semver() {
+        let line = version_line();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0], "xfprintd-gui-helper");
+
+        let parts: Vec<&str> = words[1].split('.').collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.parse::<u64>().is_ok()));
     }
 }